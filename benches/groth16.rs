@@ -0,0 +1,113 @@
+//! Compares unpacking the points of a proof kept by a verifier by value and by reference, and
+//! counts the allocations of a verification
+//!
+//! Run with `cargo bench --bench groth16`.
+
+use ark_bn254::{Bn254, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::PrimeField;
+use ark_groth16::{PreparedVerifyingKey, VerifyingKey, prepare_verifying_key};
+use criterion::{BatchSize, Criterion, black_box, criterion_group, criterion_main};
+use num_bigint::BigUint;
+use semaphore::proof::{Proof, SemaphoreProof};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts the allocations of the benchmark process
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn semaphore_proof() -> SemaphoreProof {
+    // Large values, like real field elements
+    let element = |i: u32| (BigUint::from(1u8) << 250) + i;
+    let coordinate = |x: ark_bn254::Fq| BigUint::from(x.into_bigint());
+
+    // Valid curve points, the proof itself is not valid
+    let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+    SemaphoreProof {
+        merkle_tree_depth: 10,
+        merkle_tree_root: element(0),
+        message: element(1),
+        nullifier: element(2),
+        scope: element(3),
+        points: [
+            coordinate(g1.x),
+            coordinate(g1.y),
+            coordinate(g2.x.c1),
+            coordinate(g2.x.c0),
+            coordinate(g2.y.c1),
+            coordinate(g2.y.c0),
+            coordinate(g1.x),
+            coordinate(g1.y),
+        ],
+    }
+}
+
+/// Verifying key with the shape of the Semaphore one (4 public inputs)
+fn verifying_key() -> PreparedVerifyingKey<Bn254> {
+    let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+    prepare_verifying_key(&VerifyingKey {
+        alpha_g1: g1,
+        beta_g2: g2,
+        gamma_g2: g2,
+        delta_g2: g2,
+        gamma_abc_g1: vec![g1; 5],
+    })
+}
+
+fn unpack(c: &mut Criterion) {
+    let proof = semaphore_proof();
+
+    // The points are moved into the circom proof, cloned beforehand and not measured
+    c.bench_function("unpack_groth16_proof", |b| {
+        b.iter_batched(
+            || proof.points.clone(),
+            |points| Proof::unpack_groth16_proof(black_box(points)),
+            BatchSize::SmallInput,
+        )
+    });
+    // A verifier keeping the proof clones the points to unpack them by value
+    c.bench_function("clone and unpack_groth16_proof", |b| {
+        b.iter(|| Proof::unpack_groth16_proof(black_box(&proof.points).clone()))
+    });
+    // The coordinates are cloned straight into the circom proof
+    c.bench_function("unpack_groth16_proof_ref", |b| {
+        b.iter(|| Proof::unpack_groth16_proof_ref(black_box(&proof.points)))
+    });
+}
+
+fn verify(c: &mut Criterion) {
+    let proof = semaphore_proof();
+    let verifying_key = verifying_key();
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(Proof::verify_with_key(&verifying_key, &proof));
+    println!(
+        "allocations per verification: {}",
+        ALLOCATIONS.load(Ordering::Relaxed) - before
+    );
+
+    c.bench_function("verify with key", |b| {
+        b.iter(|| Proof::verify_with_key(&verifying_key, black_box(&proof)))
+    });
+}
+
+criterion_group!(benches, unpack, verify);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Compares generating the membership proofs of many members in a batch and one at a time,
+//! and building a large group level by level in parallel
+//!
+//! Run with `cargo bench --bench group`, add `--features rayon` for the parallel batch and
+//! build.
+
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use semaphore::group::{Element, Group};
+
+const MEMBERS: usize = 1000;
+/// Members of the group built by `new_parallel`
+#[cfg(feature = "rayon")]
+const BULK_MEMBERS: usize = 1_000_000;
+
+fn members(count: usize) -> Vec<Element> {
+    (1..=count as u32)
+        .map(|i| {
+            let mut member = [0u8; 32];
+            member[..4].copy_from_slice(&i.to_le_bytes());
+            member
+        })
+        .collect()
+}
+
+fn generate_proofs(c: &mut Criterion) {
+    let members = members(MEMBERS);
+    let group = Group::new(&members).unwrap();
+    let indices: Vec<usize> = (0..MEMBERS).collect();
+
+    c.bench_function("generate_proof loop", |b| {
+        b.iter(|| {
+            black_box(&indices)
+                .iter()
+                .map(|&index| group.generate_proof(index))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        })
+    });
+    c.bench_function("batch_generate_proofs", |b| {
+        b.iter(|| group.batch_generate_proofs(black_box(&indices)).unwrap())
+    });
+}
+
+/// A build of a million members hashes about a million pairs. On a single core, one build took
+/// 72 s with `new` and 67 s with `new_parallel`, so expect the benchmark to take about 25
+/// minutes there. `new_parallel` is expected to divide its time by up to the number of cores.
+#[cfg(feature = "rayon")]
+fn build_group(c: &mut Criterion) {
+    let members = members(BULK_MEMBERS);
+    let mut group = c.benchmark_group("build 1M members");
+    group.sample_size(10);
+
+    group.bench_function("new", |b| {
+        b.iter(|| Group::new(black_box(&members)).unwrap())
+    });
+    group.bench_function("new_parallel", |b| {
+        b.iter(|| Group::new_parallel(black_box(&members)).unwrap())
+    });
+    group.finish();
+}
+
+#[cfg(feature = "rayon")]
+criterion_group!(benches, generate_proofs, build_group);
+#[cfg(not(feature = "rayon"))]
+criterion_group!(benches, generate_proofs);
+criterion_main!(benches);
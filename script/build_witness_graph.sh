@@ -37,4 +37,9 @@ cd $CURRENT_DIR/$CIRCOM_WITNESSCALC_DIR
 for depth in {1..32}; do
     cargo run --package build-circuit --bin build-circuit --release $CURRENT_DIR/$SEMAPHORE_DIR/semaphore-${depth}.circom $CURRENT_DIR/$WITNESS_GRAPH_DIR/semaphore-${depth}.bin -l $CURRENT_DIR/$SEMAPHORE_DIR/node_modules/@zk-kit/binary-merkle-root.circom/src -l $CURRENT_DIR/$SEMAPHORE_DIR/node_modules/circomlib/circuits -l $CURRENT_DIR/$SEMAPHORE_DIR/packages/circuits/src
 done
+# compress the witness graphs, they are embedded compressed in the crate
+cd $CURRENT_DIR/$WITNESS_GRAPH_DIR
+for depth in {1..32}; do
+    zstd -19 -f --rm semaphore-${depth}.bin -o semaphore-${depth}.bin.zst
+done
 # save the witness graph to the witness_graph directory
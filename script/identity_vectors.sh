@@ -0,0 +1,55 @@
+#! /bin/bash
+
+# Generates tests/fixtures/identity_vectors.json with the semaphore-js Identity
+#
+# Private keys are hex encoded bytes, covering edge-case lengths.
+
+FIXTURE="$(pwd)/tests/fixtures/identity_vectors.json"
+WORK_DIR=$(mktemp -d)
+
+cd $WORK_DIR
+npm init -y > /dev/null
+npm install @semaphore-protocol/identity@4 > /dev/null
+
+cat > vectors.mjs <<'JS'
+import { Identity } from "@semaphore-protocol/identity"
+
+const keys = [
+    Buffer.from("privateKey"),
+    Buffer.from("secret"),
+    Buffer.from("a"),
+    Buffer.from([0]),
+    Buffer.from([255]),
+    Buffer.alloc(31, 1),
+    Buffer.alloc(32, 0),
+    Buffer.alloc(32, 255),
+    Buffer.alloc(33, 7),
+    Buffer.alloc(63, 42),
+    Buffer.alloc(64, 42),
+    Buffer.alloc(65, 42),
+    Buffer.alloc(128, 3),
+    Buffer.alloc(1000, 9),
+    Buffer.from("héllo wörld"),
+    Buffer.from("🔑"),
+    Buffer.from(" "),
+    Buffer.from("0x1234"),
+    Buffer.from("0123456789abcdef0123456789abcdef"),
+    Buffer.from("a much longer passphrase used as a private key by some apps")
+]
+
+const vectors = keys.map((privateKey) => {
+    const identity = new Identity(privateKey)
+
+    return {
+        privateKey: privateKey.toString("hex"),
+        secretScalar: identity.secretScalar.toString(),
+        publicKey: identity.publicKey.map((c) => c.toString()),
+        commitment: identity.commitment.toString()
+    }
+})
+
+console.log(JSON.stringify(vectors, null, 2))
+JS
+
+node vectors.mjs > $FIXTURE
+rm -rf $WORK_DIR
@@ -12,19 +12,20 @@
 //! - Curve equation: ax² + y² = 1 + d·x²y², where
 //!    - a = 168700
 //!    - d = 168696
-//! - Generator point:
+//! - Generator point, of order `n`, see [`curve_generator`]:
 //!   (995203441582195749578291179787384436505546430278305826713579947235728471134,
 //!   5472060717959818805561601436314318772137091100104008585924551046643952123905)
-//! - Base point:
+//! - Base point, `8 * generator` of order `l`, see [`subgroup_generator`]:
 //!   (5299619240641551281634865583518297030282874472190772894086521144482721001553,
 //!   16950150798460657717958625567821834550301663161624707787222815936182638968203)
 
 use ark_ec::{
+    AffineRepr, CurveGroup,
     models::CurveConfig,
     twisted_edwards::{Affine, MontCurveConfig, Projective, TECurveConfig},
 };
 use ark_ed_on_bn254::{Fq, Fr};
-use ark_ff::{Field, MontFp};
+use ark_ff::{BigInteger, Field, MontFp, PrimeField};
 
 pub type EdwardsAffine = Affine<BabyJubjubConfig>;
 pub type EdwardsProjective = Projective<BabyJubjubConfig>;
@@ -96,12 +97,91 @@ pub const BASE_X: Fq =
 pub const BASE_Y: Fq =
     MontFp!("16950150798460657717958625567821834550301663161624707787222815936182638968203");
 
+/// Returns the EIP-2494 generator point, which generates the whole curve group (order `n`)
+///
+/// Not the point keys are computed with: use [`subgroup_generator`] for secret scalars.
+pub fn curve_generator() -> EdwardsAffine {
+    EdwardsAffine::new_unchecked(GENERATOR_X, GENERATOR_Y)
+}
+
+/// Returns the EIP-2494 base point, which generates the prime-order subgroup (order `l`)
+///
+/// This is `8 * curve_generator()` and `BabyJubjubConfig::GENERATOR`: [`PublicKey`] and
+/// [`Signature`] multiply secret scalars with it, like circomlibjs `Base8`.
+///
+/// [`PublicKey`]: crate::identity::PublicKey
+/// [`Signature`]: crate::identity::Signature
+pub fn subgroup_generator() -> EdwardsAffine {
+    BabyJubjubConfig::GENERATOR
+}
+
+/// Multiplies a point by the cofactor, mapping it into the prime-order subgroup
+pub fn clear_cofactor(p: &EdwardsAffine) -> EdwardsAffine {
+    p.mul_by_cofactor_to_group().into_affine()
+}
+
+/// Prunes a 32-byte little-endian scalar like the secret scalar derivation
+///
+/// Clears the 3 lowest bits (a multiple of the cofactor) and the highest bit, and sets the
+/// second highest bit.
+pub fn clamp_scalar(bytes: &mut [u8; 32]) {
+    bytes[0] &= 0xF8;
+    bytes[31] &= 0x7F;
+    bytes[31] |= 0x40;
+}
+
+/// Packs a point into 32 bytes, compatible with circomlibjs `packPoint`
+///
+/// The y coordinate is stored in little-endian and the sign of x in the most significant bit.
+pub fn pack_point(point: &EdwardsAffine) -> [u8; 32] {
+    let mut packed = [0u8; 32];
+    let y = point.y.into_bigint().to_bytes_le();
+    packed[..y.len()].copy_from_slice(&y);
+
+    if point.x.into_bigint() > Fq::MODULUS_MINUS_ONE_DIV_TWO {
+        packed[31] |= 0x80;
+    }
+
+    packed
+}
+
+/// Unpacks a point packed with [`pack_point`], returns `None` if it is not a valid curve point
+///
+/// The point can be outside the prime-order subgroup, like with circomlibjs `unpackPoint`:
+/// check `is_in_correct_subgroup_assuming_on_curve` before using it as a key.
+pub fn unpack_point(packed: &[u8; 32]) -> Option<EdwardsAffine> {
+    let mut y_bytes = *packed;
+    let sign = y_bytes[31] & 0x80 != 0;
+    y_bytes[31] &= 0x7F;
+
+    // Reject non-canonical y coordinates
+    let y = Fq::from_le_bytes_mod_order(&y_bytes);
+    if y.into_bigint().to_bytes_le() != y_bytes {
+        return None;
+    }
+
+    // x^2 = (1 - y^2) / (a - d * y^2)
+    let y2 = y.square();
+    let denominator = <BabyJubjubConfig as TECurveConfig>::COEFF_A
+        - <BabyJubjubConfig as TECurveConfig>::COEFF_D * y2;
+    let mut x = ((Fq::ONE - y2) * denominator.inverse()?).sqrt()?;
+
+    if x.into_bigint() > Fq::MODULUS_MINUS_ONE_DIV_TWO {
+        x = -x;
+    }
+    if sign {
+        x = -x;
+    }
+
+    let point = EdwardsAffine::new_unchecked(x, y);
+    point.is_on_curve().then_some(point)
+}
+
 #[cfg(test)]
 mod tests {
     //! Implementation of the tests presented in the EIP-2494
 
     use super::*;
-    use ark_ec::CurveGroup;
     use ark_ff::{PrimeField, Zero};
 
     #[test]
@@ -189,6 +269,74 @@ mod tests {
         assert_eq!(calculated_base_point, expected_base_point);
     }
 
+    #[test]
+    fn test_curve_and_subgroup_generators() {
+        let cofactor = Fr::from(BabyJubjubConfig::COFACTOR[0]);
+        assert_eq!(
+            (curve_generator() * cofactor).into_affine(),
+            subgroup_generator()
+        );
+        assert_eq!(subgroup_generator(), BabyJubjubConfig::GENERATOR);
+        assert_eq!(
+            subgroup_generator(),
+            EdwardsAffine::new_unchecked(BASE_X, BASE_Y)
+        );
+
+        assert!(subgroup_generator().is_in_correct_subgroup_assuming_on_curve());
+        assert!(!curve_generator().is_in_correct_subgroup_assuming_on_curve());
+        assert!(curve_generator().is_on_curve());
+    }
+
+    #[test]
+    fn test_clear_cofactor() {
+        let g = EdwardsAffine::new_unchecked(GENERATOR_X, GENERATOR_Y);
+        let base_point = EdwardsAffine::new_unchecked(BASE_X, BASE_Y);
+        assert_eq!(clear_cofactor(&g), base_point);
+        // `SUBGROUP_ORDER` is `l` as a scalar, so zero: check the order with the modulus `l`
+        assert!(!g.mul_bigint(Fr::MODULUS).is_zero());
+        assert!(base_point.mul_bigint(Fr::MODULUS).is_zero());
+
+        let p1 = EdwardsAffine::new_unchecked(
+            MontFp!(
+                "17777552123799933955779906779655732241715742912184938656739573121738514868268"
+            ),
+            MontFp!("2626589144620713026669568689430873010625803728049924121243784502389097019475"),
+        );
+        let cleared = clear_cofactor(&p1);
+        assert!(cleared.is_in_correct_subgroup_assuming_on_curve());
+        assert!(cleared.mul_bigint(Fr::MODULUS).is_zero());
+    }
+
+    #[test]
+    fn test_clamp_scalar() {
+        let mut bytes = [0xFF; 32];
+        clamp_scalar(&mut bytes);
+        assert_eq!(bytes[0], 0xF8);
+        assert_eq!(bytes[31], 0x7F);
+
+        let mut bytes = [0; 32];
+        clamp_scalar(&mut bytes);
+        assert_eq!(bytes[0], 0);
+        assert_eq!(bytes[31], 0x40);
+    }
+
+    #[test]
+    fn test_pack_unpack_point() {
+        let base_point = EdwardsAffine::new_unchecked(BASE_X, BASE_Y);
+        let points = [
+            base_point,
+            -base_point,
+            EdwardsAffine::new_unchecked(Fq::zero(), Fq::ONE),
+        ];
+
+        for point in points {
+            assert_eq!(unpack_point(&pack_point(&point)), Some(point));
+        }
+
+        assert_ne!(pack_point(&base_point), pack_point(&-base_point));
+        assert_eq!(unpack_point(&[0xFF; 32]), None);
+    }
+
     #[test]
     fn test_base_point_order() {
         let base_point = EdwardsAffine::new_unchecked(GENERATOR_X, GENERATOR_Y);
@@ -0,0 +1,176 @@
+//! Semaphore CLI
+//!
+//! Thin wrapper around the library APIs for use from shell scripts.
+//! Groups and proofs are read and written using the JSON formats of `export`/`import`.
+//! Private keys are never taken as arguments, which other processes and the shell history
+//! can read: they come from a file, the `SEMAPHORE_PRIVATE_KEY` variable or the standard input.
+
+use anyhow::{Context, Result, bail};
+use ark_ed_on_bn254::Fq;
+use clap::{Args, Parser, Subcommand};
+use semaphore::{
+    group::Group,
+    identity::Identity,
+    proof::{GroupOrMerkleProof, Proof, SemaphoreProof},
+    utils::to_element,
+};
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// Variable holding the private key when no file is given
+const PRIVATE_KEY_ENV: &str = "SEMAPHORE_PRIVATE_KEY";
+
+#[derive(Parser)]
+#[command(name = "semaphore", version, about = "Semaphore Rust CLI")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Identity commands
+    #[command(subcommand)]
+    Identity(IdentityCommand),
+    /// Group commands
+    #[command(subcommand)]
+    Group(GroupCommand),
+    /// Proof commands
+    #[command(subcommand)]
+    Proof(ProofCommand),
+}
+
+#[derive(Subcommand)]
+enum IdentityCommand {
+    /// Creates an identity from a private key and prints its commitment
+    New {
+        #[command(flatten)]
+        private_key: PrivateKeyArgs,
+    },
+}
+
+#[derive(Args)]
+struct PrivateKeyArgs {
+    /// File holding the private key, read from SEMAPHORE_PRIVATE_KEY or the standard input
+    /// if omitted
+    #[arg(long)]
+    private_key_file: Option<PathBuf>,
+}
+
+impl PrivateKeyArgs {
+    /// Reads the private key, without the line ending a file or a shell adds
+    fn read(&self) -> Result<String> {
+        let private_key = match (&self.private_key_file, std::env::var(PRIVATE_KEY_ENV)) {
+            (Some(path), _) => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+            (None, Ok(private_key)) => private_key,
+            (None, Err(_)) => {
+                let mut private_key = String::new();
+                io::stdin().read_to_string(&mut private_key)?;
+                private_key
+            }
+        };
+
+        let private_key = private_key.trim_end_matches(['\n', '\r']);
+        if private_key.is_empty() {
+            bail!("The private key is empty");
+        }
+        Ok(private_key.to_string())
+    }
+}
+
+#[derive(Subcommand)]
+enum GroupCommand {
+    /// Adds members to a group and prints the exported group
+    Add {
+        /// Exported group to add the members to, an empty group is used if omitted
+        #[arg(long)]
+        group: Option<PathBuf>,
+        /// Identity commitments in decimal format
+        #[arg(required = true)]
+        members: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProofCommand {
+    /// Generates a proof and prints the exported proof
+    Generate {
+        #[command(flatten)]
+        private_key: PrivateKeyArgs,
+        /// Exported group containing the identity
+        #[arg(long)]
+        group: PathBuf,
+        /// Message
+        #[arg(long)]
+        message: String,
+        /// Scope
+        #[arg(long)]
+        scope: String,
+        /// Merkle tree depth
+        #[arg(long)]
+        depth: u16,
+    },
+    /// Verifies an exported proof
+    Verify {
+        /// Exported proof
+        proof: PathBuf,
+    },
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Identity(IdentityCommand::New { private_key }) => {
+            let identity = Identity::new(private_key.read()?.as_bytes());
+            println!("{}", identity.commitment());
+        }
+        Command::Group(GroupCommand::Add { group, members }) => {
+            let mut group = match group {
+                Some(path) => Group::import(&fs::read_to_string(path)?)?,
+                None => Group::default(),
+            };
+            let members = members
+                .iter()
+                .map(|m| {
+                    Fq::from_str(m)
+                        .map(to_element)
+                        .map_err(|_| anyhow::anyhow!("Invalid member: {m}"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            group.add_members(&members)?;
+            println!("{}", group.export()?);
+        }
+        Command::Proof(ProofCommand::Generate {
+            private_key,
+            group,
+            message,
+            scope,
+            depth,
+        }) => {
+            let identity = Identity::new(private_key.read()?.as_bytes());
+            let group = Group::import(&fs::read_to_string(group)?)?;
+            let proof = Proof::default().generate(
+                identity,
+                GroupOrMerkleProof::Group(group),
+                message,
+                scope,
+                depth,
+            )?;
+            println!("{}", proof.export()?);
+        }
+        Command::Proof(ProofCommand::Verify { proof }) => {
+            let proof = SemaphoreProof::import(&fs::read_to_string(proof)?)
+                .context("Failed to import proof")?;
+            if Proof::default().verify_and_extract(&proof)?.is_none() {
+                bail!("Invalid proof");
+            }
+            println!("Valid proof");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+//! Artifacts embedded in the binary with the `bundled-artifacts` feature
+//!
+//! The files of the `artifacts` directory are embedded at build time, with the layout of
+//! `SEMAPHORE_ARTIFACTS_DIR`: `{version}/semaphore-{depth}.{ext}` or `semaphore-{depth}.{ext}`.
+//! Verification keys (`.vkey.json`, a few KB each) are the practical choice: they are all a
+//! verifier needs. Zkeys weigh from a few MB to hundreds of MB per depth and make the binary
+//! as large, so only bundle the depths you prove with.
+
+use crate::proof::parse_verifying_key_json;
+use anyhow::Result;
+use ark_bn254::Bn254;
+use ark_groth16::VerifyingKey;
+use rust_embed::RustEmbed;
+use std::borrow::Cow;
+
+#[derive(RustEmbed)]
+#[cfg_attr(not(test), folder = "artifacts/")]
+#[cfg_attr(test, folder = "tests/fixtures/bundled-artifacts/")]
+#[include = "*.vkey.json"]
+#[include = "*.zkey"]
+struct Artifacts;
+
+/// Returns an embedded file of a circuit version, falling back on the unversioned one
+fn file(version: &str, depth: u16, extension: &str) -> Option<Cow<'static, [u8]>> {
+    let filename = format!("semaphore-{depth}.{extension}");
+    Artifacts::get(&format!("{version}/{filename}"))
+        .or_else(|| Artifacts::get(&filename))
+        .map(|file| file.data)
+}
+
+/// Returns the embedded zkey of a circuit version and depth
+pub(crate) fn zkey(version: &str, depth: u16) -> Option<Cow<'static, [u8]>> {
+    file(version, depth, "zkey")
+}
+
+/// Parses the embedded verification key of a circuit version and depth
+pub(crate) fn verifying_key(version: &str, depth: u16) -> Option<Result<VerifyingKey<Bn254>>> {
+    file(version, depth, "vkey.json").map(|json| parse_verifying_key_json(&json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proof::{
+            Proof, ProofConfig,
+            tests::{FIXTURE_DEPTH, fixture_proof},
+        },
+        utils::CircuitVersion,
+    };
+    use ark_bn254::{G1Affine, G2Affine};
+    use ark_ec::AffineRepr;
+
+    // The fixture key of the proof tests, bundled for a circuit version that does not exist, so
+    // that it can only be verified without downloading anything
+    const FIXTURE_VERSION: &str = "0.0.0-test";
+
+    #[test]
+    fn test_parse_verifying_key() {
+        let key = verifying_key(FIXTURE_VERSION, FIXTURE_DEPTH)
+            .unwrap()
+            .unwrap();
+        assert_eq!(key.alpha_g1, G1Affine::generator());
+        assert_eq!(key.delta_g2, G2Affine::generator());
+        assert_eq!(key.gamma_abc_g1.len(), 5);
+
+        assert!(verifying_key("0.0.0-missing", FIXTURE_DEPTH).is_none());
+        assert!(verifying_key(FIXTURE_VERSION, FIXTURE_DEPTH + 1).is_none());
+        assert!(parse_verifying_key_json(br#"{"protocol":"plonk"}"#).is_err());
+    }
+
+    #[test]
+    fn test_verify_with_bundled_verifying_key() {
+        let verifier = Proof::new(ProofConfig {
+            circuit_version: CircuitVersion::pinned(FIXTURE_VERSION).unwrap(),
+            ..Default::default()
+        });
+        let proof = fixture_proof();
+        assert!(verifier.verify(&proof));
+
+        let mut invalid = proof.clone();
+        invalid.nullifier += 1u8;
+        assert!(!verifier.verify(&invalid));
+    }
+}
@@ -7,24 +7,82 @@ use thiserror::Error;
 pub enum SemaphoreError {
     #[error("Member already removed")]
     AlreadyRemovedMember,
+    #[error("Public key does not match the commitment")]
+    CommitmentMismatch,
+    #[error("The group is empty")]
+    EmptyGroup,
+    #[error("A group of {size} members does not fit in a tree of depth {depth}")]
+    GroupTooLargeForDepth { size: usize, depth: u16 },
     #[error("Member value is empty")]
     EmptyLeaf,
+    #[error("The base of a timed scope must fit in 192 bits")]
+    TimedScopeBaseTooLarge,
+    #[error("Member value is not a canonical field element")]
+    NonCanonicalElement,
+    #[error("Secret scalar is not smaller than the subgroup order")]
+    NonCanonicalScalar,
+    #[error("Leaf {0} is already set")]
+    OccupiedLeaf(usize),
+    #[error("Inserting at {index} would add {gap} empty leaves, more than the {max} allowed")]
+    InsertGapTooLarge {
+        index: usize,
+        gap: usize,
+        max: usize,
+    },
     #[error("Input array of size {0} exceeds maximum allowed length of 32 bytes")]
     InputSizeExceeded(usize),
     #[error("LeanIMT error: {0}")]
     LeanIMTError(LeanIMTError),
+    #[error("Missing proof field: {0}")]
+    MissingProofField(&'static str),
+    #[error("Proof field {0} is not in the field")]
+    ProofFieldOutOfRange(&'static str),
+    #[error("Expected 8 proof points, got {0}")]
+    InvalidPointsLength(usize),
+    #[error("Merkle proof is invalid")]
+    InvalidMerkleProof,
+    #[error("The identity is not a member of the group")]
+    IdentityNotInGroup,
     #[error("Message of size {0} exceeds maximum allowed length of 32 bytes")]
     MessageSizeExceeded(usize),
+    #[error("Hash failed: {0}")]
+    HashError(String),
+    #[error("Poseidon does not support {0} inputs, only 1 to 12")]
+    UnsupportedPoseidonArity(usize),
     #[error("Public key validation failed: point is not on curve")]
     PublicKeyNotOnCurve,
+    #[error("Public key validation failed: point is not in the prime-order subgroup")]
+    PublicKeyNotInSubgroup,
     #[error("Member has been removed")]
     RemovedMember,
+    #[error("Signature scalar s is not reduced modulo the subgroup order")]
+    MalleableSignature,
     #[error("Signature point R is not on curve")]
     SignaturePointNotOnCurve,
+    #[error("Signature point R is not in the prime-order subgroup")]
+    SignaturePointNotInSubgroup,
     #[error("Signature verification failed")]
     SignatureVerificationFailed,
+    #[error("The tree depth {0} must be a number between 1 and 32")]
+    TreeDepthOutOfRange(u16),
+    #[error("Witness calculation failed: {0}")]
+    WitnessCalculationFailed(String),
+    #[error("Invalid circuit version {0:?}: expected a version like 4.0.0")]
+    InvalidCircuitVersion(String),
+    #[error("Networking is disabled, provide the zkey locally")]
+    NetworkingDisabled,
+    #[error("Verification did not complete within the timeout")]
+    VerificationTimeout,
+    #[error("All the {0} verification workers are busy")]
+    VerificationBusy(usize),
+    #[error("Failed to load the verifying key: {0}")]
+    VerifyingKeyUnavailable(String),
+    #[error("Verifying key fingerprint {actual} does not match the expected {expected}")]
+    VerifyingKeyMismatch { expected: String, actual: String },
     #[error("Serialization error: {0}")]
     SerializationError(String),
+    #[error("Invalid group checkpoint: {0}")]
+    InvalidCheckpoint(String),
 }
 
 impl From<LeanIMTError> for SemaphoreError {
@@ -4,57 +4,539 @@
 //!
 //! Leaves and nodes are the same size, 32 bytes.
 
-use crate::error::SemaphoreError;
+use crate::{
+    MAX_TREE_DEPTH,
+    error::SemaphoreError,
+    identity::Identity,
+    proof::{max_members, max_members_overall},
+    utils::biguint_to_be_32,
+};
 use ark_ed_on_bn254::Fq;
-use ark_ff::{BigInteger, PrimeField};
-use lean_imt::hashed_tree::{HashedLeanIMT, LeanIMTHasher};
+use ark_ff::{BigInt, BigInteger, PrimeField};
+use lean_imt::{
+    hashed_tree::{HashedLeanIMT, LeanIMTHasher},
+    lean_imt::{LeanIMT, LeanIMTError},
+};
 use light_poseidon::{Poseidon, PoseidonHasher};
+use num_bigint::BigUint;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "rayon")]
+use serde::{
+    Deserialize,
+    de::{
+        IntoDeserializer,
+        value::{MapDeserializer, SeqDeserializer},
+    },
+};
+#[cfg(feature = "rayon")]
+use std::iter;
+use std::{cmp::Ordering, collections::HashMap, panic, sync::Mutex};
 
 /// Size of nodes and leaves in bytes
 pub const ELEMENT_SIZE: usize = 32;
 /// Empty element
 pub const EMPTY_ELEMENT: Element = [0u8; ELEMENT_SIZE];
 
+/// Most empty leaves [`Group::insert_at`] adds before the member it sets
+///
+/// Each placeholder is hashed into the tree, so the gap bounds the memory and time of a call.
+pub const MAX_INSERT_GAP: usize = 1 << 16;
+
+/// Magic bytes of a group checkpoint, see [`Group::export_checkpoint`]
+const CHECKPOINT_MAGIC: &[u8; 4] = b"SGCP";
+/// Version of the checkpoint format
+const CHECKPOINT_VERSION: u8 = 1;
+/// Length of the checkpoint header: magic, version, size and root
+const CHECKPOINT_HEADER_LEN: usize = CHECKPOINT_MAGIC.len() + 1 + 8 + ELEMENT_SIZE;
+
+/// Minimum number of proofs built by a thread of [`Group::batch_generate_proofs`]
+///
+/// A proof takes well under a microsecond, smaller tasks cost more to schedule than to run.
+#[cfg(feature = "rayon")]
+const PROOFS_PER_TASK: usize = 1024;
+
+/// Minimum number of parent nodes hashed by a thread of [`Group::new_parallel`]
+///
+/// A Poseidon hash takes tens of microseconds, so small tasks are worth scheduling.
+#[cfg(feature = "rayon")]
+const HASHES_PER_TASK: usize = 16;
+
 /// Element type alias
 pub type Element = [u8; ELEMENT_SIZE];
 
+/// Element bytes in little-endian order, the layout of [`Element`] and of the tree nodes
+///
+/// Use it with [`BeBytes`] to keep track of the byte order at the type level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LeElement(pub Element);
+
+/// 32 bytes in big-endian order, the `uint256` layout used on-chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BeBytes(pub [u8; ELEMENT_SIZE]);
+
+impl LeElement {
+    /// Returns the same value in big-endian order
+    pub fn to_be(self) -> BeBytes {
+        let mut bytes = self.0;
+        bytes.reverse();
+        BeBytes(bytes)
+    }
+
+    /// Returns the value as a scalar
+    pub fn to_fq(self) -> Fq {
+        element_to_fq(&self.0)
+    }
+}
+
+impl BeBytes {
+    /// Returns the same value in little-endian order
+    pub fn to_le(self) -> LeElement {
+        let mut bytes = self.0;
+        bytes.reverse();
+        LeElement(bytes)
+    }
+}
+
+impl From<Fq> for LeElement {
+    fn from(fq: Fq) -> Self {
+        LeElement(fq_to_element(&fq))
+    }
+}
+
+impl From<Fq> for BeBytes {
+    fn from(fq: Fq) -> Self {
+        LeElement::from(fq).to_be()
+    }
+}
+
+impl From<BeBytes> for LeElement {
+    fn from(bytes: BeBytes) -> Self {
+        bytes.to_le()
+    }
+}
+
+impl From<LeElement> for BeBytes {
+    fn from(element: LeElement) -> Self {
+        element.to_be()
+    }
+}
+
+impl From<LeElement> for Element {
+    fn from(element: LeElement) -> Self {
+        element.0
+    }
+}
+
+impl From<BeBytes> for Element {
+    fn from(bytes: BeBytes) -> Self {
+        bytes.to_le().0
+    }
+}
+
+impl TryFrom<&BigUint> for BeBytes {
+    type Error = SemaphoreError;
+
+    fn try_from(value: &BigUint) -> Result<Self, Self::Error> {
+        biguint_to_be_32(value).map(BeBytes)
+    }
+}
+
+impl From<LeElement> for BigUint {
+    fn from(element: LeElement) -> Self {
+        BigUint::from_bytes_le(&element.0)
+    }
+}
+
+impl From<BeBytes> for BigUint {
+    fn from(bytes: BeBytes) -> Self {
+        BigUint::from_bytes_be(&bytes.0)
+    }
+}
+
 /// Merkle proof alias
 pub type MerkleProof = lean_imt::lean_imt::MerkleProof<ELEMENT_SIZE>;
 
+/// Merkle proof with the values as `BigUint`s, like the circuit inputs
+///
+/// Elements are little-endian, see [`Group::generate_proof_biguint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProofBig {
+    pub root: BigUint,
+    pub leaf: BigUint,
+    pub index: usize,
+    pub siblings: Vec<BigUint>,
+}
+
+impl From<&MerkleProof> for MerkleProofBig {
+    fn from(proof: &MerkleProof) -> Self {
+        MerkleProofBig {
+            root: BigUint::from_bytes_le(&proof.root),
+            leaf: BigUint::from_bytes_le(&proof.leaf),
+            index: proof.index,
+            siblings: proof
+                .siblings
+                .iter()
+                .map(|sibling| BigUint::from_bytes_le(sibling))
+                .collect(),
+        }
+    }
+}
+
 /// Poseidon LeanIMT hasher
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct PoseidonHash;
 
-impl LeanIMTHasher<ELEMENT_SIZE> for PoseidonHash {
-    fn hash(input: &[u8]) -> [u8; ELEMENT_SIZE] {
+impl PoseidonHash {
+    /// Hashes two concatenated little-endian elements, returning errors instead of panicking
+    pub fn try_hash(input: &[u8]) -> Result<Element, SemaphoreError> {
+        if input.len() != 2 * ELEMENT_SIZE {
+            return Err(SemaphoreError::HashError(format!(
+                "Expected {} input bytes, got {}",
+                2 * ELEMENT_SIZE,
+                input.len()
+            )));
+        }
+
         let hash = Poseidon::<Fq>::new_circom(2)
-            .expect("Failed to initialize Poseidon")
-            .hash(&[
-                Fq::from_le_bytes_mod_order(&input[..ELEMENT_SIZE]),
-                Fq::from_le_bytes_mod_order(&input[ELEMENT_SIZE..]),
-            ])
-            .expect("Poseidon hash failed");
+            .and_then(|mut poseidon| {
+                poseidon.hash(&[
+                    Fq::from_le_bytes_mod_order(&input[..ELEMENT_SIZE]),
+                    Fq::from_le_bytes_mod_order(&input[ELEMENT_SIZE..]),
+                ])
+            })
+            .map_err(|e| SemaphoreError::HashError(e.to_string()))?;
 
         let mut hash_bytes = [0u8; ELEMENT_SIZE];
         hash_bytes.copy_from_slice(&hash.into_bigint().to_bytes_le());
 
-        hash_bytes
+        Ok(hash_bytes)
+    }
+}
+
+impl LeanIMTHasher<ELEMENT_SIZE> for PoseidonHash {
+    /// The LeanIMT hasher trait cannot fail. Poseidon only fails on the number of inputs,
+    /// which the tree never varies: it always hashes two nodes.
+    fn hash(input: &[u8]) -> [u8; ELEMENT_SIZE] {
+        Self::try_hash(input).expect("Poseidon hash failed")
     }
 }
 
+/// Hashes two elements into their parent node: `Poseidon(left, right)`
+///
+/// Elements are little-endian, like the tree nodes.
+pub fn poseidon_hash(left: &Element, right: &Element) -> Element {
+    hash_nodes::<PoseidonHash>(left, right)
+}
+
+/// Hashes two elements into their parent node with a LeanIMT hasher
+fn hash_nodes<H: LeanIMTHasher<ELEMENT_SIZE>>(left: &Element, right: &Element) -> Element {
+    let mut input = [0u8; 2 * ELEMENT_SIZE];
+    input[..ELEMENT_SIZE].copy_from_slice(left);
+    input[ELEMENT_SIZE..].copy_from_slice(right);
+
+    H::hash(&input)
+}
+
+impl FromIterator<Element> for Group {
+    /// Collects members into a group
+    ///
+    /// Panics on an empty member, use [`Group::try_from_iter`] to handle it.
+    fn from_iter<I: IntoIterator<Item = Element>>(members: I) -> Self {
+        Group::try_from_iter(members).expect("Member value is empty")
+    }
+}
+
+/// Everything a client needs to prove the membership of a member offline
+///
+/// Built by a server with [`Group::membership_bundle`] and checked by the client with
+/// [`MembershipBundle::into_merkle_proof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MembershipBundle {
+    /// Root of the group the proof was generated for
+    pub root: Element,
+    /// Member
+    pub leaf: Element,
+    /// Proof of membership of the member
+    pub proof: MerkleProof,
+}
+
+impl MembershipBundle {
+    /// Returns the Merkle proof, checking that it proves the leaf against the root
+    ///
+    /// Returns [`SemaphoreError::InvalidMerkleProof`] if the proof is for another root or
+    /// leaf, or does not recompute to its root.
+    pub fn into_merkle_proof(self) -> Result<MerkleProof, SemaphoreError> {
+        if self.proof.root != self.root
+            || self.proof.leaf != self.leaf
+            || !Group::verify_proof(&self.proof)
+        {
+            return Err(SemaphoreError::InvalidMerkleProof);
+        }
+
+        Ok(self.proof)
+    }
+}
+
+/// Membership changes between two groups, see [`Group::diff`]
+///
+/// Applying the updates, then the additions and then the removals turns the original group
+/// into the other one.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub struct Group {
-    /// Hashed LeanIMT
-    pub tree: HashedLeanIMT<ELEMENT_SIZE, PoseidonHash>,
+pub struct GroupDiff {
+    /// Members appended after the last index of the original group
+    ///
+    /// An empty leaf appended by the other group is a [`GroupDiff::PLACEHOLDER`] member here,
+    /// removed again by an index of `removed`.
+    pub added: Vec<Element>,
+    /// Indexes of the removed members, those of the appended empty leaves last
+    pub removed: Vec<usize>,
+    /// Indexes and new values of the updated members
+    ///
+    /// A member removed in the original group but set in the other one is listed too: set it
+    /// with [`Group::insert_at`], as [`Group::update_member`] rejects removed members.
+    pub updated: Vec<(usize, Element)>,
+}
+
+impl GroupDiff {
+    /// Member added in place of an appended empty leaf, to be removed again
+    pub const PLACEHOLDER: Element = {
+        let mut element = EMPTY_ELEMENT;
+        element[0] = 1;
+        element
+    };
+
+    /// Returns true if there are no changes
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// Membership operation, as emitted by the Semaphore contract events
+///
+/// `MemberAdded`, `MemberUpdated` and `MemberRemoved` map to `Add`, `Update` and `Remove`:
+/// replaying them with [`Group::apply_all`] rebuilds the on-chain tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupOp {
+    /// Appends a member
+    Add(Element),
+    /// Sets the member at an index
+    Update(usize, Element),
+    /// Removes the member at an index
+    Remove(usize),
+}
+
+/// Saved state of a group, see [`Group::snapshot`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupSnapshot {
+    tree: LeanIMT<ELEMENT_SIZE>,
+}
+
+impl GroupSnapshot {
+    /// Returns the root of the saved group
+    pub fn root(&self) -> Option<Element> {
+        self.tree.root()
+    }
+}
+
+/// Membership proofs of one state of the tree, see [`Group::set_proof_cache`]
+#[derive(Debug, Default)]
+struct CachedProofs {
+    /// Mutation count of the tree the proofs were generated from
+    mutations: u64,
+    proofs: HashMap<usize, MerkleProof>,
+}
+
+/// Mutation counter of a group and its optional proof cache
+///
+/// Not part of the group value: groups with the same tree are equal whatever their history,
+/// and a clone starts with an empty cache.
+#[derive(Debug, Default)]
+struct GroupProofCache {
+    /// Number of changes of the tree
+    mutations: u64,
+    proofs: Option<Mutex<CachedProofs>>,
+}
+
+impl Clone for GroupProofCache {
+    fn clone(&self) -> Self {
+        GroupProofCache {
+            mutations: self.mutations,
+            proofs: self.proofs.as_ref().map(|_| Mutex::default()),
+        }
+    }
+}
+
+impl PartialEq for GroupProofCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for GroupProofCache {}
+
+/// Semaphore group, a LeanIMT of members
+///
+/// The tree hashes with [`PoseidonHash`] like the Semaphore circuits. Forks hashing with
+/// another function can supply their own hasher with [`Group::with_hasher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Group<H = PoseidonHash> {
+    /// Hashed LeanIMT, see [`Group::inner`]
+    tree: HashedLeanIMT<ELEMENT_SIZE, H>,
+    /// Rejects non-canonical members, see [`Group::set_strict`]
+    strict: bool,
+    /// Membership proofs reused while the tree is unchanged, see [`Group::set_proof_cache`]
+    cache: GroupProofCache,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::with_hasher_empty(PoseidonHash)
+    }
 }
 
 impl Group {
     /// Creates a new instance of the Group with optional initial members
     pub fn new(members: &[Element]) -> Result<Self, SemaphoreError> {
+        Group::with_hasher(members, PoseidonHash)
+    }
+
+    /// Creates a group from an iterator of members, erroring on an empty member
+    pub fn try_from_iter(
+        members: impl IntoIterator<Item = Element>,
+    ) -> Result<Self, SemaphoreError> {
+        Group::new(&members.into_iter().collect::<Vec<Element>>())
+    }
+
+    /// Creates a group like [`Group::new`], hashing each level of the tree in parallel
+    ///
+    /// The nodes of a level only depend on the level below, so the hashing, which is nearly all
+    /// of a bulk load, is split between the threads of the rayon pool. The levels are then
+    /// moved into the LeanIMT through its serialized layout, without hashing again. The tree
+    /// and root are those of [`Group::new`].
+    ///
+    /// Building a group of 1M members on a single core, where there is nothing to split, took
+    /// 72 s with [`Group::new`] and 67 s with `new_parallel` (release build, one run each).
+    /// `cargo bench --bench group --features rayon` compares both on other machines, where the
+    /// build time is expected to divide by up to the number of cores.
+    #[cfg(feature = "rayon")]
+    pub fn new_parallel(members: &[Element]) -> Result<Self, SemaphoreError> {
+        if members.contains(&EMPTY_ELEMENT) {
+            return Err(SemaphoreError::EmptyLeaf);
+        }
+
+        let mut parents: Vec<Vec<Element>> = Vec::new();
+        loop {
+            let level = parents.last().map_or(members, Vec::as_slice);
+            if level.len() <= 1 {
+                break;
+            }
+            let level = level
+                .par_chunks(2)
+                .with_min_len(HASHES_PER_TASK)
+                .map(|nodes| match nodes {
+                    [left, right] => poseidon_hash(left, right),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            parents.push(level);
+        }
+
+        // The levels, leaves first, are the `nodes` the LeanIMT is serialized as. Each level is
+        // dropped once moved into the tree, so the build peaks at about the size of the tree.
+        type Error = serde::de::value::Error;
+        fn level<'a>(
+            nodes: Box<dyn Iterator<Item = Element> + 'a>,
+        ) -> impl IntoDeserializer<'a, Error> {
+            SeqDeserializer::<_, Error>::new(
+                nodes.map(|node| SeqDeserializer::<_, Error>::new(node.into_iter())),
+            )
+        }
+        let levels = iter::once(level(Box::new(members.iter().copied()))).chain(
+            parents
+                .into_iter()
+                .map(|nodes| level(Box::new(nodes.into_iter()))),
+        );
+        let tree = LeanIMT::deserialize(MapDeserializer::<_, Error>::new(iter::once((
+            "nodes",
+            SeqDeserializer::<_, Error>::new(levels),
+        ))))
+        .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+
+        Ok(Group {
+            tree: HashedLeanIMT::new_from_tree(tree, PoseidonHash),
+            strict: false,
+            cache: GroupProofCache::default(),
+        })
+    }
+
+    /// Rebuilds a group from [`Group::export_checkpoint`], checking the root of the header
+    ///
+    /// Corrupted or truncated checkpoints return [`SemaphoreError::InvalidCheckpoint`].
+    pub fn import_checkpoint(bytes: &[u8]) -> Result<Self, SemaphoreError> {
+        Group::import_checkpoint_with_hasher(bytes, PoseidonHash)
+    }
+
+    /// Rebuilds a strict group from a checkpoint, see [`Group::set_strict`]
+    ///
+    /// Like [`Group::import_checkpoint`], a leaf that is not a canonical field element being
+    /// an [`SemaphoreError::InvalidCheckpoint`] error too.
+    pub fn import_checkpoint_strict(bytes: &[u8]) -> Result<Self, SemaphoreError> {
+        let mut group = Group::import_checkpoint(bytes)?;
+        if let Some(index) = non_canonical_leaf(group.tree.leaves()) {
+            return Err(SemaphoreError::InvalidCheckpoint(format!(
+                "leaf {index} is not a canonical field element"
+            )));
+        }
+
+        group.strict = true;
+        Ok(group)
+    }
+
+    /// Verifies a proof of membership for a member
+    pub fn verify_proof(proof: &MerkleProof) -> bool {
+        HashedLeanIMT::<ELEMENT_SIZE, PoseidonHash>::verify_proof(proof)
+    }
+
+    /// Verifies a batch of membership proofs, preserving the input order in the result
+    ///
+    /// Proofs sharing a root reuse the parent hashes already computed for the other proofs.
+    /// With the `rayon` feature, proofs with different roots are verified in parallel.
+    pub fn verify_proof_batch(proofs: &[MerkleProof]) -> Vec<bool> {
+        let mut by_root = HashMap::<Element, Vec<usize>>::new();
+        for (index, proof) in proofs.iter().enumerate() {
+            by_root.entry(proof.root).or_default().push(index);
+        }
+        let batches: Vec<Vec<usize>> = by_root.into_values().collect();
+
+        let verify_batch = |indexes: &Vec<usize>| {
+            let mut parents = HashMap::new();
+            indexes
+                .iter()
+                .map(|&index| (index, verify_proof_with_cache(&proofs[index], &mut parents)))
+                .collect::<Vec<(usize, bool)>>()
+        };
+
+        #[cfg(feature = "rayon")]
+        let verified: Vec<(usize, bool)> = batches.par_iter().flat_map_iter(verify_batch).collect();
+        #[cfg(not(feature = "rayon"))]
+        let verified: Vec<(usize, bool)> = batches.iter().flat_map(verify_batch).collect();
+
+        let mut results = vec![false; proofs.len()];
+        for (index, valid) in verified {
+            results[index] = valid;
+        }
+        results
+    }
+}
+
+impl<H: LeanIMTHasher<ELEMENT_SIZE> + Clone> Group<H> {
+    /// Creates a group hashing with `hasher`, with optional initial members
+    pub fn with_hasher(members: &[Element], hasher: H) -> Result<Self, SemaphoreError> {
         if members.is_empty() {
-            return Ok(Group {
-                tree: HashedLeanIMT::<ELEMENT_SIZE, PoseidonHash>::new(&[], PoseidonHash)?,
-            });
+            return Ok(Group::with_hasher_empty(hasher));
         }
 
         for &member in members {
@@ -64,16 +546,136 @@ impl Group {
         }
 
         Ok(Group {
-            tree: HashedLeanIMT::<ELEMENT_SIZE, PoseidonHash>::new(members, PoseidonHash)?,
+            tree: HashedLeanIMT::new(members, hasher)?,
+            strict: false,
+            cache: GroupProofCache::default(),
         })
     }
 
+    /// Adds a member like [`Group::add_member`], returning a hasher failure as
+    /// [`SemaphoreError::HashError`] instead of panicking
+    ///
+    /// The LeanIMT hasher trait is infallible, so a failing hasher panics, e.g. [`PoseidonHash`]
+    /// through [`PoseidonHash::try_hash`]. The parents of the new leaf are hashed first, along
+    /// the path the insertion takes, and the member is only inserted once they all succeed, so
+    /// the group is unchanged on failure. The insertion hashes that path again: this is best
+    /// effort, a hasher that only fails on some calls can still panic. Panics are not caught
+    /// when built with `panic = "abort"`.
+    pub fn try_add_member(&mut self, member: impl Into<Element>) -> Result<(), SemaphoreError> {
+        let member = member.into();
+        self.check_member(&member)?;
+
+        let hash_error = |payload: Box<dyn std::any::Any + Send>| {
+            let message = payload
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| payload.downcast_ref::<&str>().copied())
+                .unwrap_or("the hasher panicked");
+            SemaphoreError::HashError(message.to_string())
+        };
+        let depth = self.tree.depth() + usize::from(self.size() >= 1 << self.tree.depth());
+        let (mut node, mut index) = (member, self.size());
+        for level in 0..depth {
+            if index % 2 == 1 {
+                let sibling = self.tree.get_node(level, index - 1)?;
+                node =
+                    panic::catch_unwind(|| hash_nodes::<H>(&sibling, &node)).map_err(hash_error)?;
+            }
+            index >>= 1;
+        }
+
+        self.tree_mut().insert(&member);
+        Ok(())
+    }
+
+    /// Creates an empty group hashing with `hasher`
+    fn with_hasher_empty(hasher: H) -> Self {
+        Group {
+            tree: HashedLeanIMT::new_from_tree(LeanIMT::default(), hasher),
+            strict: false,
+            cache: GroupProofCache::default(),
+        }
+    }
+
+    /// Returns the underlying LeanIMT, for its APIs the group does not wrap
+    ///
+    /// Read-only: changing the tree directly would bypass the member checks of the group.
+    pub fn inner(&self) -> &HashedLeanIMT<ELEMENT_SIZE, H> {
+        &self.tree
+    }
+
     /// Returns the root hash of the tree, or None if the tree is empty
     pub fn root(&self) -> Option<Element> {
         self.tree.root()
     }
 
+    /// Returns the root in big-endian order, as stored on-chain
+    pub fn root_be(&self) -> Option<BeBytes> {
+        self.root().map(|root| LeElement(root).to_be())
+    }
+
+    /// Returns true if both groups have the same root and size
+    ///
+    /// The derived `PartialEq` compares the whole structure, including the strict mode, so a
+    /// group is not `==` to its import when it was strict. Root equality is what matters for
+    /// proofs: groups with the same root accept the same proofs.
+    pub fn root_eq(&self, other: &Self) -> bool {
+        self.root() == other.root() && self.size() == other.size()
+    }
+
+    /// Returns true if the root differs from a previously seen root
+    ///
+    /// For sync loops polling the group: an empty group has no root, so it has changed since
+    /// any root.
+    pub fn root_changed_since(&self, previous: &Element) -> bool {
+        self.root().as_ref() != Some(previous)
+    }
+
+    /// Recomputes the root level by level from the members, without the LeanIMT
+    ///
+    /// Slow reference implementation to cross-check [`Group::root`] in tests and when
+    /// debugging root mismatches: each pair of nodes is hashed and a node without a sibling
+    /// is moved up unchanged.
+    pub fn root_reference(&self) -> Option<Element> {
+        let mut level = self.members();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|nodes| match nodes {
+                    [left, right] => hash_nodes::<H>(left, right),
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+        }
+
+        level.first().copied()
+    }
+
+    /// Returns the root the circuit computes for a proof of the given tree depth
+    ///
+    /// The LeanIMT does not hash empty nodes and the circuit stops at the actual Merkle proof
+    /// length, so the zero siblings `generate_proof` pads the proof with do not change the
+    /// root: it is [`Group::root`] for any depth the group fits in.
+    pub fn root_at_depth(&self, depth: u16) -> Result<Element, SemaphoreError> {
+        let Some(max_members) = max_members(depth) else {
+            return Err(SemaphoreError::TreeDepthOutOfRange(depth));
+        };
+        if self.size() as u64 > max_members {
+            return Err(SemaphoreError::GroupTooLargeForDepth {
+                size: self.size(),
+                depth,
+            });
+        }
+
+        self.root().ok_or(SemaphoreError::EmptyGroup)
+    }
+
     /// Returns the depth of the tree
+    ///
+    /// The depth is the number of levels above the leaves: `0` for an empty group and for a
+    /// group with a single member, then `ceil(log2(size))`. Use [`Group::is_empty`] to tell
+    /// those two cases apart.
     pub fn depth(&self) -> usize {
         self.tree.depth()
     }
@@ -83,6 +685,14 @@ impl Group {
         self.tree.size()
     }
 
+    /// Returns true if the group has no leaves
+    ///
+    /// Removed members are kept as empty leaves, so a group whose members were all
+    /// removed is not empty.
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
     /// Returns the group members
     pub fn members(&self) -> Vec<Element> {
         self.tree
@@ -92,40 +702,193 @@ impl Group {
             .collect()
     }
 
+    /// Returns the members sorted by field value, without the removed members
+    pub fn sorted_members(&self) -> Vec<Element> {
+        let mut members: Vec<Element> = self
+            .tree
+            .leaves()
+            .iter()
+            .filter(|member| **member != EMPTY_ELEMENT)
+            .copied()
+            .collect();
+        members.sort_unstable_by(element_cmp);
+        members
+    }
+
     /// Returns the index of a member if it exists
-    pub fn index_of(&self, member: Element) -> Option<usize> {
-        self.tree.index_of(&member)
+    ///
+    /// Like the other member methods, it takes an [`Element`], a [`LeElement`] or a [`BeBytes`].
+    pub fn index_of(&self, member: impl Into<Element>) -> Option<usize> {
+        self.tree.index_of(&member.into())
     }
 
-    /// Adds a new member to the group
-    pub fn add_member(&mut self, member: Element) -> Result<(), SemaphoreError> {
-        if member == EMPTY_ELEMENT {
+    /// Returns true if the identity commitment is an active member of the group
+    pub fn contains_identity(&self, identity: &Identity) -> bool {
+        self.contains_commitment(identity.commitment())
+    }
+
+    /// Returns true if the commitment is an active member of the group
+    ///
+    /// Removed members are replaced by empty leaves, so they are not found.
+    pub fn contains_commitment(&self, commitment: &Fq) -> bool {
+        self.index_of(fq_to_element(commitment)).is_some()
+    }
+
+    /// Rejects members that are not canonical field elements when `strict` is true
+    ///
+    /// Groups are not strict by default: members larger than the field modulus are reduced
+    /// when hashed, see [`is_canonical_element`].
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Returns true if non-canonical members are rejected
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Caches the membership proofs of [`Group::generate_proof`] while the tree is unchanged
+    ///
+    /// For members proving repeatedly into the same group. Any change to the tree invalidates
+    /// the cache. Disabled by default, disabling it drops the cached proofs.
+    pub fn set_proof_cache(&mut self, enabled: bool) {
+        self.cache.proofs = enabled.then(Mutex::default);
+    }
+
+    /// Returns true if membership proofs are cached
+    pub fn is_proof_cache_enabled(&self) -> bool {
+        self.cache.proofs.is_some()
+    }
+
+    /// Returns the tree to change it, counting the change to invalidate the cached proofs
+    fn tree_mut(&mut self) -> &mut HashedLeanIMT<ELEMENT_SIZE, H> {
+        self.cache.mutations += 1;
+        &mut self.tree
+    }
+
+    /// Checks that a member can be added
+    fn check_member(&self, member: &Element) -> Result<(), SemaphoreError> {
+        if *member == EMPTY_ELEMENT {
             return Err(SemaphoreError::EmptyLeaf);
         }
+        if self.strict && !is_canonical_element(member) {
+            return Err(SemaphoreError::NonCanonicalElement);
+        }
+        Ok(())
+    }
+
+    /// Adds a new member to the group
+    pub fn add_member(&mut self, member: impl Into<Element>) -> Result<(), SemaphoreError> {
+        let member = member.into();
+        self.check_member(&member)?;
 
-        self.tree.insert(&member);
+        self.tree_mut().insert(&member);
         Ok(())
     }
 
     /// Adds a set of members to the group
     pub fn add_members(&mut self, members: &[Element]) -> Result<(), SemaphoreError> {
-        for &member in members {
-            if member == EMPTY_ELEMENT {
-                return Err(SemaphoreError::EmptyLeaf);
+        for member in members {
+            self.check_member(member)?;
+        }
+
+        self.tree_mut().insert_many(members)?;
+        Ok(())
+    }
+
+    /// Adds the members of an iterator to the group
+    ///
+    /// Members are checked as the iterator is consumed, and inserted in one batch once they
+    /// are all valid, so the group is unchanged if one is invalid. The batch is buffered for
+    /// that atomicity, and because the LeanIMT inserts a batch from a slice, hashing each
+    /// parent once instead of once per member. The buffer is sized from the iterator's
+    /// `size_hint`.
+    pub fn extend<I: IntoIterator<Item = Element>>(
+        &mut self,
+        members: I,
+    ) -> Result<(), SemaphoreError> {
+        let members = members.into_iter();
+        let mut batch = Vec::with_capacity(members.size_hint().0);
+        for member in members {
+            self.check_member(&member)?;
+            batch.push(member);
+        }
+
+        if !batch.is_empty() {
+            self.tree_mut().insert_many(&batch)?;
+        }
+        Ok(())
+    }
+
+    /// Adds identity commitments to the group, see [`Group::extend`]
+    pub fn extend_commitments<I: IntoIterator<Item = Fq>>(
+        &mut self,
+        commitments: I,
+    ) -> Result<(), SemaphoreError> {
+        self.extend(
+            commitments
+                .into_iter()
+                .map(|commitment| fq_to_element(&commitment)),
+        )
+    }
+
+    /// Sets the member at an index, growing the tree with empty leaves up to it if needed
+    ///
+    /// Mirrors a tree whose members occupy given indexes. Only empty leaves can be set: the
+    /// placeholders and removed members. Indexes past the largest supported tree, of
+    /// [`max_members_overall`] members, or more than [`MAX_INSERT_GAP`] leaves past the end of
+    /// the group are rejected before growing the tree.
+    pub fn insert_at(
+        &mut self,
+        index: usize,
+        member: impl Into<Element>,
+    ) -> Result<(), SemaphoreError> {
+        let member = member.into();
+        self.check_member(&member)?;
+        if index as u64 >= max_members_overall() {
+            return Err(SemaphoreError::GroupTooLargeForDepth {
+                size: index.saturating_add(1),
+                depth: MAX_TREE_DEPTH,
+            });
+        }
+
+        if index < self.size() {
+            if self.tree.leaves()[index] != EMPTY_ELEMENT {
+                return Err(SemaphoreError::OccupiedLeaf(index));
             }
+            self.tree_mut().update(index, &member)?;
+        } else {
+            let gap = index - self.size();
+            if gap > MAX_INSERT_GAP {
+                return Err(SemaphoreError::InsertGapTooLarge {
+                    index,
+                    gap,
+                    max: MAX_INSERT_GAP,
+                });
+            }
+            let mut leaves = vec![EMPTY_ELEMENT; gap];
+            leaves.push(member);
+            self.tree_mut().insert_many(&leaves)?;
         }
 
-        self.tree.insert_many(members)?;
         Ok(())
     }
 
     /// Updates a group member
-    pub fn update_member(&mut self, index: usize, member: Element) -> Result<(), SemaphoreError> {
+    ///
+    /// The new member is checked like an added one, see [`Group::add_member`].
+    pub fn update_member(
+        &mut self,
+        index: usize,
+        member: impl Into<Element>,
+    ) -> Result<(), SemaphoreError> {
+        let member = member.into();
+        self.check_member(&member)?;
         if self.members()[index] == EMPTY_ELEMENT {
             return Err(SemaphoreError::RemovedMember);
         }
 
-        self.tree.update(index, &member)?;
+        self.tree_mut().update(index, &member)?;
         Ok(())
     }
 
@@ -135,87 +898,722 @@ impl Group {
             return Err(SemaphoreError::AlreadyRemovedMember);
         }
 
-        self.tree.update(index, &EMPTY_ELEMENT)?;
+        self.tree_mut().update(index, &EMPTY_ELEMENT)?;
         Ok(())
     }
 
+    /// Removes all the leaves, leaving an empty group
+    ///
+    /// The LeanIMT does not expose its storage, so the tree is replaced by an empty one.
+    pub fn clear(&mut self) {
+        *self.tree_mut() = Group::with_hasher_empty(self.tree.hasher().clone()).tree;
+    }
+
+    /// Rebuilds the tree from the members that were not removed
+    ///
+    /// Returns the new index of each remaining member, by old index. Pruning changes the root
+    /// and the indexes, so proofs and references to the previous group are no longer valid.
+    pub fn prune_removed(&mut self) -> Result<HashMap<usize, usize>, SemaphoreError> {
+        let mut indexes = HashMap::new();
+        let mut members = Vec::new();
+        for (index, member) in self.tree.leaves().iter().enumerate() {
+            if *member != EMPTY_ELEMENT {
+                indexes.insert(index, members.len());
+                members.push(*member);
+            }
+        }
+
+        *self.tree_mut() = Group::with_hasher(&members, self.tree.hasher().clone())?.tree;
+        Ok(indexes)
+    }
+
+    /// Applies a membership operation
+    pub fn apply(&mut self, op: GroupOp) -> Result<(), SemaphoreError> {
+        match op {
+            GroupOp::Add(member) => self.add_member(member),
+            GroupOp::Update(index, _) | GroupOp::Remove(index) if index >= self.size() => {
+                Err(LeanIMTError::IndexOutOfBounds.into())
+            }
+            GroupOp::Update(index, member) => self.update_member(index, member),
+            GroupOp::Remove(index) => self.remove_member(index),
+        }
+    }
+
+    /// Applies membership operations in order
+    ///
+    /// If an operation fails, the group is restored to its state before the first one.
+    pub fn apply_all(&mut self, ops: &[GroupOp]) -> Result<(), SemaphoreError> {
+        let snapshot = self.snapshot();
+        for &op in ops {
+            if let Err(error) = self.apply(op) {
+                self.restore(snapshot);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Saves the current state to roll back a batch of changes with [`Group::restore`]
+    ///
+    /// The snapshot copies the tree nodes, leaves included, so restoring it needs no hashing.
+    pub fn snapshot(&self) -> GroupSnapshot {
+        GroupSnapshot {
+            tree: self.tree.tree().clone(),
+        }
+    }
+
+    /// Reverts the group to a snapshot
+    pub fn restore(&mut self, snapshot: GroupSnapshot) {
+        *self.tree_mut() = HashedLeanIMT::new_from_tree(snapshot.tree, self.tree.hasher().clone());
+    }
+
+    /// Exports the leaves in a compact binary checkpoint, e.g. to persist a group periodically
+    ///
+    /// The layout is `"SGCP" || version (1 byte) || size (u64 LE) || root || leaves`, the root
+    /// and leaves being 32-byte little-endian elements and the root zero for an empty group.
+    /// Removed members are kept as zero leaves. The strict mode is not saved.
+    pub fn export_checkpoint(&self) -> Vec<u8> {
+        let leaves = self.tree.leaves();
+        let mut bytes = Vec::with_capacity(CHECKPOINT_HEADER_LEN + leaves.len() * ELEMENT_SIZE);
+        bytes.extend_from_slice(CHECKPOINT_MAGIC);
+        bytes.push(CHECKPOINT_VERSION);
+        bytes.extend_from_slice(&(leaves.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.root().unwrap_or(EMPTY_ELEMENT));
+        for leaf in leaves {
+            bytes.extend_from_slice(leaf);
+        }
+        bytes
+    }
+
+    /// Rebuilds a group hashing with `hasher` from a checkpoint, see [`Group::import_checkpoint`]
+    pub fn import_checkpoint_with_hasher(bytes: &[u8], hasher: H) -> Result<Self, SemaphoreError> {
+        let invalid = |reason: String| Err(SemaphoreError::InvalidCheckpoint(reason));
+        if bytes.len() < CHECKPOINT_HEADER_LEN {
+            return invalid(format!("{} bytes is shorter than the header", bytes.len()));
+        }
+        let (header, leaves) = bytes.split_at(CHECKPOINT_HEADER_LEN);
+        let (magic, header) = header.split_at(CHECKPOINT_MAGIC.len());
+        if magic != CHECKPOINT_MAGIC {
+            return invalid("not a group checkpoint".to_string());
+        }
+        if header[0] != CHECKPOINT_VERSION {
+            return invalid(format!("unsupported version {}", header[0]));
+        }
+        let size = u64::from_le_bytes(header[1..9].try_into().unwrap());
+        let root: Element = header[9..].try_into().unwrap();
+        if leaves.len() as u64 != size.saturating_mul(ELEMENT_SIZE as u64) {
+            return invalid(format!(
+                "{} bytes of leaves for a size of {size}",
+                leaves.len()
+            ));
+        }
+
+        let leaves: Vec<Element> = leaves
+            .chunks_exact(ELEMENT_SIZE)
+            .map(|leaf| leaf.try_into().unwrap())
+            .collect();
+        let group = Group {
+            tree: HashedLeanIMT::new(&leaves, hasher)?,
+            strict: false,
+            cache: GroupProofCache::default(),
+        };
+        if group.root().unwrap_or(EMPTY_ELEMENT) != root {
+            return invalid("the root does not match the leaves".to_string());
+        }
+
+        Ok(group)
+    }
+
     /// Creates a proof of membership for a member
+    ///
+    /// With [`Group::set_proof_cache`], the proof is reused until the tree changes.
     pub fn generate_proof(&self, index: usize) -> Result<MerkleProof, SemaphoreError> {
-        self.tree
-            .generate_proof(index)
-            .map_err(SemaphoreError::LeanIMTError)
+        let Some(cache) = &self.cache.proofs else {
+            return self
+                .tree
+                .generate_proof(index)
+                .map_err(SemaphoreError::LeanIMTError);
+        };
+
+        let mut cache = cache.lock().unwrap();
+        if cache.mutations != self.cache.mutations {
+            cache.proofs.clear();
+            cache.mutations = self.cache.mutations;
+        }
+        if let Some(proof) = cache.proofs.get(&index) {
+            return Ok(proof.clone());
+        }
+        let proof = self.tree.generate_proof(index)?;
+        cache.proofs.insert(index, proof.clone());
+        Ok(proof)
     }
 
-    /// Verifies a proof of membership for a member
-    pub fn verify_proof(proof: &MerkleProof) -> bool {
-        HashedLeanIMT::<ELEMENT_SIZE, PoseidonHash>::verify_proof(proof)
+    /// Returns the root with the proof of membership of a member
+    ///
+    /// The proof is read from the stored tree nodes and carries the root it was read with, so
+    /// both come from the same state.
+    pub fn root_and_proof(&self, index: usize) -> Result<(Element, MerkleProof), SemaphoreError> {
+        let proof = self.generate_proof(index)?;
+        Ok((proof.root, proof))
+    }
+
+    /// Returns the leaf, proof of membership and root of a member in one bundle
+    pub fn membership_bundle(&self, index: usize) -> Result<MembershipBundle, SemaphoreError> {
+        let (root, proof) = self.root_and_proof(index)?;
+        Ok(MembershipBundle {
+            root,
+            leaf: proof.leaf,
+            proof,
+        })
+    }
+
+    /// Creates a proof of membership with the values already converted for the circuit inputs
+    pub fn generate_proof_biguint(&self, index: usize) -> Result<MerkleProofBig, SemaphoreError> {
+        Ok(MerkleProofBig::from(&self.generate_proof(index)?))
+    }
+
+    /// Creates the proofs of membership of several members, in the order of `indices`
+    ///
+    /// The tree keeps every node, so a proof only reads its siblings without hashing. The
+    /// indexes are all checked before any proof is built, and with the `rayon` feature the
+    /// proofs are built in parallel.
+    pub fn batch_generate_proofs(
+        &self,
+        indices: &[usize],
+    ) -> Result<Vec<MerkleProof>, SemaphoreError>
+    where
+        H: Sync,
+    {
+        if indices.iter().any(|&index| index >= self.size()) {
+            return Err(LeanIMTError::IndexOutOfBounds.into());
+        }
+
+        #[cfg(feature = "rayon")]
+        let proofs = indices
+            .par_iter()
+            .with_min_len(PROOFS_PER_TASK)
+            .map(|&index| self.generate_proof(index));
+        #[cfg(not(feature = "rayon"))]
+        let proofs = indices.iter().map(|&index| self.generate_proof(index));
+        proofs.collect()
+    }
+
+    /// Computes the membership changes needed to go from this group to `other`
+    ///
+    /// Leaves are compared positionally. A tree never shrinks, so when `other` is shorter its
+    /// missing leaves are reported as removed: the result then has empty leaves past the end
+    /// of `other`, and another root.
+    pub fn diff(&self, other: &Self) -> GroupDiff {
+        let leaves = self.tree.leaves();
+        let other_leaves = other.tree.leaves();
+        let mut diff = GroupDiff::default();
+
+        for (index, (&leaf, &other_leaf)) in leaves.iter().zip(other_leaves).enumerate() {
+            if leaf == other_leaf {
+                continue;
+            }
+
+            if other_leaf == EMPTY_ELEMENT {
+                diff.removed.push(index);
+            } else {
+                diff.updated.push((index, other_leaf));
+            }
+        }
+
+        if other_leaves.len() > leaves.len() {
+            for (index, &leaf) in other_leaves.iter().enumerate().skip(leaves.len()) {
+                if leaf == EMPTY_ELEMENT {
+                    diff.added.push(GroupDiff::PLACEHOLDER);
+                    diff.removed.push(index);
+                } else {
+                    diff.added.push(leaf);
+                }
+            }
+        } else {
+            diff.removed
+                .extend((other_leaves.len()..leaves.len()).filter(|&i| leaves[i] != EMPTY_ELEMENT));
+        }
+
+        diff
     }
 }
 
 #[cfg(feature = "serde")]
-impl Group {
+impl<H: LeanIMTHasher<ELEMENT_SIZE>> Group<H> {
     /// Exports the LeanIMT tree to a JSON.
     pub fn export(&self) -> Result<String, SemaphoreError> {
         serde_json::to_string(&self.tree.tree())
             .map_err(|e| SemaphoreError::SerializationError(e.to_string()))
     }
 
+    /// Streams the LeanIMT tree as JSON to a writer, without building the whole string.
+    pub fn export_to_writer(&self, writer: impl std::io::Write) -> Result<(), SemaphoreError> {
+        serde_json::to_writer(writer, &self.tree.tree())
+            .map_err(|e| SemaphoreError::SerializationError(e.to_string()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Group {
     /// Imports a Group from a JSON string representing a LeanIMT tree.
+    ///
+    /// The tree is hashed again from its leaves, so a node that does not match them is a
+    /// [`SemaphoreError::SerializationError`] naming its level and index.
     pub fn import(json: &str) -> Result<Self, SemaphoreError> {
-        let lean_imt_tree: lean_imt::lean_imt::LeanIMT<ELEMENT_SIZE> =
-            serde_json::from_str(json)
-                .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+        let lean_imt_tree: LeanIMT<ELEMENT_SIZE> = serde_json::from_str(json)
+            .map_err(|e| SemaphoreError::SerializationError(format!("Invalid LeanIMT: {e}")))?;
+
+        Group::from_imported_tree(lean_imt_tree, false)
+    }
+
+    /// Imports a strict Group from a JSON LeanIMT tree, see [`Group::set_strict`]
+    ///
+    /// Errors if a leaf is not a canonical field element.
+    pub fn import_strict(json: &str) -> Result<Self, SemaphoreError> {
+        let lean_imt_tree: LeanIMT<ELEMENT_SIZE> = serde_json::from_str(json)
+            .map_err(|e| SemaphoreError::SerializationError(format!("Invalid LeanIMT: {e}")))?;
+
+        Group::from_imported_tree(lean_imt_tree, true)
+    }
+
+    /// Imports a Group from a reader streaming a JSON LeanIMT tree, like [`Group::import`].
+    pub fn import_from_reader(reader: impl std::io::Read) -> Result<Self, SemaphoreError> {
+        let lean_imt_tree: LeanIMT<ELEMENT_SIZE> = serde_json::from_reader(reader)
+            .map_err(|e| SemaphoreError::SerializationError(format!("Invalid LeanIMT: {e}")))?;
+
+        Group::from_imported_tree(lean_imt_tree, false)
+    }
+
+    /// Rebuilds an imported tree from its leaves, checking its nodes and the strict mode
+    ///
+    /// Every level is compared to the rebuilt one, which rejects tampered hashes as well as
+    /// missing or extra nodes that later insertions would trip on.
+    fn from_imported_tree(
+        tree: LeanIMT<ELEMENT_SIZE>,
+        strict: bool,
+    ) -> Result<Self, SemaphoreError> {
+        if let Some(index) = non_canonical_leaf(tree.leaves()).filter(|_| strict) {
+            return Err(SemaphoreError::SerializationError(format!(
+                "Leaf {index} is not a canonical field element"
+            )));
+        }
+
+        let rebuilt = LeanIMT::new(tree.leaves(), PoseidonHash::hash)?;
+        for level in 0..=tree.depth().max(rebuilt.depth()) {
+            for index in 0.. {
+                let node = tree.get_node(level, index).ok();
+                if node != rebuilt.get_node(level, index).ok() {
+                    return Err(SemaphoreError::SerializationError(format!(
+                        "Node {index} of level {level} does not match the leaves"
+                    )));
+                }
+                if node.is_none() {
+                    break;
+                }
+            }
+        }
 
         Ok(Group {
-            tree: HashedLeanIMT::new_from_tree(lean_imt_tree, PoseidonHash),
+            tree: HashedLeanIMT::new_from_tree(rebuilt, PoseidonHash),
+            strict,
+            cache: GroupProofCache::default(),
         })
     }
 }
 
-/// Converts a byte array to an element
+/// Verifies a membership proof, reusing and filling a cache of parent hashes
+fn verify_proof_with_cache(
+    proof: &MerkleProof,
+    parents: &mut HashMap<[u8; 2 * ELEMENT_SIZE], Element>,
+) -> bool {
+    let mut node = proof.leaf;
+
+    for (i, sibling) in proof.siblings.iter().enumerate() {
+        let mut hash_input = [0u8; 2 * ELEMENT_SIZE];
+
+        if (proof.index >> i) & 1 != 0 {
+            hash_input[..ELEMENT_SIZE].copy_from_slice(sibling);
+            hash_input[ELEMENT_SIZE..].copy_from_slice(&node);
+        } else {
+            hash_input[..ELEMENT_SIZE].copy_from_slice(&node);
+            hash_input[ELEMENT_SIZE..].copy_from_slice(sibling);
+        }
+
+        node = *parents
+            .entry(hash_input)
+            .or_insert_with(|| PoseidonHash::hash(&hash_input));
+    }
+
+    proof.root == node
+}
+
+/// Returns true if an element is smaller than the field modulus
+///
+/// Larger values are reduced when converted to a field element, so two distinct elements
+/// can hash the same.
+pub fn is_canonical_element(element: &Element) -> bool {
+    let mut limbs = [0u64; 4];
+    for (limb, bytes) in limbs.iter_mut().zip(element.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(bytes.try_into().unwrap());
+    }
+    BigInt(limbs) < Fq::MODULUS
+}
+
+/// Returns the index of the first leaf that is not a canonical field element
+fn non_canonical_leaf(leaves: &[Element]) -> Option<usize> {
+    leaves.iter().position(|leaf| !is_canonical_element(leaf))
+}
+
+/// Compares two elements by their field value
+///
+/// Elements are little-endian, so comparing the bytes does not follow the numeric order.
+/// Canonical elements are compared from their most significant byte, without arithmetic.
+/// Non-canonical ones are reduced first, like when they are hashed.
+pub fn element_cmp(a: &Element, b: &Element) -> Ordering {
+    if is_canonical_element(a) && is_canonical_element(b) {
+        return a.iter().rev().cmp(b.iter().rev());
+    }
+
+    Fq::from_le_bytes_mod_order(a)
+        .into_bigint()
+        .cmp(&Fq::from_le_bytes_mod_order(b).into_bigint())
+}
+
+/// Converts a little-endian byte array to an element
 pub fn bytes_to_element(bytes: &[u8]) -> Result<Element, SemaphoreError> {
     if bytes.len() > ELEMENT_SIZE {
         return Err(SemaphoreError::InputSizeExceeded(bytes.len()));
     }
 
-    let mut element = EMPTY_ELEMENT;
-    element[..bytes.len()].copy_from_slice(bytes);
+    let mut element = EMPTY_ELEMENT;
+    element[..bytes.len()].copy_from_slice(bytes);
+
+    Ok(element)
+}
+
+/// Converts a big-endian byte array (e.g. an Ethereum `uint256`) to an element
+pub fn bytes_to_element_be(bytes: &[u8]) -> Result<Element, SemaphoreError> {
+    let mut bytes_le = bytes.to_vec();
+    bytes_le.reverse();
+
+    bytes_to_element(&bytes_le)
+}
+
+/// Converts a scalar to an element
+pub fn fq_to_element(fq: &Fq) -> Element {
+    let mut element = EMPTY_ELEMENT;
+    let bytes = fq.into_bigint().to_bytes_le();
+    element[..bytes.len()].copy_from_slice(&bytes);
+    element
+}
+
+/// Converts an element to a scalar
+pub fn element_to_fq(element: &Element) -> Fq {
+    Fq::from_le_bytes_mod_order(element)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::element_to_biguint;
+
+    #[test]
+    fn test_conversions() {
+        let test_bytes = [
+            59, 227, 30, 252, 212, 244, 251, 255, 228, 174, 31, 212, 161, 61, 184, 169, 200, 50, 7,
+            84, 65, 96,
+        ];
+        let element = bytes_to_element(&test_bytes).unwrap();
+        let fq = element_to_fq(&element);
+        let element_back = fq_to_element(&fq);
+
+        assert_eq!(element, element_back);
+        assert_eq!(fq, Fq::from_le_bytes_mod_order(&test_bytes));
+        assert_eq!(
+            bytes_to_element(&[0; 33]),
+            Err(SemaphoreError::InputSizeExceeded(33))
+        );
+    }
+
+    #[test]
+    fn test_bytes_to_element_endianness() {
+        let value = Fq::from(0x0102u64);
+
+        assert_eq!(
+            element_to_fq(&bytes_to_element(&[0x02, 0x01]).unwrap()),
+            value
+        );
+        assert_eq!(
+            element_to_fq(&bytes_to_element_be(&[0x01, 0x02]).unwrap()),
+            value
+        );
+
+        let bytes_be = value.into_bigint().to_bytes_be();
+        assert_eq!(
+            bytes_to_element_be(&bytes_be).unwrap(),
+            fq_to_element(&value)
+        );
+        assert_eq!(
+            bytes_to_element_be(&[0; 33]),
+            Err(SemaphoreError::InputSizeExceeded(33))
+        );
+    }
+
+    #[test]
+    fn test_endianness_newtypes() {
+        let fq = Fq::from(0x0102u64);
+        let le = LeElement::from(fq);
+        let be = BeBytes::from(fq);
+
+        assert_eq!(le.0[..2], [0x02, 0x01]);
+        assert_eq!(be.0[30..], [0x01, 0x02]);
+        assert_eq!(le.to_be(), be);
+        assert_eq!(be.to_le(), le);
+        assert_eq!(le.to_fq(), fq);
+        assert_eq!(Element::from(be), le.0);
+        assert_eq!(BigUint::from(le), BigUint::from(be));
+        assert_eq!(BeBytes::try_from(&BigUint::from(be)), Ok(be));
+        assert_eq!(bytes_to_element_be(&be.0), Ok(le.0));
+
+        let mut group = Group::default();
+        group.add_member(be).unwrap();
+        assert_eq!(group.index_of(le), Some(0));
+        assert_eq!(group.root_be().unwrap().to_le().0, group.root().unwrap());
+    }
+
+    #[test]
+    fn test_poseidon_hash() {
+        let one = fq_to_element(&Fq::from(1u8));
+        let two = fq_to_element(&Fq::from(2u8));
+
+        // poseidon2([1, 2]) from semaphore-js
+        assert_eq!(
+            BigUint::from_bytes_le(&poseidon_hash(&one, &two)).to_string(),
+            "7853200120776062878684798364095072458815029376092732009249414926327459813530"
+        );
+
+        let group = Group::new(&[one, two]).unwrap();
+        assert_eq!(group.root(), Some(poseidon_hash(&one, &two)));
+    }
+
+    #[test]
+    fn test_apply_all() {
+        let one = fq_to_element(&Fq::from(1u8));
+        let two = fq_to_element(&Fq::from(2u8));
+        let nine = fq_to_element(&Fq::from(9u8));
+
+        let mut group = Group::default();
+        group
+            .apply_all(&[
+                GroupOp::Add(one),
+                GroupOp::Add(nine),
+                GroupOp::Update(1, two),
+            ])
+            .unwrap();
+        // poseidon2([1, 2]) from semaphore-js
+        assert_eq!(
+            BigUint::from_bytes_le(&group.root().unwrap()).to_string(),
+            "7853200120776062878684798364095072458815029376092732009249414926327459813530"
+        );
+
+        group
+            .apply_all(&[GroupOp::Add(nine), GroupOp::Remove(0)])
+            .unwrap();
+        let mut expected = Group::new(&[one, two, nine]).unwrap();
+        expected.remove_member(0).unwrap();
+        assert_eq!(group.root(), expected.root());
+
+        // A failing operation reverts the whole log
+        let root = group.root();
+        for (op, error) in [
+            (GroupOp::Remove(0), SemaphoreError::AlreadyRemovedMember),
+            (GroupOp::Update(0, one), SemaphoreError::RemovedMember),
+            (GroupOp::Remove(4), LeanIMTError::IndexOutOfBounds.into()),
+            (GroupOp::Add(EMPTY_ELEMENT), SemaphoreError::EmptyLeaf),
+        ] {
+            assert_eq!(group.apply_all(&[GroupOp::Add(one), op]), Err(error));
+            assert_eq!(group.root(), root);
+            assert_eq!(group.size(), 3);
+        }
+    }
+
+    #[test]
+    fn test_try_hash() {
+        let mut input = [0u8; 2 * ELEMENT_SIZE];
+        input[..ELEMENT_SIZE].copy_from_slice(&[1; 32]);
+        input[ELEMENT_SIZE..].copy_from_slice(&[2; 32]);
+        assert_eq!(
+            PoseidonHash::try_hash(&input),
+            Ok(poseidon_hash(&[1; 32], &[2; 32]))
+        );
+        assert_eq!(
+            PoseidonHash::try_hash(&input[1..]),
+            Err(SemaphoreError::HashError(
+                "Expected 64 input bytes, got 63".to_string()
+            ))
+        );
+
+        let mut group = Group::default();
+        group.try_add_member([1; 32]).unwrap();
+        group.try_add_member([2; 32]).unwrap();
+        assert_eq!(group.root(), Some(poseidon_hash(&[1; 32], &[2; 32])));
+        assert_eq!(
+            group.try_add_member(EMPTY_ELEMENT),
+            Err(SemaphoreError::EmptyLeaf)
+        );
+    }
+
+    /// Hasher failing on every hash, like a Poseidon that cannot be initialized
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct FailingHash;
+
+    impl LeanIMTHasher<ELEMENT_SIZE> for FailingHash {
+        fn hash(_: &[u8]) -> [u8; ELEMENT_SIZE] {
+            panic!("Poseidon hash failed")
+        }
+    }
+
+    #[test]
+    fn test_try_add_member_hash_error() {
+        // A single member is the root, without hashing
+        let mut group = Group::with_hasher(&[], FailingHash).unwrap();
+        group.try_add_member([1; 32]).unwrap();
+        let before = group.clone();
+
+        assert_eq!(
+            group.try_add_member([2; 32]),
+            Err(SemaphoreError::HashError(
+                "Poseidon hash failed".to_string()
+            ))
+        );
+        assert_eq!(group, before);
+        assert_eq!(group.root(), Some([1; 32]));
+    }
+
+    /// Sum hasher failing when the right node is `[9; 32]`
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct PickyHash;
+
+    impl LeanIMTHasher<ELEMENT_SIZE> for PickyHash {
+        fn hash(input: &[u8]) -> [u8; ELEMENT_SIZE] {
+            assert!(input[ELEMENT_SIZE..] != [9; 32], "Picky hash failed");
+            std::array::from_fn(|i| input[i].wrapping_add(input[ELEMENT_SIZE + i]))
+        }
+    }
+
+    #[test]
+    fn test_try_add_member_path_error() {
+        let mut group = Group::with_hasher(&[], PickyHash).unwrap();
+        for member in 1..=6 {
+            group.try_add_member([member; 32]).unwrap();
+        }
+        assert_eq!(group.root(), Some([21; 32]));
+        let before = group.clone();
+
+        // Leaf 6 is a left node, `[9; 32]` is only hashed as the right node of level 1
+        assert_eq!(
+            group.try_add_member([9; 32]),
+            Err(SemaphoreError::HashError("Picky hash failed".to_string()))
+        );
+        assert_eq!(group, before);
+        group.try_add_member([8; 32]).unwrap();
+        assert_eq!(group.root(), Some([29; 32]));
+    }
+
+    #[test]
+    fn test_root_at_depth() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+
+        for depth in [2, 10, 32] {
+            assert_eq!(group.root_at_depth(depth), Ok(group.root().unwrap()));
+        }
+        assert_eq!(
+            group.root_at_depth(1),
+            Err(SemaphoreError::GroupTooLargeForDepth { size: 3, depth: 1 })
+        );
+        assert_eq!(
+            group.root_at_depth(33),
+            Err(SemaphoreError::TreeDepthOutOfRange(33))
+        );
+        assert_eq!(
+            Group::default().root_at_depth(10),
+            Err(SemaphoreError::EmptyGroup)
+        );
+    }
+
+    #[test]
+    fn test_root_changed_since() {
+        let mut group = Group::new(&[[1; 32], [2; 32]]).unwrap();
+        let root = group.root().unwrap();
+        assert!(!group.root_changed_since(&root));
+
+        group.add_member([3; 32]).unwrap();
+        assert!(group.root_changed_since(&root));
+        assert!(!group.root_changed_since(&group.root().unwrap()));
 
-    Ok(element)
-}
+        group.clear();
+        assert!(group.root_changed_since(&root));
+    }
 
-/// Converts a scalar to an element
-pub fn fq_to_element(fq: &Fq) -> Element {
-    let mut element = EMPTY_ELEMENT;
-    let bytes = fq.into_bigint().to_bytes_le();
-    element[..bytes.len()].copy_from_slice(&bytes);
-    element
-}
+    #[test]
+    fn test_root_reference() {
+        assert_eq!(Group::default().root_reference(), None);
+
+        // Pseudo-random members from Keccak, with some removed members
+        let member = |i: u32| {
+            BeBytes(crate::utils::hash_bytes(&i.to_be_bytes()))
+                .to_le()
+                .0
+        };
+        for size in 1..=40u32 {
+            let members: Vec<Element> = (0..size).map(member).collect();
+            let mut group = Group::new(&members).unwrap();
+            for index in (0..size as usize).step_by(7) {
+                group.remove_member(index).unwrap();
+            }
 
-/// Converts an element to a scalar
-pub fn element_to_fq(element: &Element) -> Fq {
-    Fq::from_le_bytes_mod_order(element)
-}
+            assert_eq!(group.root_reference(), group.root(), "size {size}");
+        }
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_inner() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let tree = group.inner();
+
+        assert_eq!(tree.leaves(), group.members());
+        assert_eq!(
+            tree.get_node(1, 0).unwrap(),
+            poseidon_hash(&[1; 32], &[2; 32])
+        );
+        // The last node has no sibling and moves up unchanged
+        assert_eq!(tree.get_node(1, 1).unwrap(), [3; 32]);
+        assert_eq!(tree.tree().root(), group.root());
+    }
 
+    #[cfg(feature = "rayon")]
     #[test]
-    fn test_conversions() {
-        let test_bytes = [
-            59, 227, 30, 252, 212, 244, 251, 255, 228, 174, 31, 212, 161, 61, 184, 169, 200, 50, 7,
-            84, 65, 96,
-        ];
-        let element = bytes_to_element(&test_bytes).unwrap();
-        let fq = element_to_fq(&element);
-        let element_back = fq_to_element(&fq);
+    fn test_new_parallel() {
+        let member = |i: u32| {
+            BeBytes(crate::utils::hash_bytes(&i.to_be_bytes()))
+                .to_le()
+                .0
+        };
+        for size in 0..=70 {
+            let members: Vec<Element> = (0..size).map(member).collect();
+            let group = Group::new_parallel(&members).unwrap();
+
+            assert_eq!(
+                group.root(),
+                Group::new(&members).unwrap().root(),
+                "size {size}"
+            );
+            assert_eq!(group, Group::new(&members).unwrap(), "size {size}");
+        }
 
-        assert_eq!(element, element_back);
-        assert_eq!(fq, Fq::from_le_bytes_mod_order(&test_bytes));
         assert_eq!(
-            bytes_to_element(&[0; 33]),
-            Err(SemaphoreError::InputSizeExceeded(33))
+            Group::new_parallel(&[[1; 32], EMPTY_ELEMENT]),
+            Err(SemaphoreError::EmptyLeaf)
         );
     }
 
@@ -228,6 +1626,19 @@ mod tests {
         assert_eq!(group.size(), 0);
     }
 
+    #[test]
+    fn test_is_empty() {
+        let mut group = Group::default();
+        assert!(group.is_empty());
+
+        group.add_member([1; 32]).unwrap();
+        assert!(!group.is_empty());
+        assert_eq!(group.depth(), 0);
+
+        group.remove_member(0).unwrap();
+        assert!(!group.is_empty());
+    }
+
     #[test]
     fn test_create_group_with_members() {
         let member1 = [1; 32];
@@ -246,6 +1657,19 @@ mod tests {
         assert_eq!(group1.size(), 3);
     }
 
+    #[test]
+    fn test_from_iter() {
+        let members = [[1; 32], [2; 32], [3; 32]];
+
+        let group: Group = members.into_iter().collect();
+        assert_eq!(group.root(), Group::new(&members).unwrap().root());
+        assert_eq!(Group::try_from_iter(members), Ok(group));
+        assert_eq!(
+            Group::try_from_iter([[1; 32], EMPTY_ELEMENT]),
+            Err(SemaphoreError::EmptyLeaf)
+        );
+    }
+
     #[test]
     fn test_create_group_with_zero_member() {
         let member1 = [1; 32];
@@ -369,6 +1793,22 @@ mod tests {
         assert_eq!(result, Err(SemaphoreError::AlreadyRemovedMember));
     }
 
+    #[test]
+    fn test_clear() {
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        group.clear();
+
+        assert_eq!(group.root(), None);
+        assert_eq!(group.size(), 0);
+        assert_eq!(group, Group::default());
+
+        group.add_members(&[[4; 32], [5; 32]]).unwrap();
+        assert_eq!(
+            group.root(),
+            Group::new(&[[4; 32], [5; 32]]).unwrap().root()
+        );
+    }
+
     #[test]
     fn test_generate_merkle_proof() {
         let member1 = [1; 32];
@@ -390,13 +1830,561 @@ mod tests {
         group.add_members(&[member1, member2]).unwrap();
 
         let proof_0 = group.generate_proof(0).unwrap();
-        assert_eq!(Group::verify_proof(&proof_0), true);
+        assert!(Group::verify_proof(&proof_0));
 
         let mut proof_1 = group.generate_proof(1).unwrap();
-        assert_eq!(Group::verify_proof(&proof_1), true);
+        assert!(Group::verify_proof(&proof_1));
 
         proof_1.leaf = member1;
-        assert_eq!(Group::verify_proof(&proof_1), false);
+        assert!(!Group::verify_proof(&proof_1));
+    }
+
+    /// Trivial hasher: the byte-wise wrapping sum of the two nodes
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct SumHash;
+
+    impl LeanIMTHasher<ELEMENT_SIZE> for SumHash {
+        fn hash(input: &[u8]) -> [u8; ELEMENT_SIZE] {
+            std::array::from_fn(|i| input[i].wrapping_add(input[ELEMENT_SIZE + i]))
+        }
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        let mut group = Group::with_hasher(&[[1; 32], [2; 32], [3; 32]], SumHash).unwrap();
+        assert_eq!(group.root(), Some([6; 32]));
+        assert_eq!(group.root_reference(), group.root());
+
+        group.add_member([4; 32]).unwrap();
+        group.remove_member(0).unwrap();
+        assert_eq!(group.root(), Some([9; 32]));
+
+        let proof = group.generate_proof(3).unwrap();
+        assert!(HashedLeanIMT::<ELEMENT_SIZE, SumHash>::verify_proof(&proof));
+        assert!(!Group::verify_proof(&proof));
+
+        let snapshot = group.snapshot();
+        group.clear();
+        assert!(group.is_empty());
+        group.restore(snapshot);
+        assert_eq!(group.prune_removed().unwrap().len(), 3);
+        assert_eq!(group.root(), Some([9; 32]));
+    }
+
+    #[test]
+    fn test_root_and_proof() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+
+        for index in 0..3 {
+            let (root, proof) = group.root_and_proof(index).unwrap();
+            assert_eq!(Some(root), group.root());
+            assert_eq!(proof, group.generate_proof(index).unwrap());
+        }
+        assert_eq!(
+            group.root_and_proof(3),
+            Err(SemaphoreError::LeanIMTError(LeanIMTError::IndexOutOfBounds))
+        );
+    }
+
+    #[test]
+    fn test_membership_bundle() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let bundle = group.membership_bundle(1).unwrap();
+        assert_eq!(Some(bundle.root), group.root());
+        assert_eq!(bundle.leaf, [2; 32]);
+        assert_eq!(
+            bundle.clone().into_merkle_proof(),
+            Ok(group.generate_proof(1).unwrap())
+        );
+
+        let mut other_root = bundle.clone();
+        other_root.root = [4; 32];
+        assert_eq!(
+            other_root.into_merkle_proof(),
+            Err(SemaphoreError::InvalidMerkleProof)
+        );
+        let mut other_leaf = bundle.clone();
+        other_leaf.leaf = [1; 32];
+        assert_eq!(
+            other_leaf.into_merkle_proof(),
+            Err(SemaphoreError::InvalidMerkleProof)
+        );
+        let mut tampered = bundle;
+        tampered.proof.siblings[0] = [5; 32];
+        assert_eq!(
+            tampered.into_merkle_proof(),
+            Err(SemaphoreError::InvalidMerkleProof)
+        );
+
+        assert!(group.membership_bundle(3).is_err());
+    }
+
+    #[test]
+    fn test_proof_cache() {
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        group.set_proof_cache(true);
+        assert!(group.is_proof_cache_enabled());
+        let cached = |group: &Group| {
+            group
+                .cache
+                .proofs
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .proofs
+                .len()
+        };
+
+        let proof = group.generate_proof(0).unwrap();
+        assert_eq!(group.generate_proof(0).unwrap(), proof);
+        assert_eq!(cached(&group), 1);
+
+        // Adding a member changes the root and the siblings of the proof
+        group.add_member([4; 32]).unwrap();
+        let updated = group.generate_proof(0).unwrap();
+        assert_ne!(updated, proof);
+        assert_eq!(Some(updated.root), group.root());
+        assert!(Group::verify_proof(&updated));
+        assert_eq!(cached(&group), 1);
+
+        group.remove_member(3).unwrap();
+        assert_eq!(group.generate_proof(0).unwrap().root, group.root().unwrap());
+
+        // Clones and restored groups do not reuse proofs of another tree
+        let snapshot = group.snapshot();
+        let clone = group.clone();
+        group.update_member(0, [5; 32]).unwrap();
+        assert_eq!(group.generate_proof(0).unwrap().leaf, [5; 32]);
+        assert_eq!(clone.generate_proof(0).unwrap().leaf, [1; 32]);
+        group.restore(snapshot);
+        assert_eq!(group.generate_proof(0).unwrap().leaf, [1; 32]);
+        assert_eq!(group, clone);
+
+        group.set_proof_cache(false);
+        assert!(!group.is_proof_cache_enabled());
+        assert_eq!(group.generate_proof(0).unwrap().leaf, [1; 32]);
+    }
+
+    #[test]
+    fn test_generate_proof_biguint() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let proof = group.generate_proof(2).unwrap();
+
+        let proof_big = group.generate_proof_biguint(2).unwrap();
+
+        assert_eq!(proof_big.root, element_to_biguint(&proof.root));
+        assert_eq!(proof_big.leaf, element_to_biguint(&[3; 32]));
+        assert_eq!(proof_big.index, proof.index);
+        assert_eq!(
+            proof_big.siblings,
+            proof
+                .siblings
+                .iter()
+                .map(element_to_biguint)
+                .collect::<Vec<_>>()
+        );
+        assert!(group.generate_proof_biguint(3).is_err());
+    }
+
+    #[test]
+    fn test_batch_generate_proofs() {
+        let members: Vec<Element> = (1..=9).map(|i| [i; 32]).collect();
+        let group = Group::new(&members).unwrap();
+        let indices = [8, 0, 3, 3, 5];
+
+        let proofs = group.batch_generate_proofs(&indices).unwrap();
+
+        assert_eq!(proofs.len(), indices.len());
+        for (proof, index) in proofs.iter().zip(indices) {
+            assert_eq!(*proof, group.generate_proof(index).unwrap());
+            assert_eq!(proof.leaf, members[index]);
+        }
+        assert!(group.batch_generate_proofs(&[]).unwrap().is_empty());
+        assert_eq!(
+            group.batch_generate_proofs(&[0, 9]),
+            Err(SemaphoreError::LeanIMTError(LeanIMTError::IndexOutOfBounds))
+        );
+    }
+
+    #[test]
+    fn test_verify_proof_batch() {
+        let group1 = Group::new(&[[1; 32], [2; 32], [3; 32], [4; 32], [5; 32]]).unwrap();
+        let group2 = Group::new(&[[6; 32], [7; 32]]).unwrap();
+
+        let mut proofs: Vec<MerkleProof> = (0..group1.size())
+            .map(|i| group1.generate_proof(i).unwrap())
+            .collect();
+        proofs.push(group2.generate_proof(1).unwrap());
+
+        let mut tampered_leaf = group1.generate_proof(2).unwrap();
+        tampered_leaf.leaf = [9; 32];
+        let mut tampered_sibling = group2.generate_proof(0).unwrap();
+        tampered_sibling.siblings[0] = [9; 32];
+        proofs.insert(1, tampered_leaf);
+        proofs.push(tampered_sibling);
+
+        let results = Group::verify_proof_batch(&proofs);
+        let expected: Vec<bool> = proofs.iter().map(Group::verify_proof).collect();
+
+        assert_eq!(results, expected);
+        assert_eq!(
+            results,
+            vec![true, false, true, true, true, true, true, false]
+        );
+        assert!(Group::verify_proof_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_contains_identity() {
+        let identity = Identity::new("secret".as_bytes());
+        let other = Identity::new("other".as_bytes());
+        let mut group = Group::new(&[[1; 32], identity.commitment_element().0]).unwrap();
+
+        assert!(group.contains_identity(&identity));
+        assert!(group.contains_commitment(identity.commitment()));
+        assert!(!group.contains_identity(&other));
+        assert!(!group.contains_commitment(other.commitment()));
+
+        group.remove_member(1).unwrap();
+        assert!(!group.contains_identity(&identity));
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut group = Group::new(&[[1; 32]]).unwrap();
+        group.extend((2..=4u8).map(|i| [i; 32])).unwrap();
+        group.extend(std::iter::empty()).unwrap();
+
+        let expected = Group::new(&[[1; 32], [2; 32], [3; 32], [4; 32]]).unwrap();
+        assert_eq!(group, expected);
+
+        // Nothing is added if a member is empty
+        assert_eq!(
+            group.extend([[5; 32], EMPTY_ELEMENT]),
+            Err(SemaphoreError::EmptyLeaf)
+        );
+        assert_eq!(group, expected);
+
+        let identities: Vec<Identity> = ["alice", "bob"]
+            .iter()
+            .map(|secret| Identity::new(secret.as_bytes()))
+            .collect();
+        group
+            .extend_commitments(identities.iter().map(|identity| *identity.commitment()))
+            .unwrap();
+        assert!(
+            identities
+                .iter()
+                .all(|identity| group.contains_identity(identity))
+        );
+        assert_eq!(group.size(), 6);
+    }
+
+    #[test]
+    fn test_insert_at() {
+        let mut group = Group::default();
+        group.insert_at(3, [4; 32]).unwrap();
+        group.insert_at(1, [2; 32]).unwrap();
+
+        assert_eq!(
+            group.members(),
+            vec![EMPTY_ELEMENT, [2; 32], EMPTY_ELEMENT, [4; 32]]
+        );
+        assert_eq!(group.index_of([4; 32]), Some(3));
+        assert_eq!(group.root(), group.root_reference());
+
+        // Same layout as a group whose members were removed
+        let mut mirror = Group::new(&[[1; 32], [2; 32], [3; 32], [4; 32]]).unwrap();
+        mirror.remove_member(0).unwrap();
+        mirror.remove_member(2).unwrap();
+        assert_eq!(group.root(), mirror.root());
+
+        assert_eq!(
+            group.insert_at(1, [5; 32]),
+            Err(SemaphoreError::OccupiedLeaf(1))
+        );
+        assert_eq!(
+            group.insert_at(0, EMPTY_ELEMENT),
+            Err(SemaphoreError::EmptyLeaf)
+        );
+        group.insert_at(4, [5; 32]).unwrap();
+        assert_eq!(group.size(), 5);
+
+        // Rejected before allocating the placeholders
+        for index in [1 << MAX_TREE_DEPTH, usize::MAX] {
+            assert_eq!(
+                group.insert_at(index, [6; 32]),
+                Err(SemaphoreError::GroupTooLargeForDepth {
+                    size: index.saturating_add(1),
+                    depth: MAX_TREE_DEPTH
+                })
+            );
+        }
+        let index = u32::MAX as usize;
+        assert_eq!(
+            Group::default().insert_at(index, [6; 32]),
+            Err(SemaphoreError::InsertGapTooLarge {
+                index,
+                gap: index,
+                max: MAX_INSERT_GAP
+            })
+        );
+        assert_eq!(
+            group.insert_at(5 + MAX_INSERT_GAP + 1, [6; 32]),
+            Err(SemaphoreError::InsertGapTooLarge {
+                index: 5 + MAX_INSERT_GAP + 1,
+                gap: MAX_INSERT_GAP + 1,
+                max: MAX_INSERT_GAP
+            })
+        );
+        assert_eq!(group.size(), 5);
+    }
+
+    #[test]
+    fn test_prune_removed() {
+        let members: Vec<Element> = (1..=6).map(|i| [i; 32]).collect();
+        let mut group = Group::new(&members).unwrap();
+        for index in [0, 2, 3] {
+            group.remove_member(index).unwrap();
+        }
+        let root = group.root();
+
+        let indexes = group.prune_removed().unwrap();
+
+        assert_eq!(indexes, HashMap::from([(1, 0), (4, 1), (5, 2)]));
+        assert_eq!(group.members(), vec![[2; 32], [5; 32], [6; 32]]);
+        assert_ne!(group.root(), root);
+        assert_eq!(group.depth(), 2);
+        assert_eq!(
+            group.root(),
+            Group::new(&[[2; 32], [5; 32], [6; 32]]).unwrap().root()
+        );
+        for (old, new) in indexes {
+            assert_eq!(group.members()[new], members[old]);
+        }
+
+        group.remove_member(0).unwrap();
+        group.remove_member(1).unwrap();
+        group.remove_member(2).unwrap();
+        assert!(group.prune_removed().unwrap().is_empty());
+        assert!(group.is_empty());
+    }
+
+    #[test]
+    fn test_element_cmp() {
+        let two = bytes_to_element(&[2]).unwrap();
+        let two_hundred_fifty_seven = bytes_to_element(&[1, 1]).unwrap();
+        let modulus: Element = Fq::MODULUS.to_bytes_le().try_into().unwrap();
+        let mut modulus_plus_two = modulus;
+        modulus_plus_two[0] += 2;
+
+        // The bytes and the field values are in opposite orders
+        assert_eq!(two.cmp(&two_hundred_fifty_seven), Ordering::Greater);
+        assert_eq!(element_cmp(&two, &two_hundred_fifty_seven), Ordering::Less);
+        assert_eq!(element_cmp(&two, &two), Ordering::Equal);
+        assert_eq!(element_cmp(&modulus_plus_two, &two), Ordering::Equal);
+        assert_eq!(
+            element_cmp(&modulus_plus_two, &two_hundred_fifty_seven),
+            Ordering::Less
+        );
+
+        let mut group = Group::new(&[two_hundred_fifty_seven, [1; 32], two]).unwrap();
+        group.remove_member(1).unwrap();
+        assert_eq!(group.sorted_members(), vec![two, two_hundred_fifty_seven]);
+    }
+
+    #[test]
+    fn test_canonical_element() {
+        let modulus: Element = Fq::MODULUS.to_bytes_le().try_into().unwrap();
+        let mut largest = modulus;
+        largest[0] -= 1;
+
+        assert!(is_canonical_element(&[1; 32]));
+        assert!(is_canonical_element(&largest));
+        assert!(!is_canonical_element(&modulus));
+        assert!(!is_canonical_element(&[255; 32]));
+
+        // The modulus is reduced to zero when hashed
+        let mut group = Group::default();
+        group.add_member(modulus).unwrap();
+
+        let mut strict = Group::default();
+        strict.set_strict(true);
+        assert!(strict.is_strict());
+        assert_eq!(
+            strict.add_member(modulus),
+            Err(SemaphoreError::NonCanonicalElement)
+        );
+        assert_eq!(
+            strict.add_members(&[[1; 32], [255; 32]]),
+            Err(SemaphoreError::NonCanonicalElement)
+        );
+        assert_eq!(
+            strict.extend([[1; 32], [255; 32]]),
+            Err(SemaphoreError::NonCanonicalElement)
+        );
+        assert!(strict.is_empty());
+
+        strict.add_members(&[[1; 32], largest]).unwrap();
+        assert_eq!(strict.size(), 2);
+
+        // Updates are checked like additions, directly or replayed
+        assert_eq!(
+            strict.update_member(0, modulus),
+            Err(SemaphoreError::NonCanonicalElement)
+        );
+        assert_eq!(
+            strict.apply(GroupOp::Update(1, [255; 32])),
+            Err(SemaphoreError::NonCanonicalElement)
+        );
+        assert_eq!(strict.members(), vec![[1; 32], largest]);
+        group.update_member(0, [255; 32]).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let root = group.root();
+        let snapshot = group.snapshot();
+
+        group.add_member([4; 32]).unwrap();
+        group.remove_member(0).unwrap();
+        group.update_member(1, [5; 32]).unwrap();
+        assert!(group.update_member(0, [6; 32]).is_err());
+        assert_ne!(group.root(), root);
+
+        group.restore(snapshot.clone());
+        assert_eq!(group.root(), root);
+        assert_eq!(snapshot.root(), root);
+        assert_eq!(group.members(), vec![[1; 32], [2; 32], [3; 32]]);
+        assert_eq!(group, Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap());
+
+        // The restored group keeps working
+        group.add_member([4; 32]).unwrap();
+        assert_eq!(group.root(), group.root_reference());
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        group.remove_member(1).unwrap();
+        let checkpoint = group.export_checkpoint();
+        assert_eq!(checkpoint.len(), 45 + 3 * 32);
+
+        let imported = Group::import_checkpoint(&checkpoint).unwrap();
+        assert_eq!(imported, group);
+        assert_eq!(imported.root(), group.root());
+        let empty = Group::import_checkpoint(&Group::default().export_checkpoint()).unwrap();
+        assert_eq!(empty, Group::default());
+
+        let invalid = |reason: &str| Err(SemaphoreError::InvalidCheckpoint(reason.to_string()));
+        // A corrupted leaf no longer hashes to the root
+        let mut corrupted = checkpoint.clone();
+        corrupted[45] ^= 1;
+        assert_eq!(
+            Group::import_checkpoint(&corrupted),
+            invalid("the root does not match the leaves")
+        );
+        assert_eq!(
+            Group::import_checkpoint(&checkpoint[..checkpoint.len() - 1]),
+            invalid("95 bytes of leaves for a size of 3")
+        );
+        assert_eq!(
+            Group::import_checkpoint(&checkpoint[..10]),
+            invalid("10 bytes is shorter than the header")
+        );
+        let mut version = checkpoint.clone();
+        version[4] = 2;
+        assert_eq!(
+            Group::import_checkpoint(&version),
+            invalid("unsupported version 2")
+        );
+        assert_eq!(
+            Group::import_checkpoint(&[0; 45]),
+            invalid("not a group checkpoint")
+        );
+
+        // Non-canonical members round-trip unless the import is strict
+        let group = Group::new(&[[1; 32], [255; 32]]).unwrap();
+        let checkpoint = group.export_checkpoint();
+        assert_eq!(Group::import_checkpoint(&checkpoint).unwrap(), group);
+        assert_eq!(
+            Group::import_checkpoint_strict(&checkpoint),
+            invalid("leaf 1 is not a canonical field element")
+        );
+        let strict = Group::import_checkpoint_strict(&imported.export_checkpoint()).unwrap();
+        assert!(strict.is_strict());
+        assert!(strict.root_eq(&imported));
+    }
+
+    #[test]
+    fn test_diff() {
+        let member1 = [1; 32];
+        let member2 = [2; 32];
+        let member3 = [3; 32];
+        let member4 = [4; 32];
+        let group = Group::new(&[member1, member2, member3]).unwrap();
+
+        assert!(group.diff(&group.clone()).is_empty());
+
+        let mut other = group.clone();
+        other.add_member(member4).unwrap();
+        other.remove_member(0).unwrap();
+        other.update_member(2, member4).unwrap();
+
+        let diff = group.diff(&other);
+        assert_eq!(diff.added, vec![member4]);
+        assert_eq!(diff.removed, vec![0]);
+        assert_eq!(diff.updated, vec![(2, member4)]);
+
+        let diff = other.diff(&group);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed, vec![3]);
+        assert_eq!(diff.updated, vec![(0, member1), (2, member3)]);
+    }
+
+    #[test]
+    fn test_diff_apply() {
+        let apply = |group: &Group, other: &Group| {
+            let diff = group.diff(other);
+            let mut applied = group.clone();
+            for &(index, member) in &diff.updated {
+                if applied.members()[index] == EMPTY_ELEMENT {
+                    applied.insert_at(index, member).unwrap();
+                } else {
+                    applied.update_member(index, member).unwrap();
+                }
+            }
+            applied.extend(diff.added.iter().copied()).unwrap();
+            for &index in &diff.removed {
+                applied.remove_member(index).unwrap();
+            }
+            applied
+        };
+
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        group.remove_member(1).unwrap();
+        let mut other =
+            Group::new(&[[4; 32], [5; 32], [3; 32], [6; 32], [7; 32], [8; 32]]).unwrap();
+        other.remove_member(0).unwrap();
+        other.remove_member(4).unwrap();
+
+        let diff = group.diff(&other);
+        assert_eq!(diff.added, vec![[6; 32], GroupDiff::PLACEHOLDER, [8; 32]]);
+        assert_eq!(diff.removed, vec![0, 4]);
+        assert_eq!(diff.updated, vec![(1, [5; 32])]);
+        assert_eq!(apply(&group, &other), other);
+
+        // Shorter groups leave empty leaves behind
+        let applied = apply(&other, &group);
+        assert_eq!(&applied.members()[..3], group.members());
+        assert!(
+            applied.members()[3..]
+                .iter()
+                .all(|&leaf| leaf == EMPTY_ELEMENT)
+        );
+        assert_ne!(applied.root(), group.root());
     }
 
     #[cfg(feature = "serde")]
@@ -412,4 +2400,120 @@ mod tests {
 
         assert_eq!(group, imported_group);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_membership_bundle_serde() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let bundle = group.membership_bundle(2).unwrap();
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        let imported: MembershipBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(imported, bundle);
+        assert_eq!(
+            imported.into_merkle_proof(),
+            Ok(group.generate_proof(2).unwrap())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_root_eq() {
+        let mut group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        group.set_strict(true);
+        let imported_group = Group::import(&group.export().unwrap()).unwrap();
+
+        // The strict mode is not exported
+        assert_ne!(group, imported_group);
+        assert!(group.root_eq(&imported_group));
+
+        group.remove_member(2).unwrap();
+        assert!(!group.root_eq(&imported_group));
+        assert!(Group::default().root_eq(&Group::default()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_import_invalid_leaf() {
+        let group = Group::new(&[[1; 32], [2; 32], [3; 32]]).unwrap();
+        let mut json: serde_json::Value = serde_json::from_str(&group.export().unwrap()).unwrap();
+
+        // Larger than the field modulus
+        json["nodes"][0][1] = serde_json::to_value([255u8; 32]).unwrap();
+        assert_eq!(
+            Group::import_strict(&json.to_string()),
+            Err(SemaphoreError::SerializationError(
+                "Leaf 1 is not a canonical field element".to_string()
+            ))
+        );
+        assert!(
+            Group::import_strict(&group.export().unwrap())
+                .unwrap()
+                .is_strict()
+        );
+
+        // Nodes that do not match the leaves
+        let [member1, member2, member3] = [[1; 32], [2; 32], [3; 32]].map(serde_json::Value::from);
+        let node = |level: usize, index: usize| {
+            Err(SemaphoreError::SerializationError(format!(
+                "Node {index} of level {level} does not match the leaves"
+            )))
+        };
+        let tampered = serde_json::json!({"nodes": [[member1, member2], [member3]]});
+        assert_eq!(Group::import(&tampered.to_string()), node(1, 0));
+        let missing = serde_json::json!({"nodes": [[member1, member2, member3], []]});
+        assert_eq!(Group::import(&missing.to_string()), node(1, 0));
+        let extra = serde_json::json!({"nodes": [[member1], [member1]]});
+        assert_eq!(
+            Group::import_from_reader(extra.to_string().as_bytes()),
+            node(1, 0)
+        );
+        let mut json: serde_json::Value = serde_json::from_str(&group.export().unwrap()).unwrap();
+        json["nodes"][2][0] = member1.clone();
+        assert_eq!(Group::import(&json.to_string()), node(2, 0));
+        assert_eq!(Group::import(r#"{"nodes": []}"#).unwrap(), Group::default());
+
+        assert!(matches!(
+            Group::import(r#"{"nodes": [[[1, 2]]]}"#),
+            Err(SemaphoreError::SerializationError(e)) if e.starts_with("Invalid LeanIMT")
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_export_import_non_canonical_member() {
+        // Accepted by groups that are not strict
+        let group = Group::new(&[[1; 32], [255; 32]]).unwrap();
+
+        let imported = Group::import(&group.export().unwrap()).unwrap();
+        assert_eq!(imported, group);
+        let imported = Group::import_from_reader(group.export().unwrap().as_bytes()).unwrap();
+        assert_eq!(imported, group);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_export_import_streaming() {
+        use std::{
+            fs::File,
+            io::{BufReader, BufWriter},
+        };
+
+        let members: Vec<Element> = (1..=4096u32)
+            .map(|i| bytes_to_element(&i.to_le_bytes()).unwrap())
+            .collect();
+        let group = Group::new(&members).unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("semaphore-group-{}.json", std::process::id()));
+        group
+            .export_to_writer(BufWriter::new(File::create(&path).unwrap()))
+            .unwrap();
+        let imported_group =
+            Group::import_from_reader(BufReader::new(File::open(&path).unwrap())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(group, imported_group);
+        assert_eq!(imported_group.size(), 4096);
+    }
 }
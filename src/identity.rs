@@ -1,19 +1,36 @@
 //! Identity Module
 
 use crate::{
-    baby_jubjub::{BabyJubjubConfig, EdwardsAffine},
+    baby_jubjub::{
+        BabyJubjubConfig, EdwardsAffine, EdwardsProjective, clamp_scalar, pack_point, unpack_point,
+    },
     error::SemaphoreError,
+    group::{BeBytes, LeElement},
+    utils::{hash_bytes, scalar_from_bytes},
 };
 use ark_ec::{CurveConfig, CurveGroup, twisted_edwards::TECurveConfig};
 use ark_ed_on_bn254::{Fq, Fr};
 use ark_ff::{BigInteger, PrimeField};
+#[cfg(feature = "serde")]
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use blake::Blake;
+use ethers_core::utils::hex;
 use light_poseidon::{Poseidon, PoseidonHasher};
 use num_bigint::{BigInt, Sign};
-use std::ops::Mul;
+use std::{fmt, ops::Mul, str::FromStr};
+use subtle::ConstantTimeEq;
+
+/// Domain separator of the scoped identities, see [`Identity::derive_scoped`]
+const SCOPED_DOMAIN: &[u8] = b"semaphore-rs/scoped-identity";
+
+/// Domain separator of the seeded identities, see [`Identity::from_seed`]
+const SEED_DOMAIN: &[u8] = b"semaphore-rs/seed-identity";
 
 /// Semaphore identity
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// `Debug` prints the public key and commitment only, the private key and secret scalar are
+/// left out so that logging an identity does not leak them.
+#[derive(Clone, PartialEq, Eq)]
 pub struct Identity {
     /// Private key
     private_key: Vec<u8>,
@@ -45,6 +62,22 @@ impl Identity {
         }
     }
 
+    /// Creates an identity from a 32-byte seed, separated by a domain, e.g. an app name
+    ///
+    /// The private key is the Blake 512 hash of
+    /// `"semaphore-rs/seed-identity" || len(domain) as u64 LE || domain || seed`, then the
+    /// identity is derived as with [`Identity::new`]. The same seed gives unrelated identities
+    /// in different domains.
+    pub fn from_seed(seed: &[u8; 32], domain: &[u8]) -> Identity {
+        let mut input = Vec::with_capacity(SEED_DOMAIN.len() + 8 + domain.len() + seed.len());
+        input.extend_from_slice(SEED_DOMAIN);
+        input.extend_from_slice(&(domain.len() as u64).to_le_bytes());
+        input.extend_from_slice(domain);
+        input.extend_from_slice(seed);
+
+        Identity::new(&blake_512(&input))
+    }
+
     /// Returns the private key
     pub fn private_key(&self) -> &[u8] {
         &self.private_key
@@ -55,7 +88,7 @@ impl Identity {
         &self.secret_scalar
     }
 
-    /// Returns the public key
+    /// Returns the public key, the secret scalar times the subgroup generator
     pub fn public_key(&self) -> &PublicKey {
         &self.public_key
     }
@@ -65,30 +98,88 @@ impl Identity {
         &self.commitment
     }
 
+    /// Returns the identity commitment as a group member (little-endian)
+    pub fn commitment_element(&self) -> LeElement {
+        LeElement::from(self.commitment)
+    }
+
+    /// Returns the identity commitment as an on-chain `uint256` (big-endian)
+    pub fn commitment_bytes(&self) -> BeBytes {
+        BeBytes::from(self.commitment)
+    }
+
+    /// Returns true if both identities have the same secret scalar and commitment
+    ///
+    /// The derived `PartialEq` also compares the stored private key bytes, so two identities
+    /// holding different representations of the same key are not equal with `==`.
+    /// The secret scalar and commitment are what define an identity in the protocol.
+    pub fn same_as(&self, other: &Identity) -> bool {
+        self.secret_scalar == other.secret_scalar && self.commitment == other.commitment
+    }
+
+    /// Derives the identity of a scope, e.g. an app or a context, from this identity
+    ///
+    /// The private key of the scoped identity is the Blake 512 hash of this private key and
+    /// the scope, so it is recovered from the root private key and the scope alone. Without
+    /// the root private key, the commitments of different scopes cannot be linked to each
+    /// other or to the root commitment.
+    pub fn derive_scoped(&self, scope: &[u8]) -> Identity {
+        // The private key length separates the key from the scope
+        let mut input =
+            Vec::with_capacity(SCOPED_DOMAIN.len() + 8 + self.private_key.len() + scope.len());
+        input.extend_from_slice(SCOPED_DOMAIN);
+        input.extend_from_slice(&(self.private_key.len() as u64).to_le_bytes());
+        input.extend_from_slice(&self.private_key);
+        input.extend_from_slice(scope);
+
+        Identity::new(&blake_512(&input))
+    }
+
+    /// Signs a challenge to prove the ownership of the identity commitment
+    ///
+    /// The challenge can be of any length, it is hashed with [`hash_bytes`] before signing.
+    /// Verify it with [`PublicKey::verify_ownership`].
+    pub fn prove_ownership(&self, challenge: &[u8]) -> Signature {
+        self.sign_message(&hash_bytes(challenge))
+            .expect("The challenge hash fits in 32 bytes")
+    }
+
     /// Signs a message
     pub fn sign_message(&self, message: &[u8]) -> Result<Signature, SemaphoreError> {
         if message.len() > 32 {
             return Err(SemaphoreError::MessageSizeExceeded(message.len()));
         }
 
-        // Hash the private key and prune
-        let mut priv_key_hash = blake_512(&self.private_key);
-        priv_key_hash[0] &= 0xF8;
-        priv_key_hash[31] &= 0x7F;
-        priv_key_hash[31] |= 0x40;
+        self.sign_message_with_nonce(message, self.nonce(message))
+    }
 
-        // Prepare the message in little-endian format
-        let mut message_le = message.to_vec();
-        message_le.reverse();
+    /// Signs a message and verifies the signature against the identity public key
+    ///
+    /// A self-consistency check: if the public key does not match the private key, e.g. after
+    /// a corruption, this returns [`SemaphoreError::SignatureVerificationFailed`] instead of
+    /// a signature nobody can verify.
+    pub fn sign_and_verify(&self, message: &[u8]) -> Result<Signature, SemaphoreError> {
+        let signature = self.sign_message(message)?;
+        signature.verify(&self.public_key, message)?;
+        Ok(signature)
+    }
 
-        // Compute ephemeral nonce scalar
-        let mut k_input = [0u8; 64];
-        k_input[..32].copy_from_slice(&priv_key_hash[32..]);
-        k_input[32..32 + message.len()].copy_from_slice(&message_le);
-        let k_fr = Fr::from_le_bytes_mod_order(&blake_512(&k_input));
+    /// Signs a message with a caller-supplied nonce `k`
+    ///
+    /// For test vectors and other nonce derivation schemes, [`Identity::sign_message`] derives
+    /// `k` from the private key and the message. The nonce must be secret and never reused
+    /// for another message: two signatures with the same `k` reveal the secret scalar.
+    pub fn sign_message_with_nonce(
+        &self,
+        message: &[u8],
+        k: Fr,
+    ) -> Result<Signature, SemaphoreError> {
+        if message.len() > 32 {
+            return Err(SemaphoreError::MessageSizeExceeded(message.len()));
+        }
 
         // Calculate ephemeral point r = k * base point
-        let r = BabyJubjubConfig::GENERATOR.mul(k_fr).into_affine();
+        let r = BabyJubjubConfig::GENERATOR.mul(k).into_affine();
 
         // Compute challenge scalar
         let poseidon_inputs = [
@@ -105,23 +196,40 @@ impl Identity {
         let c_fr = Fr::from_le_bytes_mod_order(&c_fq.into_bigint().to_bytes_le());
 
         // Calculate secret scalar (without dividing by cofactor)
-        let secret_scalar = Fr::from_le_bytes_mod_order(&priv_key_hash[..32]);
+        let secret_scalar = Fr::from_le_bytes_mod_order(&self.private_key_hash()[..32]);
 
         // s = nonce + challenge * secret
-        let s = k_fr + c_fr * secret_scalar;
+        let s = k + c_fr * secret_scalar;
 
         Ok(Signature::new(r, s))
     }
 
+    /// Hashes the private key and prunes it
+    fn private_key_hash(&self) -> [u8; 64] {
+        let mut priv_key_hash = blake_512(&self.private_key);
+        clamp_scalar((&mut priv_key_hash[..32]).try_into().unwrap());
+        priv_key_hash
+    }
+
+    /// Derives the ephemeral nonce scalar from the private key hash and the message
+    fn nonce(&self, message: &[u8]) -> Fr {
+        // Prepare the message in little-endian format
+        let mut message_le = message.to_vec();
+        message_le.reverse();
+
+        let mut k_input = [0u8; 64];
+        k_input[..32].copy_from_slice(&self.private_key_hash()[32..]);
+        k_input[32..32 + message.len()].copy_from_slice(&message_le);
+        Fr::from_le_bytes_mod_order(&blake_512(&k_input))
+    }
+
     /// Generates the secret scalar from the private key
     fn gen_secret_scalar(private_key: &[u8]) -> Fr {
         // Hash the private key
         let mut hash = blake_512(private_key);
 
         // Prune hash
-        hash[0] &= 0xF8;
-        hash[31] &= 0x7F;
-        hash[31] |= 0x40;
+        clamp_scalar((&mut hash[..32]).try_into().unwrap());
 
         // Use first half of hash and divide by cofactor (equivalent to shifting right by 3 bits)
         let shifted: BigInt = BigInt::from_bytes_le(Sign::Plus, &hash[..32]) >> 3;
@@ -131,6 +239,10 @@ impl Identity {
 }
 
 /// Semaphore public key
+///
+/// A point of the prime-order subgroup, generated by [`subgroup_generator`].
+///
+/// [`subgroup_generator`]: crate::baby_jubjub::subgroup_generator
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PublicKey {
     point: EdwardsAffine,
@@ -143,6 +255,9 @@ impl PublicKey {
     }
 
     /// Creates a new subgroup public key from a scalar
+    ///
+    /// The point is `secret_scalar * subgroup_generator()`, the EIP-2494 base point, not the
+    /// curve generator.
     pub fn from_scalar(secret_scalar: &Fr) -> Self {
         let point = BabyJubjubConfig::GENERATOR.mul(secret_scalar).into_affine();
 
@@ -157,6 +272,23 @@ impl PublicKey {
             .unwrap()
     }
 
+    /// Verifies that the signer of a challenge owns `commitment`
+    ///
+    /// Checks that `public_key` is the key behind `commitment` and that `signature` was produced
+    /// by [`Identity::prove_ownership`] over `challenge`, e.g. before adding it to a group.
+    pub fn verify_ownership(
+        commitment: &Fq,
+        public_key: &PublicKey,
+        challenge: &[u8],
+        signature: &Signature,
+    ) -> Result<(), SemaphoreError> {
+        if public_key.commitment() != *commitment {
+            return Err(SemaphoreError::CommitmentMismatch);
+        }
+
+        signature.verify(public_key, &hash_bytes(challenge))
+    }
+
     /// Returns the public key point in Affine form
     pub fn point(&self) -> EdwardsAffine {
         self.point
@@ -173,8 +305,79 @@ impl PublicKey {
     }
 }
 
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Identity")
+            .field("public_key", &self.public_key)
+            .field("commitment", &self.commitment)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Formats the public key as the hex of its packed point (circomlibjs `packPoint`)
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", hex::encode(pack_point(&self.point)))
+    }
+}
+
+/// Parses a public key formatted with `Display`, with an optional `0x` prefix
+impl FromStr for PublicKey {
+    type Err = SemaphoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let packed = decode_hex_32(s, "public key")?;
+
+        Ok(PublicKey::from_point(unpack_public_key(&packed)?))
+    }
+}
+
+/// JWK curve name of Baby Jubjub, which has no registered identifier
+#[cfg(feature = "serde")]
+pub const JWK_CURVE: &str = "BabyJubJub";
+
+#[cfg(feature = "serde")]
+impl PublicKey {
+    /// Exports the public key as a JWK
+    ///
+    /// Like Ed25519 keys (RFC 8037) the key type is `OKP` and `x` is the base64url encoded
+    /// packed point (circomlibjs `packPoint`). The curve is [`JWK_CURVE`].
+    pub fn to_jwk(&self) -> serde_json::Value {
+        serde_json::json!({
+            "kty": "OKP",
+            "crv": JWK_CURVE,
+            "x": URL_SAFE_NO_PAD.encode(pack_point(&self.point)),
+        })
+    }
+
+    /// Imports a public key from a JWK exported with [`PublicKey::to_jwk`]
+    pub fn from_jwk(jwk: &serde_json::Value) -> Result<Self, SemaphoreError> {
+        if jwk["kty"] != "OKP" || jwk["crv"] != JWK_CURVE {
+            return Err(SemaphoreError::SerializationError(format!(
+                "Expected an OKP {JWK_CURVE} key"
+            )));
+        }
+
+        let x = jwk["x"]
+            .as_str()
+            .ok_or_else(|| SemaphoreError::SerializationError("Missing x".to_string()))?;
+        let packed: [u8; 32] = URL_SAFE_NO_PAD
+            .decode(x)
+            .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?
+            .try_into()
+            .map_err(|_| SemaphoreError::SerializationError("Invalid x length".to_string()))?;
+
+        Ok(PublicKey::from_point(unpack_public_key(&packed)?))
+    }
+}
+
 /// Signature
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "SignatureJson", into = "SignatureJson")
+)]
 pub struct Signature {
     /// `r` point
     pub r: EdwardsAffine,
@@ -189,7 +392,54 @@ impl Signature {
     }
 
     /// Verifies against a public key and message
+    ///
+    /// `s` and `s + l` satisfy the same equation, `l` being the subgroup order. `s` is an
+    /// [`Fr`], whose modulus is `l`, so it is always reduced here: signatures are parsed with
+    /// [`Signature::from_str`] and serde, which reject a non-reduced `s` with
+    /// [`SemaphoreError::MalleableSignature`] instead of reducing it.
     pub fn verify(&self, public_key: &PublicKey, message: &[u8]) -> Result<(), SemaphoreError> {
+        let (left, right) = self.verification_points(public_key, message)?;
+
+        if left != right {
+            return Err(SemaphoreError::SignatureVerificationFailed);
+        }
+
+        Ok(())
+    }
+
+    /// Verifies against a public key and message, comparing the points in constant time
+    ///
+    /// [`Signature::verify`] compares the points with `!=`, which can exit on the first differing
+    /// limb and tell an attacker timing many forged signatures how close they got. This compares
+    /// the encoded points with [`subtle`] instead. The scalar multiplications themselves are not
+    /// constant-time in arkworks, but they only depend on public inputs (signature, public key
+    /// and message). The extra affine conversion makes this a few percent slower.
+    pub fn verify_ct(&self, public_key: &PublicKey, message: &[u8]) -> Result<(), SemaphoreError> {
+        let (left, right) = self.verification_points(public_key, message)?;
+        let (left, right) = (left.into_affine(), right.into_affine());
+
+        let left_bytes = [
+            left.x.into_bigint().to_bytes_le(),
+            left.y.into_bigint().to_bytes_le(),
+        ];
+        let right_bytes = [
+            right.x.into_bigint().to_bytes_le(),
+            right.y.into_bigint().to_bytes_le(),
+        ];
+
+        if !bool::from(left_bytes.concat().ct_eq(&right_bytes.concat())) {
+            return Err(SemaphoreError::SignatureVerificationFailed);
+        }
+
+        Ok(())
+    }
+
+    /// Computes both sides of `s * generator = r + challenge * public_key`
+    fn verification_points(
+        &self,
+        public_key: &PublicKey,
+        message: &[u8],
+    ) -> Result<(EdwardsProjective, EdwardsProjective), SemaphoreError> {
         if message.len() > 32 {
             return Err(SemaphoreError::MessageSizeExceeded(message.len()));
         }
@@ -202,6 +452,14 @@ impl Signature {
             return Err(SemaphoreError::PublicKeyNotOnCurve);
         }
 
+        // The challenge is multiplied by the cofactor, which clears any small-order component
+        if !public_key
+            .point()
+            .is_in_correct_subgroup_assuming_on_curve()
+        {
+            return Err(SemaphoreError::PublicKeyNotInSubgroup);
+        }
+
         // Compute challenge scalar
         let poseidon_inputs = [
             self.r.x,
@@ -219,18 +477,115 @@ impl Signature {
         // Multiply challenge scalar by cofactor
         c_fr *= Fr::from_be_bytes_mod_order(&[BabyJubjubConfig::COFACTOR[0] as u8]);
 
-        // s * generator
+        // s * subgroup generator
         let left = BabyJubjubConfig::GENERATOR.mul(self.s);
 
         // nonce + challenge * public_key
         let right = self.r + public_key.point().mul(c_fr);
 
-        // s * generator = nonce + challenge * public_key
-        if left != right {
-            return Err(SemaphoreError::SignatureVerificationFailed);
+        Ok((left, right))
+    }
+}
+
+/// Formats the signature as `r:s`, the hex of the packed `r` point and of `s` in little-endian
+///
+/// The two parts are the halves of circomlibjs `packSignature`.
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = [0u8; 32];
+        let s_bytes = self.s.into_bigint().to_bytes_le();
+        s[..s_bytes.len()].copy_from_slice(&s_bytes);
+
+        write!(f, "{}:{}", hex::encode(pack_point(&self.r)), hex::encode(s))
+    }
+}
+
+/// Parses a signature formatted with `Display`, with optional `0x` prefixes
+impl FromStr for Signature {
+    type Err = SemaphoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (r, s) = s
+            .split_once(':')
+            .ok_or_else(|| SemaphoreError::SerializationError("Expected r:s".to_string()))?;
+
+        let r = unpack_signature_point(&decode_hex_32(r, "r")?)?;
+        let s = scalar_from_bytes(&decode_hex_32(s, "s")?)
+            .map_err(|_| SemaphoreError::MalleableSignature)?;
+
+        Ok(Signature::new(r, s))
+    }
+}
+
+/// Unpacks a public key, rejecting points outside the prime-order subgroup
+///
+/// Signatures are verified with the challenge multiplied by the cofactor, so `A + T`, with
+/// `T` a small-order point, would verify the signatures of `A` under another commitment.
+fn unpack_public_key(packed: &[u8; 32]) -> Result<EdwardsAffine, SemaphoreError> {
+    let point = unpack_point(packed).ok_or(SemaphoreError::PublicKeyNotOnCurve)?;
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(SemaphoreError::PublicKeyNotInSubgroup);
+    }
+
+    Ok(point)
+}
+
+/// Unpacks the `r` point of a signature, rejecting points outside the prime-order subgroup
+fn unpack_signature_point(packed: &[u8; 32]) -> Result<EdwardsAffine, SemaphoreError> {
+    let point = unpack_point(packed).ok_or(SemaphoreError::SignaturePointNotOnCurve)?;
+    if !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(SemaphoreError::SignaturePointNotInSubgroup);
+    }
+
+    Ok(point)
+}
+
+/// Decodes 32 hex encoded bytes, with an optional `0x` prefix
+fn decode_hex_32(value: &str, name: &str) -> Result<[u8; 32], SemaphoreError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|e| SemaphoreError::SerializationError(format!("Invalid {name}: {e}")))?
+        .try_into()
+        .map_err(|_| SemaphoreError::SerializationError(format!("Invalid {name} length")))
+}
+
+/// Serialized signature: `r` as hex packed point bytes and `s` as a decimal string
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SignatureJson {
+    r: String,
+    s: String,
+}
+
+#[cfg(feature = "serde")]
+impl From<Signature> for SignatureJson {
+    fn from(signature: Signature) -> Self {
+        Self {
+            r: hex::encode(pack_point(&signature.r)),
+            s: signature.s.to_string(),
         }
+    }
+}
 
-        Ok(())
+#[cfg(feature = "serde")]
+impl TryFrom<SignatureJson> for Signature {
+    type Error = SemaphoreError;
+
+    fn try_from(json: SignatureJson) -> Result<Self, Self::Error> {
+        let r_bytes: [u8; 32] = hex::decode(json.r.trim_start_matches("0x"))
+            .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?
+            .try_into()
+            .map_err(|_| SemaphoreError::SerializationError("Invalid r length".to_string()))?;
+        let r = unpack_signature_point(&r_bytes)?;
+        // `Fr::from_str` reduces values above the modulus, which would accept `s + l`
+        let s = num_bigint::BigUint::from_str(&json.s)
+            .map_err(|_| SemaphoreError::SerializationError("Invalid s scalar".to_string()))?;
+        let s = Fr::from_bigint(
+            s.try_into()
+                .map_err(|_| SemaphoreError::MalleableSignature)?,
+        )
+        .ok_or(SemaphoreError::MalleableSignature)?;
+
+        Ok(Signature::new(r, s))
     }
 }
 
@@ -244,3 +599,332 @@ pub fn blake_512(input: &[u8]) -> [u8; 64] {
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_same_as() {
+        let identity = Identity::new("secret".as_bytes());
+
+        // Same scalar and commitment, different stored private key representation
+        let mut other = identity.clone();
+        other.private_key = b"secret\0".to_vec();
+
+        assert_ne!(identity, other);
+        assert!(identity.same_as(&other));
+        assert!(!identity.same_as(&Identity::new("other".as_bytes())));
+    }
+
+    #[test]
+    fn test_derive_scoped() {
+        let identity = Identity::new("secret".as_bytes());
+
+        let app = identity.derive_scoped(b"app");
+        let forum = identity.derive_scoped(b"forum");
+        assert_ne!(app.commitment(), forum.commitment());
+        assert_ne!(app.commitment(), identity.commitment());
+        assert_eq!(app, identity.derive_scoped(b"app"));
+        assert_eq!(
+            app,
+            Identity::new("secret".as_bytes()).derive_scoped(b"app")
+        );
+
+        // The key and the scope are not concatenated ambiguously
+        let shifted = Identity::new("secre".as_bytes()).derive_scoped(b"tapp");
+        assert_ne!(shifted.commitment(), app.commitment());
+
+        // Scoped identities sign like any identity
+        let signature = app.sign_message(b"message").unwrap();
+        assert!(signature.verify(app.public_key(), b"message").is_ok());
+    }
+
+    #[test]
+    fn test_from_seed() {
+        let seed = [7u8; 32];
+
+        let app = Identity::from_seed(&seed, b"app");
+        let forum = Identity::from_seed(&seed, b"forum");
+        assert_ne!(app.commitment(), forum.commitment());
+        assert_eq!(app, Identity::from_seed(&seed, b"app"));
+        assert_ne!(app.commitment(), Identity::new(&seed).commitment());
+        assert_ne!(
+            app.commitment(),
+            Identity::from_seed(&[8u8; 32], b"app").commitment()
+        );
+
+        // The derivation is documented for other implementations
+        let mut input = b"semaphore-rs/seed-identity".to_vec();
+        input.extend_from_slice(&3u64.to_le_bytes());
+        input.extend_from_slice(b"app");
+        input.extend_from_slice(&seed);
+        assert_eq!(app, Identity::new(&blake_512(&input)));
+    }
+
+    #[test]
+    fn test_display_from_str() {
+        let identity = Identity::new("secret".as_bytes());
+        let public_key = identity.public_key();
+        let signature = identity.sign_message(b"message").unwrap();
+
+        let public_key_str = public_key.to_string();
+        let signature_str = signature.to_string();
+        assert_eq!(public_key_str.len(), 64);
+        assert_eq!(signature_str.len(), 129);
+        assert_eq!(public_key_str, hex::encode(pack_point(&public_key.point())));
+
+        assert_eq!(public_key_str.parse::<PublicKey>().unwrap(), *public_key);
+        assert_eq!(
+            format!("0x{public_key_str}").parse::<PublicKey>().unwrap(),
+            *public_key
+        );
+        assert_eq!(signature_str.parse::<Signature>().unwrap(), signature);
+
+        assert!("00".parse::<PublicKey>().is_err());
+        assert!(public_key_str.parse::<Signature>().is_err());
+        let (r, _) = signature_str.split_once(':').unwrap();
+        assert_eq!(
+            format!("{r}:{}", "ff".repeat(32)).parse::<Signature>(),
+            Err(SemaphoreError::MalleableSignature)
+        );
+    }
+
+    #[test]
+    fn test_debug_redacts_secrets() {
+        let identity = Identity::new("secret".as_bytes());
+        let debug = format!("{identity:?}");
+        let pretty = format!("{identity:#?}");
+
+        for output in [&debug, &pretty] {
+            assert!(output.contains(&identity.commitment().to_string()));
+            assert!(!output.contains("private_key"));
+            assert!(!output.contains("secret_scalar"));
+            assert!(!output.contains(&identity.secret_scalar().to_string()));
+            assert!(!output.contains(&format!("{:?}", identity.private_key())));
+            assert!(!output.contains(&hex::encode(identity.private_key())));
+        }
+    }
+
+    #[test]
+    fn test_malleable_signature() {
+        let identity = Identity::new("secret".as_bytes());
+        let signature = identity.sign_message(b"message").unwrap();
+        let signature_str = signature.to_string();
+        let (r, _) = signature_str.split_once(':').unwrap();
+
+        // `s + l` fits in 32 bytes and satisfies the same verification equation
+        let s = num_bigint::BigUint::from(signature.s.into_bigint())
+            + num_bigint::BigUint::from(Fr::MODULUS);
+        let mut s_bytes = s.to_bytes_le();
+        s_bytes.resize(32, 0);
+        assert_eq!(
+            format!("{r}:{}", hex::encode(s_bytes)).parse::<Signature>(),
+            Err(SemaphoreError::MalleableSignature)
+        );
+        assert!(signature.verify(identity.public_key(), b"message").is_ok());
+    }
+
+    #[test]
+    fn test_ownership() {
+        let identity = Identity::new("secret".as_bytes());
+        let other = Identity::new("other".as_bytes());
+        let challenge = b"relayer challenge longer than the 32 bytes of a message";
+        let signature = identity.prove_ownership(challenge);
+
+        assert_eq!(
+            PublicKey::verify_ownership(
+                identity.commitment(),
+                identity.public_key(),
+                challenge,
+                &signature
+            ),
+            Ok(())
+        );
+        // Someone else's commitment
+        assert_eq!(
+            PublicKey::verify_ownership(
+                other.commitment(),
+                identity.public_key(),
+                challenge,
+                &signature
+            ),
+            Err(SemaphoreError::CommitmentMismatch)
+        );
+        // Signature of another identity or challenge
+        assert_eq!(
+            PublicKey::verify_ownership(
+                other.commitment(),
+                other.public_key(),
+                challenge,
+                &signature
+            ),
+            Err(SemaphoreError::SignatureVerificationFailed)
+        );
+        assert_eq!(
+            PublicKey::verify_ownership(
+                identity.commitment(),
+                identity.public_key(),
+                b"other challenge",
+                &signature
+            ),
+            Err(SemaphoreError::SignatureVerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_sign_message_with_nonce() {
+        let identity = Identity::new("secret".as_bytes());
+        let signature = identity
+            .sign_message_with_nonce(b"message", Fr::from(42u8))
+            .unwrap();
+
+        assert_eq!(
+            signature.r,
+            BabyJubjubConfig::GENERATOR
+                .mul(Fr::from(42u8))
+                .into_affine()
+        );
+        // Regression vector, s = 42 + challenge * secret
+        assert_eq!(
+            signature.s.to_string(),
+            "1651743427325098705928573298901504099653755201106888188935713754751206240680"
+        );
+        assert_eq!(signature.verify(identity.public_key(), b"message"), Ok(()));
+
+        // The default nonce is derived from the private key and the message
+        assert_eq!(
+            identity.sign_message_with_nonce(b"message", identity.nonce(b"message")),
+            identity.sign_message(b"message")
+        );
+        assert_eq!(
+            identity.sign_message_with_nonce(&[0; 33], Fr::from(42u8)),
+            Err(SemaphoreError::MessageSizeExceeded(33))
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let identity = Identity::new("secret".as_bytes());
+        assert_eq!(
+            identity.sign_and_verify(b"message"),
+            identity.sign_message(b"message")
+        );
+        assert_eq!(
+            identity.sign_and_verify(&[0; 33]),
+            Err(SemaphoreError::MessageSizeExceeded(33))
+        );
+
+        let mut corrupted = identity.clone();
+        corrupted.public_key = Identity::new("other".as_bytes()).public_key;
+        assert_eq!(
+            corrupted.sign_and_verify(b"message"),
+            Err(SemaphoreError::SignatureVerificationFailed)
+        );
+    }
+
+    #[test]
+    fn test_verify_ct() {
+        let identity = Identity::new("secret".as_bytes());
+        let other = Identity::new("other".as_bytes());
+        let signature = identity.sign_message(b"message").unwrap();
+        let mut tampered = signature.clone();
+        tampered.s += Fr::from(1u8);
+
+        for (public_key, message, signature) in [
+            (identity.public_key(), b"message".as_slice(), &signature),
+            (identity.public_key(), b"other message", &signature),
+            (other.public_key(), b"message", &signature),
+            (identity.public_key(), b"message", &tampered),
+            (identity.public_key(), &[0; 33], &signature),
+        ] {
+            assert_eq!(
+                signature.verify_ct(public_key, message),
+                signature.verify(public_key, message)
+            );
+        }
+        assert_eq!(
+            signature.verify_ct(identity.public_key(), b"message"),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_commitment_bytes() {
+        let identity = Identity::new("secret".as_bytes());
+        let commitment = BigUint::from(identity.commitment().into_bigint());
+
+        assert_eq!(
+            identity.commitment_element().0,
+            crate::utils::to_element(*identity.commitment())
+        );
+        assert_eq!(
+            identity.commitment_bytes(),
+            identity.commitment_element().to_be()
+        );
+        assert_eq!(BigUint::from(identity.commitment_bytes()), commitment);
+    }
+
+    #[test]
+    fn test_torsion_shifted_public_key() {
+        let identity = Identity::new("secret".as_bytes());
+        let challenge = b"relayer challenge";
+        let signature = identity.prove_ownership(challenge);
+
+        // `(0, -1)` has order 2: the shifted key verifies the same signatures under another
+        // commitment, unless it is rejected
+        let torsion = EdwardsAffine::new_unchecked(Fq::from(0u8), -Fq::from(1u8));
+        let shifted =
+            PublicKey::from_point((identity.public_key().point() + torsion).into_affine());
+        assert!(shifted.point().is_on_curve());
+        assert_ne!(shifted.commitment(), *identity.commitment());
+
+        assert_eq!(
+            PublicKey::verify_ownership(&shifted.commitment(), &shifted, challenge, &signature),
+            Err(SemaphoreError::PublicKeyNotInSubgroup)
+        );
+        assert_eq!(
+            shifted.to_string().parse::<PublicKey>(),
+            Err(SemaphoreError::PublicKeyNotInSubgroup)
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            PublicKey::from_jwk(&shifted.to_jwk()),
+            Err(SemaphoreError::PublicKeyNotInSubgroup)
+        );
+
+        let shifted_r = pack_point(&(signature.r + torsion).into_affine());
+        let signature_str = signature.to_string();
+        let (_, s) = signature_str.split_once(':').unwrap();
+        assert_eq!(
+            format!("{}:{s}", hex::encode(shifted_r)).parse::<Signature>(),
+            Err(SemaphoreError::SignaturePointNotInSubgroup)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_jwk() {
+        let public_key = Identity::new("secret".as_bytes()).public_key().clone();
+        let jwk = public_key.to_jwk();
+
+        assert_eq!(jwk["kty"], "OKP");
+        assert_eq!(jwk["crv"], JWK_CURVE);
+        assert_eq!(PublicKey::from_jwk(&jwk).unwrap(), public_key);
+
+        let mut wrong_curve = jwk.clone();
+        wrong_curve["crv"] = "Ed25519".into();
+        assert!(matches!(
+            PublicKey::from_jwk(&wrong_curve),
+            Err(SemaphoreError::SerializationError(_))
+        ));
+
+        let mut truncated = jwk.clone();
+        truncated["x"] = URL_SAFE_NO_PAD.encode([1u8; 31]).into();
+        assert!(matches!(
+            PublicKey::from_jwk(&truncated),
+            Err(SemaphoreError::SerializationError(_))
+        ));
+    }
+}
@@ -4,10 +4,13 @@
 //! - <https://github.com/zkspecs/zkspecs/tree/main/specs/3>
 
 pub mod baby_jubjub;
+#[cfg(feature = "bundled-artifacts")]
+mod bundled;
 pub mod error;
 pub mod group;
 pub mod identity;
 pub mod proof;
+pub mod telemetry;
 pub mod utils;
 pub mod witness;
 
@@ -1,44 +1,370 @@
-#[cfg(feature = "serde")]
-use crate::error::SemaphoreError;
 use crate::{
     MAX_TREE_DEPTH, MIN_TREE_DEPTH,
-    group::{EMPTY_ELEMENT, Element, Group, MerkleProof},
+    error::SemaphoreError,
+    group::{Element, Group, MerkleProof, MerkleProofBig},
     identity::Identity,
-    utils::{download_zkey, hash, to_big_uint, to_element},
+    telemetry,
+    utils::{
+        CircuitVersion, HashMode, biguint_to_be_32, download_zkey_version, element_to_biguint,
+        fingerprint, hash, hash_bytes, to_big_uint, to_element,
+    },
     witness::dispatch_witness,
 };
-use anyhow::{Ok, Result, bail};
+use anyhow::{Result, anyhow};
+use ark_bn254::{Bn254, Fq2, Fr, G1Affine, G2Affine};
+use ark_ed_on_bn254::Fq;
+use ark_ff::{BigInt, BigInteger, PrimeField};
+use ark_groth16::{Groth16, PreparedVerifyingKey, VerifyingKey, prepare_verifying_key};
+use ark_serialize::CanonicalDeserialize;
 use circom_prover::{
     CircomProver,
     prover::{
-        CircomProof, ProofLib, PublicInputs,
+        ProofLib,
+        ark_circom::read_proving_key,
         circom::{self, CURVE_BN254, G1, G2, PROTOCOL_GROTH16},
     },
     witness::WitnessFn,
 };
+use ethers_core::types::U256;
+use light_poseidon::{Poseidon, PoseidonHasher};
 use num_bigint::BigUint;
 use num_traits::{Zero, identities::One};
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fs::File,
+    io::BufReader,
+    str::FromStr,
+    sync::{
+        Arc, LazyLock, RwLock,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 pub type PackedGroth16Proof = [BigUint; 8];
 
+/// Public inputs of the circuits: root, nullifier, message hash and scope hash
+const PUBLIC_INPUTS: usize = 4;
+
+/// Prepared verifying keys loaded in memory, by circuit version and tree depth
+type VerifyingKeys = HashMap<(CircuitVersion, u16), Arc<PreparedVerifyingKey<Bn254>>>;
+static VERIFYING_KEYS: LazyLock<RwLock<VerifyingKeys>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Prepared verifying keys of the local zkeys of [`ProofConfig::zkey_path`], by path
+type ZkeyVerifyingKeys = HashMap<String, Arc<PreparedVerifyingKey<Bn254>>>;
+static ZKEY_VERIFYING_KEYS: LazyLock<RwLock<ZkeyVerifyingKeys>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+/// Maximum number of worker threads running [`Proof::verify_proof_with_timeout`] verifications
+///
+/// Timed out verifications keep their worker until they finish, so this also caps the threads
+/// slow proofs can pile up.
+pub const MAX_VERIFICATION_WORKERS: usize = 16;
+
+/// Worker threads running [`Proof::verify_proof_with_timeout`] verifications
+static VERIFICATION_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Slot of a verification worker thread, released when the worker ends
+struct WorkerSlot(&'static AtomicUsize);
+
+impl WorkerSlot {
+    /// Takes a slot if fewer than `max` workers are running
+    fn acquire(workers: &'static AtomicUsize, max: usize) -> Option<Self> {
+        workers
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                (running < max).then_some(running + 1)
+            })
+            .ok()
+            .map(|_| WorkerSlot(workers))
+    }
+}
+
+impl Drop for WorkerSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
 pub enum GroupOrMerkleProof {
     Group(Group),
     MerkleProof(MerkleProof),
 }
 
 impl GroupOrMerkleProof {
-    fn merkle_proof(&self, leaf: &Element) -> MerkleProof {
+    /// Wraps a Merkle proof, checking that it recomputes to its root
+    pub fn from_merkle_proof(proof: MerkleProof) -> Result<Self, SemaphoreError> {
+        if !Group::verify_proof(&proof) {
+            return Err(SemaphoreError::InvalidMerkleProof);
+        }
+
+        Ok(GroupOrMerkleProof::MerkleProof(proof))
+    }
+
+    /// Checks that the group fits in a tree of the given depth
+    fn check_capacity(&self, merkle_tree_depth: u16) -> Result<(), SemaphoreError> {
+        match self {
+            GroupOrMerkleProof::Group(group)
+                if max_members(merkle_tree_depth).is_some_and(|max| group.size() as u64 > max) =>
+            {
+                Err(SemaphoreError::GroupTooLargeForDepth {
+                    size: group.size(),
+                    depth: merkle_tree_depth,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the root the proof is generated for
+    fn root(&self) -> Option<Element> {
+        match self {
+            GroupOrMerkleProof::Group(group) => group.root(),
+            GroupOrMerkleProof::MerkleProof(proof) => Some(proof.root),
+        }
+    }
+
+    /// Returns the Merkle proof of a leaf, or [`SemaphoreError::IdentityNotInGroup`] if it is
+    /// not a member of the group
+    fn merkle_proof(&self, leaf: &Element) -> Result<MerkleProof, SemaphoreError> {
         match self {
             GroupOrMerkleProof::Group(group) => {
-                let idx = group.index_of(*leaf).expect("The identity does not exist");
-                group.generate_proof(idx).unwrap()
+                let idx = group
+                    .index_of(*leaf)
+                    .ok_or(SemaphoreError::IdentityNotInGroup)?;
+                group.generate_proof(idx)
+            }
+            GroupOrMerkleProof::MerkleProof(proof) => Ok(proof.clone()),
+        }
+    }
+}
+
+/// Defines the encodings and conversions shared by [`Message`] and [`Scope`]
+macro_rules! text_or_numeric {
+    ($name:ident, $what:literal) => {
+        impl $name {
+            #[doc = concat!("Returns the ", $what, " encoded as an integer")]
+            ///
+            /// # Panics
+            ///
+            /// If the text is longer than 32 bytes, see `try_to_big_uint`.
+            pub fn to_big_uint(&self) -> BigUint {
+                match self {
+                    $name::Text(text) => to_big_uint(text),
+                    $name::Numeric(value) => value.clone(),
+                }
+            }
+
+            #[doc = concat!("Returns the ", $what, " encoded as an integer, or an error if")]
+            /// the text is longer than 32 bytes
+            pub fn try_to_big_uint(&self) -> Result<BigUint, SemaphoreError> {
+                match self {
+                    $name::Text(text) if text.len() > 32 => {
+                        Err(SemaphoreError::InputSizeExceeded(text.len()))
+                    }
+                    _ => Ok(self.to_big_uint()),
+                }
+            }
+
+            #[doc = concat!("Reduces a ", $what, " of any length into the field with Keccak-256")]
+            /// (shifted right by 8 bits)
+            ///
+            #[doc = concat!("Unlike the `bytes32` encoding of [`", stringify!($name), "::Text`],")]
+            /// which is limited to 32 bytes, this hashes the UTF-8 bytes. Use it as a
+            #[doc = concat!("[`", stringify!($name), "::Numeric`] value, with verifiers hashing")]
+            /// the same way.
+            pub fn from_str_hashed(text: &str) -> BigUint {
+                BigUint::from_bytes_be(&hash_bytes(text.as_bytes()))
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                $name::Text(text)
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(text: &str) -> Self {
+                $name::Text(text.to_string())
+            }
+        }
+
+        impl From<BigUint> for $name {
+            fn from(value: BigUint) -> Self {
+                $name::Numeric(value)
             }
-            GroupOrMerkleProof::MerkleProof(proof) => proof.clone(),
         }
+    };
+}
+
+/// Proof message
+///
+/// Mirrors the `toBigInt` encoding of semaphore-js:
+/// - `Text` is encoded as a `bytes32` string (UTF-8, right-padded to 32 bytes).
+/// - `Numeric` is used as is, like a JS `bigint` or numeric string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Numeric(BigUint),
+}
+
+text_or_numeric!(Message, "message");
+
+/// Proof scope
+///
+/// Encoded the same way as [`Message`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Text(String),
+    Numeric(BigUint),
+}
+
+text_or_numeric!(Scope, "scope");
+
+impl Scope {
+    /// Derives a scope from a verifier identifier and a nonce chosen by the verifier
+    ///
+    /// The input is `CHALLENGE_DOMAIN || len(verifier_id) as u64 LE || verifier_id || nonce`,
+    /// hashed like [`Scope::from_str_hashed`]. The length prefix keeps `("ab", "c")` and
+    /// `("a", "bc")` apart. A fresh nonce per request binds a proof to it, so it cannot be
+    /// replayed, and nullifiers stay unlinkable between challenges.
+    pub fn from_challenge(verifier_id: &[u8], nonce: &[u8]) -> BigUint {
+        let mut input =
+            Vec::with_capacity(CHALLENGE_DOMAIN.len() + 8 + verifier_id.len() + nonce.len());
+        input.extend_from_slice(CHALLENGE_DOMAIN);
+        input.extend_from_slice(&(verifier_id.len() as u64).to_le_bytes());
+        input.extend_from_slice(verifier_id);
+        input.extend_from_slice(nonce);
+
+        BigUint::from_bytes_be(&hash_bytes(&input))
+    }
+}
+
+/// Domain separating [`Scope::from_challenge`] from other hashed scopes
+const CHALLENGE_DOMAIN: &[u8] = b"semaphore-rs/challenge";
+
+/// Bits of a [`TimedScope`] holding the epoch
+const EPOCH_BITS: usize = 64;
+
+/// Scope bucketing signals by epoch, e.g. one vote per poll and per day
+///
+/// The scope is `base << 64 | epoch`, a `uint256` like any numeric scope, so the base is
+/// limited to 192 bits. Nullifiers differ between epochs, and verifiers read the epoch back
+/// from the proof scope with [`extract_epoch`] to reject stale proofs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedScope {
+    base: BigUint,
+    epoch: u64,
+}
+
+impl TimedScope {
+    /// Creates a timed scope, erroring if the base does not fit in 192 bits
+    pub fn new(base: BigUint, epoch: u64) -> Result<Self, SemaphoreError> {
+        if base.bits() > (256 - EPOCH_BITS) as u64 {
+            return Err(SemaphoreError::TimedScopeBaseTooLarge);
+        }
+
+        Ok(TimedScope { base, epoch })
+    }
+
+    /// Decodes the scope of a proof, `None` if it is larger than a `uint256`
+    pub fn from_scope(scope: &BigUint) -> Option<Self> {
+        (scope.bits() <= 256).then(|| TimedScope {
+            base: scope >> EPOCH_BITS,
+            epoch: extract_epoch(scope),
+        })
+    }
+
+    pub fn base(&self) -> &BigUint {
+        &self.base
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the scope encoded as an integer
+    pub fn to_big_uint(&self) -> BigUint {
+        (&self.base << EPOCH_BITS) | BigUint::from(self.epoch)
+    }
+}
+
+impl From<TimedScope> for Scope {
+    fn from(scope: TimedScope) -> Self {
+        Scope::Numeric(scope.to_big_uint())
+    }
+}
+
+/// Recent group roots accepted in the current epoch, like the root history of a contract
+///
+/// Holds up to `size` roots, the oldest being evicted first. See
+/// [`Proof::verify_proof_windowed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootWindow {
+    size: usize,
+    roots: VecDeque<BigUint>,
+    epoch: u64,
+}
+
+impl RootWindow {
+    /// Creates an empty window of `size` roots for an epoch
+    pub fn new(size: usize, epoch: u64) -> Self {
+        RootWindow {
+            size,
+            roots: VecDeque::with_capacity(size),
+            epoch,
+        }
+    }
+
+    /// Adds the latest root, evicting the oldest one if the window is full
+    ///
+    /// Group roots are little-endian elements, convert them with
+    /// [`element_to_biguint`](crate::utils::element_to_biguint).
+    pub fn push_root(&mut self, root: BigUint) {
+        if self.size == 0 {
+            return;
+        }
+        if self.roots.len() == self.size {
+            self.roots.pop_front();
+        }
+        self.roots.push_back(root);
+    }
+
+    /// Moves to another epoch, keeping the roots
+    pub fn set_epoch(&mut self, epoch: u64) {
+        self.epoch = epoch;
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the roots from the oldest to the latest
+    pub fn roots(&self) -> impl Iterator<Item = &BigUint> {
+        self.roots.iter()
+    }
+
+    /// Returns true if the root is one of the window
+    pub fn contains_root(&self, root: &BigUint) -> bool {
+        self.roots.contains(root)
+    }
+
+    /// Returns true if the proof root is in the window and its scope is for the window epoch
+    pub fn accepts(&self, proof: &SemaphoreProof) -> bool {
+        TimedScope::from_scope(&proof.scope).is_some_and(|scope| scope.epoch() == self.epoch)
+            && self.contains_root(&proof.merkle_tree_root)
     }
 }
 
+/// Returns the epoch of a scope encoded with [`TimedScope`], its lowest 64 bits
+pub fn extract_epoch(scope: &BigUint) -> u64 {
+    scope.iter_u64_digits().next().unwrap_or(0)
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct SemaphoreProof {
     pub merkle_tree_depth: u16,
@@ -49,6 +375,202 @@ pub struct SemaphoreProof {
     pub points: PackedGroth16Proof,
 }
 
+impl SemaphoreProof {
+    /// Encodes the proof as the ABI `SemaphoreProof` struct taken by `Semaphore.sol`
+    ///
+    /// Returns the 13 `uint256` words (depth, root, nullifier, message, scope, points),
+    /// without a function selector.
+    pub fn to_solidity_calldata(&self) -> Result<Vec<u8>, SemaphoreError> {
+        let mut calldata = Vec::with_capacity(13 * 32);
+        for value in [
+            &BigUint::from(self.merkle_tree_depth),
+            &self.merkle_tree_root,
+            &self.nullifier,
+            &self.message,
+            &self.scope,
+        ]
+        .into_iter()
+        .chain(self.points.iter())
+        {
+            calldata.extend_from_slice(&biguint_to_be_32(value)?);
+        }
+        Ok(calldata)
+    }
+
+    /// Rebuilds a proof from the `uint256` values of the `Semaphore.sol` `SemaphoreProof` struct
+    ///
+    /// The reverse of [`SemaphoreProof::to_solidity_calldata`]: the points are in the packed
+    /// order, with the `G2` coordinates already swapped for the precompile. The values are
+    /// validated like with [`SemaphoreProof::builder`].
+    pub fn from_solidity_calldata(
+        points: [U256; 8],
+        merkle_tree_root: U256,
+        nullifier: U256,
+        message: U256,
+        scope: U256,
+        merkle_tree_depth: u16,
+    ) -> Result<SemaphoreProof, SemaphoreError> {
+        let uint = |value: U256| {
+            let mut bytes = [0u8; 32];
+            value.to_big_endian(&mut bytes);
+            BigUint::from_bytes_be(&bytes)
+        };
+
+        SemaphoreProof::builder()
+            .merkle_tree_depth(merkle_tree_depth)
+            .merkle_tree_root(uint(merkle_tree_root))
+            .nullifier(uint(nullifier))
+            .message(uint(message))
+            .scope(uint(scope))
+            .points(points.map(uint))
+            .build()
+    }
+}
+
+impl SemaphoreProof {
+    /// Length of [`SemaphoreProof::to_fixed_bytes`]
+    pub const FIXED_BYTES_LEN: usize = 2 + 12 * 32;
+
+    /// Encodes the proof in a fixed-size layout for binary protocols, smaller than JSON
+    ///
+    /// The depth is a big-endian `u16`, followed by the root, nullifier, message, scope and
+    /// the 8 packed points as big-endian 32-byte words. Errors if a value exceeds 32 bytes.
+    pub fn to_fixed_bytes(&self) -> Result<[u8; Self::FIXED_BYTES_LEN], SemaphoreError> {
+        let mut bytes = [0u8; Self::FIXED_BYTES_LEN];
+        bytes[..2].copy_from_slice(&self.merkle_tree_depth.to_be_bytes());
+        for (word, value) in bytes[2..].chunks_exact_mut(32).zip(
+            [
+                &self.merkle_tree_root,
+                &self.nullifier,
+                &self.message,
+                &self.scope,
+            ]
+            .into_iter()
+            .chain(self.points.iter()),
+        ) {
+            word.copy_from_slice(&biguint_to_be_32(value)?);
+        }
+        Ok(bytes)
+    }
+
+    /// Decodes a proof encoded with [`SemaphoreProof::to_fixed_bytes`]
+    ///
+    /// The values are validated like with [`SemaphoreProof::builder`].
+    pub fn from_fixed_bytes(
+        bytes: &[u8; Self::FIXED_BYTES_LEN],
+    ) -> Result<SemaphoreProof, SemaphoreError> {
+        let mut words = bytes[2..].chunks_exact(32).map(BigUint::from_bytes_be);
+        let mut word = || words.next().unwrap();
+
+        SemaphoreProof::builder()
+            .merkle_tree_depth(u16::from_be_bytes([bytes[0], bytes[1]]))
+            .merkle_tree_root(word())
+            .nullifier(word())
+            .message(word())
+            .scope(word())
+            .points(std::array::from_fn::<_, 8, _>(|_| word()))
+            .build()
+    }
+
+    /// Checks that the proof root is the root of the group at the proof depth
+    ///
+    /// A cheap pre-filter before the pairing check of [`Proof::verify`]. Errors if the depth is
+    /// unsupported, the group is empty or does not fit in the proof depth.
+    pub fn check_root_consistency(&self, group: &Group) -> Result<bool, SemaphoreError> {
+        let root = group.root_at_depth(self.merkle_tree_depth)?;
+        Ok(element_to_biguint(&root) == self.merkle_tree_root)
+    }
+
+    /// Starts a validated [`SemaphoreProof`] assembled from separate sources
+    pub fn builder() -> SemaphoreProofBuilder {
+        SemaphoreProofBuilder::default()
+    }
+}
+
+/// Builder of a [`SemaphoreProof`], see [`SemaphoreProof::builder`]
+///
+/// All the fields are required. Message and scope are any `uint256`, as they are hashed
+/// before verification, the root and nullifier must be in the BN254 scalar field and the
+/// points in its base field.
+#[derive(Debug, Clone, Default)]
+pub struct SemaphoreProofBuilder {
+    merkle_tree_depth: Option<u16>,
+    merkle_tree_root: Option<BigUint>,
+    message: Option<BigUint>,
+    nullifier: Option<BigUint>,
+    scope: Option<BigUint>,
+    points: Option<Vec<BigUint>>,
+}
+
+impl SemaphoreProofBuilder {
+    pub fn merkle_tree_depth(mut self, merkle_tree_depth: u16) -> Self {
+        self.merkle_tree_depth = Some(merkle_tree_depth);
+        self
+    }
+
+    pub fn merkle_tree_root(mut self, merkle_tree_root: BigUint) -> Self {
+        self.merkle_tree_root = Some(merkle_tree_root);
+        self
+    }
+
+    pub fn message(mut self, message: BigUint) -> Self {
+        self.message = Some(message);
+        self
+    }
+
+    pub fn nullifier(mut self, nullifier: BigUint) -> Self {
+        self.nullifier = Some(nullifier);
+        self
+    }
+
+    pub fn scope(mut self, scope: BigUint) -> Self {
+        self.scope = Some(scope);
+        self
+    }
+
+    pub fn points(mut self, points: impl Into<Vec<BigUint>>) -> Self {
+        self.points = Some(points.into());
+        self
+    }
+
+    /// Validates the fields and builds the proof
+    pub fn build(self) -> Result<SemaphoreProof, SemaphoreError> {
+        let merkle_tree_depth = self
+            .merkle_tree_depth
+            .ok_or(SemaphoreError::MissingProofField("merkle_tree_depth"))?;
+        check_tree_depth(merkle_tree_depth)?;
+
+        let scalar_modulus = BigUint::from(Fr::MODULUS);
+        let base_modulus = BigUint::from(ark_bn254::Fq::MODULUS);
+        let field = |value: Option<BigUint>, name, modulus: &BigUint| {
+            let value = value.ok_or(SemaphoreError::MissingProofField(name))?;
+            if value >= *modulus {
+                return Err(SemaphoreError::ProofFieldOutOfRange(name));
+            }
+            Ok(value)
+        };
+        let uint256_modulus = BigUint::one() << 256;
+
+        let points: PackedGroth16Proof = self
+            .points
+            .ok_or(SemaphoreError::MissingProofField("points"))?
+            .try_into()
+            .map_err(|points: Vec<BigUint>| SemaphoreError::InvalidPointsLength(points.len()))?;
+        if points.iter().any(|point| *point >= base_modulus) {
+            return Err(SemaphoreError::ProofFieldOutOfRange("points"));
+        }
+
+        Ok(SemaphoreProof {
+            merkle_tree_depth,
+            merkle_tree_root: field(self.merkle_tree_root, "merkle_tree_root", &scalar_modulus)?,
+            message: field(self.message, "message", &uint256_modulus)?,
+            nullifier: field(self.nullifier, "nullifier", &scalar_modulus)?,
+            scope: field(self.scope, "scope", &uint256_modulus)?,
+            points,
+        })
+    }
+}
+
 #[cfg(feature = "serde")]
 impl SemaphoreProof {
     pub fn export(&self) -> Result<String, SemaphoreError> {
@@ -67,8 +589,7 @@ impl SemaphoreProof {
         json.insert(
             "points".to_string(),
             self.points
-                .to_vec()
-                .into_iter()
+                .iter()
                 .map(|p| p.to_string())
                 .collect::<Vec<String>>()
                 .into(),
@@ -76,102 +597,204 @@ impl SemaphoreProof {
         serde_json::to_string(&json).map_err(|e| SemaphoreError::SerializationError(e.to_string()))
     }
 
+    /// Imports a proof exported with [`SemaphoreProof::export`]
+    ///
+    /// Missing or malformed fields are returned as [`SemaphoreError::SerializationError`].
     pub fn import(json: &str) -> Result<Self, SemaphoreError> {
         let json: serde_json::Map<String, serde_json::Value> = serde_json::from_str(json)
             .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+
+        let error = |field: &str| SemaphoreError::SerializationError(format!("Invalid {field}"));
+        let decimal = |value: Option<&serde_json::Value>, field: &str| {
+            value
+                .and_then(|value| value.as_str())
+                .and_then(|value| BigUint::from_str(value).ok())
+                .ok_or_else(|| error(field))
+        };
+
         Ok(SemaphoreProof {
-            merkle_tree_depth: json.get("merkle_tree_depth").unwrap().as_u64().unwrap() as u16,
-            merkle_tree_root: BigUint::from_str(
-                json.get("merkle_tree_root").unwrap().as_str().unwrap(),
-            )
-            .unwrap(),
-            message: BigUint::from_str(json.get("message").unwrap().as_str().unwrap()).unwrap(),
-            nullifier: BigUint::from_str(json.get("nullifier").unwrap().as_str().unwrap()).unwrap(),
-            scope: BigUint::from_str(json.get("scope").unwrap().as_str().unwrap()).unwrap(),
+            merkle_tree_depth: json
+                .get("merkle_tree_depth")
+                .and_then(|depth| depth.as_u64())
+                .and_then(|depth| u16::try_from(depth).ok())
+                .ok_or_else(|| error("merkle_tree_depth"))?,
+            merkle_tree_root: decimal(json.get("merkle_tree_root"), "merkle_tree_root")?,
+            message: decimal(json.get("message"), "message")?,
+            nullifier: decimal(json.get("nullifier"), "nullifier")?,
+            scope: decimal(json.get("scope"), "scope")?,
             points: json
                 .get("points")
-                .unwrap()
-                .as_array()
-                .unwrap()
+                .and_then(|points| points.as_array())
+                .ok_or_else(|| error("points"))?
                 .iter()
-                .map(|p| BigUint::from_str(p.as_str().unwrap()).unwrap())
-                .collect::<Vec<BigUint>>()
+                .map(|point| decimal(Some(point), "points"))
+                .collect::<Result<Vec<BigUint>, SemaphoreError>>()?
                 .try_into()
-                .unwrap(),
+                .map_err(|_| error("points"))?,
         })
-        .map_err(|e| SemaphoreError::SerializationError(e.to_string()))
     }
 }
 
-pub struct Proof {}
+/// Public signals carried by a Semaphore proof
+///
+/// `message` and `scope` are not hashed, like in [`SemaphoreProof`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicSignals {
+    pub merkle_tree_root: BigUint,
+    pub nullifier: BigUint,
+    pub message: BigUint,
+    pub scope: BigUint,
+}
+
+/// Verification results of a batch of proofs, see [`Proof::verify_proofs_summary`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationSummary {
+    /// Number of proofs
+    pub total: usize,
+    /// Valid proofs
+    pub passed: usize,
+    /// Invalid proofs
+    pub failed: usize,
+    /// Proofs that could not be verified: unsupported depth or unavailable verifying key
+    pub errored: usize,
+}
+
+/// What the verifier checked for an invalid proof, see [`Proof::verify_proof_debug`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationDebug {
+    /// Decimal public inputs fed to the verifier: root, nullifier, message hash and scope hash
+    ///
+    /// Empty if the message or scope is too large for the hash mode.
+    pub public_inputs: Vec<String>,
+    /// Unpacked Groth16 proof points
+    pub points: circom::Proof,
+    /// Whether the points are canonical curve points
+    pub points_on_curve: bool,
+    /// Fingerprint of the verifying key used, see [`Proof::verifying_key_fingerprint`]
+    ///
+    /// Compare it with the fingerprint of the prover artifacts to rule out a key mismatch.
+    pub verifying_key_fingerprint: String,
+}
+
+/// Proof in one of the formats frontends send, see [`Proof::verify_any`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProofInput {
+    /// JSON of [`SemaphoreProof::export`] or semaphore-js
+    #[cfg(feature = "serde")]
+    Json(String),
+    /// Layout of [`SemaphoreProof::to_fixed_bytes`]
+    FixedBytes(Box<[u8; SemaphoreProof::FIXED_BYTES_LEN]>),
+    /// `uint256` words of [`SemaphoreProof::to_solidity_calldata`]: depth, root, nullifier,
+    /// message, scope and points
+    Calldata(Box<[U256; 13]>),
+}
+
+impl ProofInput {
+    /// Decodes the proof
+    pub fn parse(&self) -> Result<SemaphoreProof, SemaphoreError> {
+        match self {
+            #[cfg(feature = "serde")]
+            ProofInput::Json(json) => SemaphoreProof::import(json),
+            ProofInput::FixedBytes(bytes) => SemaphoreProof::from_fixed_bytes(bytes),
+            ProofInput::Calldata(words) => {
+                let [depth, root, nullifier, message, scope, points @ ..] = &**words;
+                let depth = u16::try_from(*depth)
+                    .map_err(|_| SemaphoreError::ProofFieldOutOfRange("merkle_tree_depth"))?;
+                SemaphoreProof::from_solidity_calldata(
+                    *points, *root, *nullifier, *message, *scope, depth,
+                )
+            }
+        }
+    }
+}
+
+/// Proof configuration
+#[derive(Debug, Clone)]
+pub struct ProofConfig {
+    /// Backend used to generate the Groth16 proofs
+    pub proof_lib: ProofLib,
+    /// Local zkey used instead of the artifact downloaded for the tree depth
+    ///
+    /// The zkey must match the tree depth of the proofs it generates or verifies. Its verifying
+    /// key is read once and kept in memory by path, so a zkey replaced at the same path is not
+    /// reloaded for verification.
+    pub zkey_path: Option<String>,
+    /// Version of the circuits the artifacts are downloaded for
+    pub circuit_version: CircuitVersion,
+    /// Hash of the message and scope, which must match the circuits
+    pub hash_mode: HashMode,
+}
+
+impl Default for ProofConfig {
+    fn default() -> Self {
+        ProofConfig {
+            proof_lib: ProofLib::Arkworks,
+            zkey_path: None,
+            circuit_version: CircuitVersion::Latest,
+            hash_mode: HashMode::Keccak,
+        }
+    }
+}
+
+/// Generates and verifies Semaphore proofs
+///
+/// `Proof::default()` downloads the artifacts and proves with arkworks.
+#[derive(Debug, Clone, Default)]
+pub struct Proof {
+    config: ProofConfig,
+}
 
 impl Proof {
-    pub fn generate_proof(
+    pub fn new(config: ProofConfig) -> Self {
+        Proof { config }
+    }
+
+    pub fn config(&self) -> &ProofConfig {
+        &self.config
+    }
+
+    /// Generates a proof of membership of `identity` in `group`
+    ///
+    /// A non-member identity is a [`SemaphoreError::IdentityNotInGroup`] error, and a message
+    /// or scope text longer than 32 bytes a [`SemaphoreError::InputSizeExceeded`] one.
+    pub fn generate(
+        &self,
         identity: Identity,
         group: GroupOrMerkleProof,
-        message: String,
-        scope: String,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
         merkle_tree_depth: u16,
     ) -> Result<SemaphoreProof> {
-        // check tree depth
-        if !(MIN_TREE_DEPTH..=MAX_TREE_DEPTH).contains(&merkle_tree_depth) {
-            bail!(format!(
-                "The tree depth must be a number between {} and {}",
-                MIN_TREE_DEPTH, MAX_TREE_DEPTH
-            ));
-        }
+        check_tree_depth(merkle_tree_depth)?;
+        group.check_capacity(merkle_tree_depth)?;
 
-        let merkle_proof = group.merkle_proof(&to_element(*identity.commitment()));
-        let merkle_proof_length = merkle_proof.siblings.len();
-
-        let mut merkle_proof_siblings = Vec::<Element>::new();
-        for i in 0..merkle_tree_depth {
-            if let Some(sibling) = merkle_proof.siblings.get(i as usize) {
-                merkle_proof_siblings.push(*sibling);
-            } else {
-                merkle_proof_siblings.push(EMPTY_ELEMENT);
-            }
-        }
+        let merkle_proof = group.merkle_proof(&to_element(*identity.commitment()))?;
+        let scope_uint = scope.into().try_to_big_uint()?;
+        let message_uint = message.into().try_to_big_uint()?;
+        let inputs = circuit_inputs(
+            &identity,
+            &merkle_proof,
+            &message_uint,
+            &scope_uint,
+            merkle_tree_depth,
+            self.config.hash_mode,
+        )?;
 
-        let scope_uint = to_big_uint(&scope);
-        let message_uint = to_big_uint(&message);
-        let inputs = HashMap::from([
-            (
-                "secret".to_string(),
-                vec![identity.secret_scalar().to_string()],
-            ),
-            (
-                "merkleProofLength".to_string(),
-                vec![merkle_proof_length.to_string()],
-            ),
-            (
-                "merkleProofIndex".to_string(),
-                vec![merkle_proof.index.to_string()],
-            ),
-            (
-                "merkleProofSiblings".to_string(),
-                merkle_proof_siblings
-                    .iter()
-                    .map(|s| BigUint::from_bytes_le(s.to_vec().as_ref()).to_string())
-                    .collect(),
-            ),
-            ("scope".to_string(), vec![hash(scope_uint.clone())]),
-            ("message".to_string(), vec![hash(message_uint.clone())]),
-        ]);
-
-        let zkey_path = download_zkey(merkle_tree_depth).expect("Failed to download zkey");
+        let zkey_path = self.zkey_path(merkle_tree_depth)?;
         let witness_fn = dispatch_witness(merkle_tree_depth);
 
+        let start = Instant::now();
         let circom_proof = CircomProver::prove(
-            ProofLib::Arkworks,
+            self.config.proof_lib,
             WitnessFn::CircomWitnessCalc(witness_fn),
             serde_json::to_string(&inputs).unwrap(),
             zkey_path,
         )?;
+        telemetry::proof_generated(start.elapsed());
 
         Ok(SemaphoreProof {
             merkle_tree_depth,
-            merkle_tree_root: BigUint::from_bytes_le(merkle_proof.root.as_ref()),
+            merkle_tree_root: element_to_biguint(&merkle_proof.root),
             message: message_uint,
             nullifier: circom_proof.pub_inputs.0.get(1).unwrap().clone(),
             scope: scope_uint,
@@ -179,396 +802,3620 @@ impl Proof {
         })
     }
 
-    pub fn verify_proof(proof: SemaphoreProof) -> bool {
+    /// Generates a proof on a dedicated thread pool
+    ///
+    /// The proving work, including the witness computation, runs on `pool` while the calling
+    /// thread waits for the result. Cached zkeys and verifying keys are shared safely between
+    /// threads: downloads are serialized and the in-memory keys are behind a lock.
+    #[cfg(feature = "rayon")]
+    pub fn generate_on(
+        &self,
+        pool: &rayon::ThreadPool,
+        identity: Identity,
+        group: GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<SemaphoreProof> {
+        let message = message.into();
+        let scope = scope.into();
+        pool.install(|| self.generate(identity, group, message, scope, merkle_tree_depth))
+    }
+
+    /// Verifies a proof, taken either by value or by reference
+    ///
+    /// This is the legacy path: the root and nullifier are reduced into the field, so a proof
+    /// stays valid with the field order added to them. Use [`Proof::verify_and_extract`] or the
+    /// other `Result`-returning verifiers, which reject them, when nullifiers are stored.
+    pub fn verify(&self, proof: impl Borrow<SemaphoreProof>) -> bool {
+        let proof = proof.borrow();
+
         // check tree depth
-        if proof.merkle_tree_depth < MIN_TREE_DEPTH || proof.merkle_tree_depth > MAX_TREE_DEPTH {
-            panic!("The tree depth must be a number between and");
+        if !is_supported_depth(proof.merkle_tree_depth) {
+            panic!("The tree depth must be a number between {MIN_TREE_DEPTH} and {MAX_TREE_DEPTH}");
         }
 
-        let scope = BigUint::from_str(hash(proof.scope).as_str()).unwrap();
-        let message = BigUint::from_str(hash(proof.message).as_str()).unwrap();
-        let pub_inputs = PublicInputs(vec![
-            proof.merkle_tree_root,
-            proof.nullifier,
-            message,
-            scope,
-        ]);
-        let p = CircomProof {
-            proof: Self::unpack_groth16_proof(proof.points),
-            pub_inputs,
-        };
+        let verifying_key = self
+            .configured_verifying_key(proof.merkle_tree_depth)
+            .expect("Failed to load zkey");
 
-        let zkey_path = download_zkey(proof.merkle_tree_depth).expect("Failed to download zkey");
-        CircomProver::verify(ProofLib::Arkworks, p, zkey_path).unwrap()
+        Self::verify_with_key_and_mode(&verifying_key, proof, self.config.hash_mode)
     }
 
-    pub fn pack_groth16_proof(p: circom::Proof) -> PackedGroth16Proof {
-        [
-            p.a.x,
-            p.a.y,
-            p.b.x[1].clone(),
-            p.b.x[0].clone(),
-            p.b.y[1].clone(),
-            p.b.y[0].clone(),
-            p.c.x,
-            p.c.y,
-        ]
+    /// Verifies a proof and returns its nullifier, or `None` if the proof is invalid
+    ///
+    /// The nullifier of a proof is only meaningful once the proof is verified. Unlike
+    /// [`Proof::verify`], an unsupported tree depth or a verifying key that cannot be loaded
+    /// is returned as an error. So is a root or nullifier that is not a canonical field element,
+    /// which the verifier would reduce: the nullifier returned is the one checked, and cannot be
+    /// replayed with the field order added to it.
+    pub fn verify_and_extract(
+        &self,
+        proof: &SemaphoreProof,
+    ) -> Result<Option<BigUint>, SemaphoreError> {
+        check_proof(proof)?;
+
+        let verifying_key = self
+            .configured_verifying_key(proof.merkle_tree_depth)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+
+        Ok(
+            Self::verify_with_key_and_mode(&verifying_key, proof, self.config.hash_mode)
+                .then(|| proof.nullifier.clone()),
+        )
     }
 
-    pub fn unpack_groth16_proof(packed: PackedGroth16Proof) -> circom::Proof {
-        let a = G1 {
-            x: packed[0].clone(),
-            y: packed[1].clone(),
-            z: BigUint::one(),
-        };
-        let b = G2 {
-            x: [packed[3].clone(), packed[2].clone()],
-            y: [packed[5].clone(), packed[4].clone()],
-            z: [BigUint::one(), BigUint::zero()],
-        };
-        let c = G1 {
-            x: packed[6].clone(),
-            y: packed[7].clone(),
-            z: BigUint::one(),
-        };
+    /// Decodes a proof in any of the [`ProofInput`] formats and verifies it
+    ///
+    /// Decoding errors are returned as is, other errors are those of
+    /// [`Proof::verify_and_extract`].
+    pub fn verify_any(&self, input: &ProofInput) -> Result<bool, SemaphoreError> {
+        Ok(self.verify_and_extract(&input.parse()?)?.is_some())
+    }
 
-        circom::Proof {
-            a,
-            b,
-            c,
-            protocol: PROTOCOL_GROTH16.to_string(),
-            curve: CURVE_BN254.to_string(),
+    /// Verifies a proof on a worker thread, giving up after `timeout`
+    ///
+    /// Bounds the time an endpoint waits on an untrusted proof: past the timeout,
+    /// [`SemaphoreError::VerificationTimeout`] is returned. The worker thread cannot be
+    /// interrupted and finishes in the background, so at most [`MAX_VERIFICATION_WORKERS`]
+    /// workers run at once, timed out or not: past that, [`SemaphoreError::VerificationBusy`] is
+    /// returned without verifying. Other errors are those of [`Proof::verify_and_extract`]; a
+    /// first verification of a depth includes loading its key.
+    pub fn verify_proof_with_timeout(
+        &self,
+        proof: &SemaphoreProof,
+        timeout: Duration,
+    ) -> Result<bool, SemaphoreError> {
+        check_proof(proof)?;
+
+        let slot = WorkerSlot::acquire(&VERIFICATION_WORKERS, MAX_VERIFICATION_WORKERS)
+            .ok_or(SemaphoreError::VerificationBusy(MAX_VERIFICATION_WORKERS))?;
+        let (sender, receiver) = mpsc::channel();
+        let verifier = self.clone();
+        let proof = proof.clone();
+        thread::spawn(move || {
+            let _slot = slot;
+            // The receiver is dropped after a timeout
+            let _ = sender.send(verifier.verify_and_extract(&proof));
+        });
+
+        match receiver.recv_timeout(timeout) {
+            Ok(nullifier) => Ok(nullifier?.is_some()),
+            Err(_) => Err(SemaphoreError::VerificationTimeout),
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        group::{Element, Group},
-        identity::Identity,
-        proof::SemaphoreProof,
-    };
-    use num_bigint::BigUint;
-    use std::str::FromStr;
+    /// Verifies a proof with the given message and scope hashes as public inputs
+    ///
+    /// For proofs of provers that hash the message and scope themselves and whose circuits take
+    /// other values than [`hash`] of them: the hashes are used as is, instead of hashing
+    /// `proof.message` and `proof.scope` with the hash mode, which are ignored. Proofs of this
+    /// crate and semaphore-js are verified with [`Proof::verify`]. The hashes, root and
+    /// nullifier must be canonical field elements.
+    pub fn verify_proof_prehashed(
+        &self,
+        proof: &SemaphoreProof,
+        message_hashed: &BigUint,
+        scope_hashed: &BigUint,
+    ) -> Result<bool, SemaphoreError> {
+        check_proof(proof)?;
+        let pub_inputs = [
+            canonical_field_element(&proof.merkle_tree_root, "merkle_tree_root")?,
+            canonical_field_element(&proof.nullifier, "nullifier")?,
+            canonical_field_element(message_hashed, "message")?,
+            canonical_field_element(scope_hashed, "scope")?,
+        ];
 
-    const TREE_DEPTH: usize = 10;
-    const MESSAGE: &str = "Hello world";
-    const SCOPE: &str = "Scope";
+        let verifying_key = self
+            .configured_verifying_key(proof.merkle_tree_depth)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+        let Some(groth16_proof) = groth16_proof(&proof.points) else {
+            telemetry::verified(false);
+            return Ok(false);
+        };
 
-    const MEMBER1: Element = [1; 32];
-    const MEMBER2: Element = [2; 32];
+        let valid = Groth16::<Bn254>::verify_proof(&verifying_key, &groth16_proof, &pub_inputs)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+        telemetry::verified(valid);
+        Ok(valid)
+    }
 
-    #[cfg(test)]
-    mod gen_proof {
+    /// Verifies a proof and returns what was checked if it is invalid
+    ///
+    /// `None` means the proof is valid. Compare the public inputs with those of the prover,
+    /// e.g. from [`Proof::build_inputs`], and the verifying key fingerprint with that of the
+    /// prover artifacts to find the mismatch.
+    pub fn verify_proof_debug(
+        &self,
+        proof: &SemaphoreProof,
+    ) -> Result<Option<VerificationDebug>, SemaphoreError> {
+        check_proof(proof)?;
+
+        let verifying_key = self
+            .configured_verifying_key(proof.merkle_tree_depth)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+        if Self::verify_with_key_and_mode(&verifying_key, proof, self.config.hash_mode) {
+            return Ok(None);
+        }
+
+        Ok(Some(VerificationDebug {
+            public_inputs: public_inputs(proof, self.config.hash_mode)
+                .map(|inputs| inputs.iter().map(Fr::to_string).collect())
+                .unwrap_or_default(),
+            points: Self::unpack_groth16_proof_ref(&proof.points),
+            points_on_curve: groth16_proof(&proof.points).is_some(),
+            verifying_key_fingerprint: fingerprint(&verifying_key.vk),
+        }))
+    }
+
+    /// Verifies the proofs of an iterator lazily, as they are consumed
+    ///
+    /// Each proof is returned with its result, an unsupported depth, a root or nullifier that
+    /// is not a canonical field element or a verifying key that cannot be loaded being an
+    /// error, like in [`Proof::verify_and_extract`]. The verifying key of each depth is loaded
+    /// once for the stream, so only the keys of the depths seen are kept in memory.
+    pub fn verify_stream<'a>(
+        &'a self,
+        proofs: impl Iterator<Item = SemaphoreProof> + 'a,
+    ) -> impl Iterator<Item = (SemaphoreProof, Result<bool, SemaphoreError>)> + 'a {
+        let mut verifying_keys = HashMap::<u16, Arc<PreparedVerifyingKey<Bn254>>>::new();
+        proofs.map(move |proof| {
+            let depth = proof.merkle_tree_depth;
+            let verifying_key =
+                check_proof(&proof).and_then(|_| match verifying_keys.get(&depth) {
+                    Some(verifying_key) => Ok(verifying_key.clone()),
+                    None => {
+                        let verifying_key = self
+                            .configured_verifying_key(depth)
+                            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+                        verifying_keys.insert(depth, verifying_key.clone());
+                        Ok(verifying_key)
+                    }
+                });
+            let result = verifying_key
+                .map(|key| Self::verify_with_key_and_mode(&key, &proof, self.config.hash_mode));
+            (proof, result)
+        })
+    }
+
+    /// Verifies a batch of proofs and counts the valid, invalid and unverifiable ones
+    ///
+    /// Built on [`Proof::verify_stream`], so the verifying key of each depth is loaded once and
+    /// a root or nullifier that is not a canonical field element is counted as errored.
+    pub fn verify_proofs_summary(&self, proofs: &[SemaphoreProof]) -> VerificationSummary {
+        let mut summary = VerificationSummary {
+            total: proofs.len(),
+            ..Default::default()
+        };
+        for (_, result) in self.verify_stream(proofs.iter().cloned()) {
+            match result {
+                Ok(true) => summary.passed += 1,
+                Ok(false) => summary.failed += 1,
+                Err(_) => summary.errored += 1,
+            }
+        }
+        summary
+    }
+
+    /// Returns the fingerprint of the verifying key used for a tree depth
+    ///
+    /// See [`verifying_key_fingerprint`](crate::utils::verifying_key_fingerprint).
+    pub fn verifying_key_fingerprint(
+        &self,
+        merkle_tree_depth: u16,
+    ) -> Result<String, SemaphoreError> {
+        check_tree_depth(merkle_tree_depth)?;
+
+        let verifying_key = self
+            .configured_verifying_key(merkle_tree_depth)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+        Ok(fingerprint(&verifying_key.vk))
+    }
+
+    /// Verifies a proof, failing fast if the verifying key is not the expected one
+    ///
+    /// A proof generated with other artifacts is a [`SemaphoreError::VerifyingKeyMismatch`]
+    /// error instead of an invalid proof.
+    pub fn verify_with_fingerprint(
+        &self,
+        proof: &SemaphoreProof,
+        expected_fingerprint: &str,
+    ) -> Result<bool, SemaphoreError> {
+        let fingerprint = self.verifying_key_fingerprint(proof.merkle_tree_depth)?;
+        if fingerprint != expected_fingerprint {
+            return Err(SemaphoreError::VerifyingKeyMismatch {
+                expected: expected_fingerprint.to_string(),
+                actual: fingerprint,
+            });
+        }
+
+        Ok(self.verify_and_extract(proof)?.is_some())
+    }
+
+    /// Parses a proof exported with [`SemaphoreProof::export`] and verifies it
+    ///
+    /// Malformed JSON is a [`SemaphoreError::SerializationError`], other errors are those of
+    /// [`Proof::verify_and_extract`].
+    #[cfg(feature = "serde")]
+    pub fn verify_json(&self, json: &str) -> Result<bool, SemaphoreError> {
+        let proof = SemaphoreProof::import(json)?;
+        Ok(self.verify_and_extract(&proof)?.is_some())
+    }
+
+    /// Verifies a proof for a message and scope the verifier already knows
+    ///
+    /// Returns `false` without verifying if the proof is for another message or scope, so a
+    /// prover cannot claim a different one.
+    pub fn verify_proof_bound(
+        &self,
+        proof: &SemaphoreProof,
+        expected_message: &BigUint,
+        expected_scope: &BigUint,
+    ) -> Result<bool, SemaphoreError> {
+        if proof.message != *expected_message || proof.scope != *expected_scope {
+            return Ok(false);
+        }
+
+        Ok(self.verify_and_extract(proof)?.is_some())
+    }
+
+    /// Verifies a proof for a challenge issued with [`Scope::from_challenge`]
+    ///
+    /// Returns `false` without verifying if the proof scope is not that of the challenge, e.g.
+    /// a proof replayed from another verifier or another nonce.
+    pub fn verify_challenge(
+        &self,
+        proof: &SemaphoreProof,
+        verifier_id: &[u8],
+        nonce: &[u8],
+    ) -> Result<bool, SemaphoreError> {
+        if proof.scope != Scope::from_challenge(verifier_id, nonce) {
+            return Ok(false);
+        }
+
+        Ok(self.verify_and_extract(proof)?.is_some())
+    }
+
+    /// Verifies a proof for one of the recent roots of a window, in the window epoch
+    ///
+    /// Returns `false` without verifying if the root is not in the window or the epoch of the
+    /// proof scope, encoded with [`TimedScope`], is not the window epoch.
+    pub fn verify_proof_windowed(
+        &self,
+        proof: &SemaphoreProof,
+        window: &RootWindow,
+    ) -> Result<bool, SemaphoreError> {
+        if !window.accepts(proof) {
+            return Ok(false);
+        }
+
+        Ok(self.verify_and_extract(proof)?.is_some())
+    }
+
+    /// Verifies that all the proofs are valid, share `scope` and have distinct nullifiers
+    ///
+    /// This is the usual anti-double-signaling check, e.g. for a vote. The scope and nullifiers
+    /// are checked before the proofs, so a mismatch returns `false` without verifying anything.
+    /// A root or nullifier that is not a canonical field element is a
+    /// [`SemaphoreError::ProofFieldOutOfRange`] error: the verifier reduces it, so `nullifier + r`
+    /// would pass as a distinct nullifier. Other errors are those of [`Proof::verify_and_extract`].
+    pub fn verify_distinct(
+        &self,
+        proofs: &[SemaphoreProof],
+        scope: &BigUint,
+    ) -> Result<bool, SemaphoreError> {
+        for proof in proofs {
+            check_proof(proof)?;
+        }
+
+        if proofs.iter().any(|proof| proof.scope != *scope) {
+            return Ok(false);
+        }
+
+        let mut nullifiers = HashSet::with_capacity(proofs.len());
+        if !proofs
+            .iter()
+            .all(|proof| nullifiers.insert(&proof.nullifier))
+        {
+            return Ok(false);
+        }
+
+        for proof in proofs {
+            if self.verify_and_extract(proof)?.is_none() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Verifies proofs on the tokio blocking pool, preserving the input order in the result
+    ///
+    /// The verifying keys of the depths of the proofs are loaded, or downloaded, first with
+    /// one blocking task per depth, then each proof is verified in its own blocking task, so
+    /// the async runtime is never blocked. Must be called from a tokio runtime. A root or
+    /// nullifier that is not a canonical field element is a
+    /// [`SemaphoreError::ProofFieldOutOfRange`] error, like in [`Proof::verify_and_extract`].
+    #[cfg(feature = "async")]
+    pub async fn verify_proofs_async(
+        &self,
+        proofs: Vec<SemaphoreProof>,
+    ) -> Vec<Result<bool, SemaphoreError>> {
+        let depths: HashSet<u16> = proofs
+            .iter()
+            .map(|proof| proof.merkle_tree_depth)
+            .filter(|&depth| check_tree_depth(depth).is_ok())
+            .collect();
+        let loads: Vec<_> = depths
+            .into_iter()
+            .map(|depth| {
+                let verifier = self.clone();
+                tokio::task::spawn_blocking(move || {
+                    let verifying_key = verifier
+                        .configured_verifying_key(depth)
+                        .map_err(|e| e.to_string());
+                    (depth, verifying_key)
+                })
+            })
+            .collect();
+        let mut verifying_keys = HashMap::new();
+        for load in loads {
+            let (depth, verifying_key) = join(load.await);
+            verifying_keys.insert(depth, verifying_key);
+        }
+
+        let hash_mode = self.config.hash_mode;
+        let tasks: Vec<_> = proofs
+            .into_iter()
+            .map(|proof| {
+                let verifying_key = check_proof(&proof).and_then(|_| {
+                    verifying_keys[&proof.merkle_tree_depth]
+                        .clone()
+                        .map_err(SemaphoreError::VerifyingKeyUnavailable)
+                });
+                tokio::task::spawn_blocking(move || {
+                    verifying_key.map(|key| Self::verify_with_key_and_mode(&key, &proof, hash_mode))
+                })
+            })
+            .collect();
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(join(task.await));
+        }
+        results
+    }
+
+    /// # Panics
+    ///
+    /// If the identity is not a member of the group, or if the message or scope text is longer
+    /// than 32 bytes. [`Proof::generate`] returns these as errors.
+    #[deprecated(note = "use `Proof::default().generate`")]
+    pub fn generate_proof(
+        identity: Identity,
+        group: GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<SemaphoreProof> {
+        legacy_panics(Self::default().generate(identity, group, message, scope, merkle_tree_depth))
+    }
+
+    /// # Panics
+    ///
+    /// Like [`Proof::generate_proof`].
+    #[cfg(feature = "rayon")]
+    #[deprecated(note = "use `Proof::default().generate_on`")]
+    pub fn generate_proof_on(
+        pool: &rayon::ThreadPool,
+        identity: Identity,
+        group: GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<SemaphoreProof> {
+        legacy_panics(Self::default().generate_on(
+            pool,
+            identity,
+            group,
+            message,
+            scope,
+            merkle_tree_depth,
+        ))
+    }
+
+    #[deprecated(note = "use `Proof::default().verify`")]
+    pub fn verify_proof(proof: SemaphoreProof) -> bool {
+        Self::default().verify(proof)
+    }
+
+    /// Returns the zkey of a tree depth, downloading it unless a local zkey is configured
+    fn zkey_path(&self, merkle_tree_depth: u16) -> Result<String> {
+        match &self.config.zkey_path {
+            Some(zkey_path) if std::path::Path::new(zkey_path).is_file() => Ok(zkey_path.clone()),
+            Some(zkey_path) => Err(anyhow!("The zkey {zkey_path} does not exist")),
+            None => download_zkey_version(merkle_tree_depth, &self.config.circuit_version)
+                .map_err(|e| anyhow!(e.to_string())),
+        }
+    }
+
+    /// Computes the public signals of a proof without proving
+    ///
+    /// Much cheaper than [`Proof::generate`], e.g. to check a nullifier before proving.
+    pub fn compute_public_signals(
+        identity: &Identity,
+        group: &GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<PublicSignals, SemaphoreError> {
+        Self::compute_public_signals_with_mode(
+            identity,
+            group,
+            message,
+            scope,
+            merkle_tree_depth,
+            HashMode::Keccak,
+        )
+    }
+
+    /// Computes the public signals of a proof for circuits hashing with `hash_mode`
+    pub fn compute_public_signals_with_mode(
+        identity: &Identity,
+        group: &GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+        hash_mode: HashMode,
+    ) -> Result<PublicSignals, SemaphoreError> {
+        check_tree_depth(merkle_tree_depth)?;
+        group.check_capacity(merkle_tree_depth)?;
+
+        let merkle_proof = group.merkle_proof(&to_element(*identity.commitment()))?;
+        let scope = scope.into().try_to_big_uint()?;
+
+        Ok(PublicSignals {
+            merkle_tree_root: element_to_biguint(&merkle_proof.root),
+            nullifier: nullifier(identity, &hashed(&scope, hash_mode, "scope")?),
+            message: message.into().try_to_big_uint()?,
+            scope,
+        })
+    }
+
+    /// Builds the circuit inputs fed to the witness calculator, without proving
+    ///
+    /// Useful to compare against the inputs built by the semaphore-js SDK when a proof is invalid.
+    pub fn build_inputs(
+        identity: &Identity,
+        group: &GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<HashMap<String, Vec<String>>, SemaphoreError> {
+        Self::build_inputs_with_mode(
+            identity,
+            group,
+            message,
+            scope,
+            merkle_tree_depth,
+            HashMode::Keccak,
+        )
+    }
+
+    /// Builds the circuit inputs for circuits hashing the message and scope with `hash_mode`
+    pub fn build_inputs_with_mode(
+        identity: &Identity,
+        group: &GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+        hash_mode: HashMode,
+    ) -> Result<HashMap<String, Vec<String>>, SemaphoreError> {
+        check_tree_depth(merkle_tree_depth)?;
+        group.check_capacity(merkle_tree_depth)?;
+
+        let merkle_proof = group.merkle_proof(&to_element(*identity.commitment()))?;
+
+        circuit_inputs(
+            identity,
+            &merkle_proof,
+            &message.into().try_to_big_uint()?,
+            &scope.into().try_to_big_uint()?,
+            merkle_tree_depth,
+            hash_mode,
+        )
+    }
+
+    /// Loads the verifying key of a tree depth in memory
+    ///
+    /// Once loaded, `verify_proof` does not touch the filesystem or the network for that depth.
+    /// The keys are shared between threads.
+    pub fn preload(merkle_tree_depth: u16) -> Result<()> {
+        Self::preload_version(&CircuitVersion::Latest, merkle_tree_depth)
+    }
+
+    /// Loads the verifying key of a circuit version and tree depth in memory
+    pub fn preload_version(version: &CircuitVersion, merkle_tree_depth: u16) -> Result<()> {
+        Self::verifying_key(version, merkle_tree_depth).map(|_| ())
+    }
+
+    /// Loads the verifying key of the configured zkey, or of the configured circuit version
+    fn configured_verifying_key(
+        &self,
+        merkle_tree_depth: u16,
+    ) -> Result<Arc<PreparedVerifyingKey<Bn254>>> {
+        match &self.config.zkey_path {
+            Some(zkey_path) => Self::zkey_verifying_key(zkey_path),
+            None => Self::verifying_key(&self.config.circuit_version, merkle_tree_depth),
+        }
+    }
+
+    /// Loads the verifying key of a local zkey, reading the zkey only the first time
+    fn zkey_verifying_key(zkey_path: &str) -> Result<Arc<PreparedVerifyingKey<Bn254>>> {
+        if let Some(verifying_key) = ZKEY_VERIFYING_KEYS.read().unwrap().get(zkey_path) {
+            return Ok(verifying_key.clone());
+        }
+
+        let verifying_key = Arc::new(load_verifying_key(zkey_path)?);
+        ZKEY_VERIFYING_KEYS
+            .write()
+            .unwrap()
+            .insert(zkey_path.to_string(), verifying_key.clone());
+
+        Ok(verifying_key)
+    }
+
+    /// Verifies a proof with a verifying key held in memory, e.g. from a database
+    ///
+    /// The key is either a snarkjs `verification_key.json`, or the arkworks compressed
+    /// serialization returned by [`verifying_key_bytes`](crate::utils::verifying_key_bytes).
+    /// A malformed key is a [`SemaphoreError::VerifyingKeyUnavailable`] error.
+    pub fn verify_proof_with_vk_bytes(
+        proof: &SemaphoreProof,
+        vk: &[u8],
+    ) -> Result<bool, SemaphoreError> {
+        check_proof(proof)?;
+
+        let verifying_key = parse_verifying_key(vk)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+        // One point per public input, plus the constant one
+        if verifying_key.gamma_abc_g1.len() != PUBLIC_INPUTS + 1 {
+            return Err(SemaphoreError::VerifyingKeyUnavailable(format!(
+                "Expected {} IC points, got {}",
+                PUBLIC_INPUTS + 1,
+                verifying_key.gamma_abc_g1.len()
+            )));
+        }
+        Ok(Self::verify_with_key(
+            &prepare_verifying_key(&verifying_key),
+            proof,
+        ))
+    }
+
+    /// Checks the Groth16 proof against the public signals of a proof
+    ///
+    /// For verifiers managing their own keys. The proof is read in place: the conversions to
+    /// field elements and curve points allocate nothing, down from 39 allocations when they
+    /// went through cloned `BigUint`s and the circom proof types. The pairing check itself
+    /// allocates about 100 times. Points that are not canonical curve points make the proof
+    /// invalid.
+    pub fn verify_with_key(
+        verifying_key: &PreparedVerifyingKey<Bn254>,
+        proof: &SemaphoreProof,
+    ) -> bool {
+        Self::verify_with_key_and_mode(verifying_key, proof, HashMode::Keccak)
+    }
+
+    /// Checks the Groth16 proof for circuits hashing the message and scope with `hash_mode`
+    ///
+    /// With [`HashMode::Poseidon`], a message or scope larger than 256 bits makes the proof
+    /// invalid, and so does a key for another number of public inputs.
+    pub fn verify_with_key_and_mode(
+        verifying_key: &PreparedVerifyingKey<Bn254>,
+        proof: &SemaphoreProof,
+        hash_mode: HashMode,
+    ) -> bool {
+        let (Some(groth16_proof), Some(pub_inputs)) = (
+            groth16_proof(&proof.points),
+            public_inputs(proof, hash_mode),
+        ) else {
+            telemetry::verified(false);
+            return false;
+        };
+
+        let valid = Groth16::<Bn254>::verify_proof(verifying_key, &groth16_proof, &pub_inputs)
+            .unwrap_or(false);
+        telemetry::verified(valid);
+        valid
+    }
+
+    fn verifying_key(
+        version: &CircuitVersion,
+        merkle_tree_depth: u16,
+    ) -> Result<Arc<PreparedVerifyingKey<Bn254>>> {
+        check_tree_depth(merkle_tree_depth)?;
+
+        let key = (version.clone(), merkle_tree_depth);
+        if let Some(verifying_key) = VERIFYING_KEYS.read().unwrap().get(&key) {
+            return Ok(verifying_key.clone());
+        }
+
+        #[cfg(feature = "bundled-artifacts")]
+        let bundled = crate::bundled::verifying_key(version.as_str(), merkle_tree_depth)
+            .transpose()?
+            .map(|verifying_key| prepare_verifying_key(&verifying_key));
+        #[cfg(not(feature = "bundled-artifacts"))]
+        let bundled = None;
+
+        let verifying_key = match bundled {
+            Some(verifying_key) => Arc::new(verifying_key),
+            None => {
+                let zkey_path = download_zkey_version(merkle_tree_depth, version)
+                    .map_err(|e| anyhow!(e.to_string()))?;
+                Arc::new(load_verifying_key(&zkey_path)?)
+            }
+        };
+
+        VERIFYING_KEYS
+            .write()
+            .unwrap()
+            .insert(key, verifying_key.clone());
+
+        Ok(verifying_key)
+    }
+
+    pub fn pack_groth16_proof(p: circom::Proof) -> PackedGroth16Proof {
+        let [b_x0, b_x1, ..] = p.b.x;
+        let [b_y0, b_y1, ..] = p.b.y;
+        [p.a.x, p.a.y, b_x1, b_x0, b_y1, b_y0, p.c.x, p.c.y]
+    }
+
+    pub fn unpack_groth16_proof(packed: PackedGroth16Proof) -> circom::Proof {
+        let [a_x, a_y, b_x1, b_x0, b_y1, b_y0, c_x, c_y] = packed;
+        let a = G1 {
+            x: a_x,
+            y: a_y,
+            z: BigUint::one(),
+        };
+        let b = G2 {
+            x: [b_x0, b_x1],
+            y: [b_y0, b_y1],
+            z: [BigUint::one(), BigUint::zero()],
+        };
+        let c = G1 {
+            x: c_x,
+            y: c_y,
+            z: BigUint::one(),
+        };
+
+        circom::Proof {
+            a,
+            b,
+            c,
+            protocol: PROTOCOL_GROTH16.to_string(),
+            curve: CURVE_BN254.to_string(),
+        }
+    }
+
+    /// Unpacks a borrowed Groth16 proof, e.g. one kept in a [`SemaphoreProof`]
+    ///
+    /// Each coordinate is cloned once into the circom proof, which owns them, so a caller
+    /// keeping the packed proof does not clone it first.
+    pub fn unpack_groth16_proof_ref(packed: &PackedGroth16Proof) -> circom::Proof {
+        let [a_x, a_y, b_x1, b_x0, b_y1, b_y0, c_x, c_y] = packed;
+        let a = G1 {
+            x: a_x.clone(),
+            y: a_y.clone(),
+            z: BigUint::one(),
+        };
+        let b = G2 {
+            x: [b_x0.clone(), b_x1.clone()],
+            y: [b_y0.clone(), b_y1.clone()],
+            z: [BigUint::one(), BigUint::zero()],
+        };
+        let c = G1 {
+            x: c_x.clone(),
+            y: c_y.clone(),
+            z: BigUint::one(),
+        };
+
+        circom::Proof {
+            a,
+            b,
+            c,
+            protocol: PROTOCOL_GROTH16.to_string(),
+            curve: CURVE_BN254.to_string(),
+        }
+    }
+}
+
+/// Bounded cache of generated proofs, for apps that may request the same proof twice
+///
+/// Proofs are keyed by the identity commitment, group root, message, scope and tree depth, and
+/// by the hash mode, circuit version and zkey of the prover. Proving is not deterministic, so a hit returns the first proof generated for
+/// these inputs instead of a new one, and the nullifier is the same either way. The least
+/// recently used proof is evicted when the cache is full. Errors are not cached.
+#[derive(Debug, Clone)]
+pub struct ProofCache {
+    capacity: usize,
+    /// Proofs with the generation of their last use
+    proofs: HashMap<[u8; 32], (u64, SemaphoreProof)>,
+    /// Keys by generation of their last use, the least recently used first
+    order: BTreeMap<u64, [u8; 32]>,
+    /// Generation of the next use
+    generation: u64,
+}
+
+impl ProofCache {
+    /// Creates a cache holding up to `capacity` proofs, nothing is cached with `0`
+    pub fn new(capacity: usize) -> Self {
+        ProofCache {
+            capacity,
+            proofs: HashMap::with_capacity(capacity),
+            order: BTreeMap::new(),
+            generation: 0,
+        }
+    }
+
+    /// Returns the cached proof for these inputs, or generates it with `prover`
+    pub fn generate(
+        &mut self,
+        prover: &Proof,
+        identity: Identity,
+        group: GroupOrMerkleProof,
+        message: impl Into<Message>,
+        scope: impl Into<Scope>,
+        merkle_tree_depth: u16,
+    ) -> Result<SemaphoreProof> {
+        let message = message.into().try_to_big_uint()?;
+        let scope = scope.into().try_to_big_uint()?;
+        let key = Self::key(
+            &identity,
+            &group.root().ok_or(SemaphoreError::EmptyGroup)?,
+            &message,
+            &scope,
+            merkle_tree_depth,
+            &prover.config,
+        );
+
+        self.get_or_insert_with(key, || {
+            prover.generate(
+                identity,
+                group,
+                Message::Numeric(message),
+                Scope::Numeric(scope),
+                merkle_tree_depth,
+            )
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.proofs.clear();
+        self.order.clear();
+    }
+
+    /// Hashes the inputs, each length-prefixed so that they cannot be shifted into each other
+    ///
+    /// A zkey path is prefixed by a `1` byte, so that no path hashes like the default zkey.
+    fn key(
+        identity: &Identity,
+        root: &Element,
+        message: &BigUint,
+        scope: &BigUint,
+        merkle_tree_depth: u16,
+        config: &ProofConfig,
+    ) -> [u8; 32] {
+        let zkey_path = match &config.zkey_path {
+            Some(path) => [&[1], path.as_bytes()].concat(),
+            None => vec![0],
+        };
+        let mut input = Vec::new();
+        for value in [
+            identity.commitment_bytes().0.to_vec(),
+            root.to_vec(),
+            message.to_bytes_be(),
+            scope.to_bytes_be(),
+            merkle_tree_depth.to_le_bytes().to_vec(),
+            vec![config.hash_mode as u8],
+            config.circuit_version.as_str().as_bytes().to_vec(),
+            zkey_path,
+        ] {
+            input.extend_from_slice(&(value.len() as u64).to_le_bytes());
+            input.extend_from_slice(&value);
+        }
+
+        ethers_core::utils::keccak256(input)
+    }
+
+    fn get_or_insert_with(
+        &mut self,
+        key: [u8; 32],
+        prove: impl FnOnce() -> Result<SemaphoreProof>,
+    ) -> Result<SemaphoreProof> {
+        let generation = self.generation;
+        self.generation += 1;
+        if let Some((used, proof)) = self.proofs.get_mut(&key) {
+            self.order.remove(used);
+            self.order.insert(generation, key);
+            *used = generation;
+            return Ok(proof.clone());
+        }
+
+        let proof = prove()?;
+        if self.capacity == 0 {
+            return Ok(proof);
+        }
+        if self.proofs.len() == self.capacity
+            && let Some((_, oldest)) = self.order.pop_first()
+        {
+            self.proofs.remove(&oldest);
+        }
+        self.proofs.insert(key, (generation, proof.clone()));
+        self.order.insert(generation, key);
+
+        Ok(proof)
+    }
+}
+
+/// Returns the little-endian limbs of a `uint256`, or `None` if it is larger
+fn limbs(value: &BigUint) -> Option<[u64; 4]> {
+    if value.bits() > 256 {
+        return None;
+    }
+
+    let mut limbs = [0u64; 4];
+    for (limb, digit) in limbs.iter_mut().zip(value.iter_u64_digits()) {
+        *limb = digit;
+    }
+    Some(limbs)
+}
+
+/// Converts a value to a field element like `from_le_bytes_mod_order`, without allocating
+/// for canonical values
+fn field_element(value: &BigUint) -> Fr {
+    limbs(value)
+        .and_then(|limbs| Fr::from_bigint(BigInt::new(limbs)))
+        .unwrap_or_else(|| Fr::from_le_bytes_mod_order(&value.to_bytes_le()))
+}
+
+/// Converts a value to a field element, or a [`SemaphoreError::ProofFieldOutOfRange`] error if it
+/// is not smaller than the field order
+fn canonical_field_element(value: &BigUint, field: &'static str) -> Result<Fr, SemaphoreError> {
+    limbs(value)
+        .and_then(|limbs| Fr::from_bigint(BigInt::new(limbs)))
+        .ok_or(SemaphoreError::ProofFieldOutOfRange(field))
+}
+
+/// Checks that the root and nullifier of a proof are canonical field elements
+///
+/// The verifier reduces them, so a proof stays valid with the field order added to them.
+fn check_canonical_signals(proof: &SemaphoreProof) -> Result<(), SemaphoreError> {
+    canonical_field_element(&proof.merkle_tree_root, "merkle_tree_root")?;
+    canonical_field_element(&proof.nullifier, "nullifier")?;
+    Ok(())
+}
+
+/// Checks what every `Result`-returning verifier rejects before verifying a proof: an
+/// unsupported tree depth, and a root or nullifier that is not a canonical field element
+///
+/// Only [`Proof::verify`] and the key-level `verify_with_key*` skip it, and reduce them.
+fn check_proof(proof: &SemaphoreProof) -> Result<(), SemaphoreError> {
+    check_tree_depth(proof.merkle_tree_depth)?;
+    check_canonical_signals(proof)
+}
+
+/// Computes [`hash`] as a field element, without allocating for `uint256` values
+fn hash_to_field(value: &BigUint) -> Fr {
+    let Some(limbs) = limbs(value) else {
+        return field_element(&hash(value.clone()));
+    };
+    let mut bytes = [0u8; 32];
+    for (i, limb) in limbs.iter().enumerate() {
+        bytes[24 - 8 * i..32 - 8 * i].copy_from_slice(&limb.to_be_bytes());
+    }
+    let hashed = hash_bytes(&bytes);
+
+    // Big-endian bytes to little-endian limbs
+    let limbs = std::array::from_fn(|i| {
+        u64::from_be_bytes(hashed[24 - 8 * i..32 - 8 * i].try_into().unwrap())
+    });
+    Fr::from_bigint(BigInt::new(limbs)).expect("The hash fits in 248 bits")
+}
+
+/// Returns the public inputs of a proof in the circuit order, or `None` if the message or
+/// scope is too large for the hash mode
+fn public_inputs(proof: &SemaphoreProof, hash_mode: HashMode) -> Option<[Fr; 4]> {
+    Some([
+        field_element(&proof.merkle_tree_root),
+        field_element(&proof.nullifier),
+        hash_to_field_with_mode(&proof.message, hash_mode)?,
+        hash_to_field_with_mode(&proof.scope, hash_mode)?,
+    ])
+}
+
+/// Hashes a message or scope to a field element, or `None` if Poseidon would reduce it
+fn hash_to_field_with_mode(value: &BigUint, hash_mode: HashMode) -> Option<Fr> {
+    match hash_mode {
+        HashMode::Keccak => Some(hash_to_field(value)),
+        HashMode::Poseidon => {
+            let [low_0, low_1, high_0, high_1] = limbs(value)?;
+            let hash = crate::utils::poseidon(&[
+                Fq::from_bigint(BigInt::new([high_0, high_1, 0, 0]))?,
+                Fq::from_bigint(BigInt::new([low_0, low_1, 0, 0]))?,
+            ])
+            .ok()?;
+            Fr::from_bigint(hash.into_bigint())
+        }
+    }
+}
+
+/// Decodes packed Groth16 points, or `None` if they are not canonical curve points
+fn groth16_proof(points: &PackedGroth16Proof) -> Option<ark_groth16::Proof<Bn254>> {
+    let coordinate = |value: &BigUint| ark_bn254::Fq::from_bigint(BigInt::new(limbs(value)?));
+    let [a_x, a_y, b_x1, b_x0, b_y1, b_y0, c_x, c_y] = points;
+
+    let g1 = |x, y| -> Option<G1Affine> {
+        let (x, y) = (coordinate(x)?, coordinate(y)?);
+        if x.is_zero() && y.is_zero() {
+            return Some(G1Affine::identity());
+        }
+        let point = G1Affine::new_unchecked(x, y);
+        (point.is_on_curve() && point.is_in_correct_subgroup_assuming_on_curve()).then_some(point)
+    };
+    let x = Fq2::new(coordinate(b_x0)?, coordinate(b_x1)?);
+    let y = Fq2::new(coordinate(b_y0)?, coordinate(b_y1)?);
+    let b = if x.is_zero() && y.is_zero() {
+        G2Affine::identity()
+    } else {
+        let point = G2Affine::new_unchecked(x, y);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return None;
+        }
+        point
+    };
+
+    Some(ark_groth16::Proof {
+        a: g1(a_x, a_y)?,
+        b,
+        c: g1(c_x, c_y)?,
+    })
+}
+
+/// Reads a zkey and prepares its verifying key
+fn load_verifying_key(zkey_path: &str) -> Result<PreparedVerifyingKey<Bn254>> {
+    let mut reader = BufReader::new(File::open(zkey_path)?);
+    let proving_key = read_proving_key::<_, Bn254>(&mut reader)?;
+    Ok(prepare_verifying_key(&proving_key.vk))
+}
+
+/// Parses a verifying key, either a snarkjs `verification_key.json` or arkworks bytes
+fn parse_verifying_key(bytes: &[u8]) -> Result<VerifyingKey<Bn254>> {
+    if bytes.trim_ascii_start().starts_with(b"{") {
+        return parse_verifying_key_json(bytes);
+    }
+
+    Ok(VerifyingKey::deserialize_compressed(bytes)?)
+}
+
+/// Parses a snarkjs `verification_key.json`
+pub(crate) fn parse_verifying_key_json(json: &[u8]) -> Result<VerifyingKey<Bn254>> {
+    let json: serde_json::Value = serde_json::from_slice(json)?;
+
+    let field = |value: &serde_json::Value| -> Result<ark_bn254::Fq> {
+        let decimal = value.as_str().ok_or(anyhow!("Expected a decimal string"))?;
+        ark_bn254::Fq::from_str(decimal).map_err(|_| anyhow!("Invalid field element: {decimal}"))
+    };
+    let g1 = |value: &serde_json::Value| -> Result<G1Affine> {
+        if value[2] == "0" {
+            return Ok(G1Affine::identity());
+        }
+        let point = G1Affine::new_unchecked(field(&value[0])?, field(&value[1])?);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return Err(anyhow!("Invalid G1 point"));
+        }
+        Ok(point)
+    };
+    let g2 = |value: &serde_json::Value| -> Result<G2Affine> {
+        let fq2 = |value: &serde_json::Value| -> Result<Fq2> {
+            Ok(Fq2::new(field(&value[0])?, field(&value[1])?))
+        };
+        if value[2][0] == "0" && value[2][1] == "0" {
+            return Ok(G2Affine::identity());
+        }
+        let point = G2Affine::new_unchecked(fq2(&value[0])?, fq2(&value[1])?);
+        if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+            return Err(anyhow!("Invalid G2 point"));
+        }
+        Ok(point)
+    };
+
+    if json["protocol"] != "groth16" || json["curve"] != "bn128" {
+        return Err(anyhow!("Expected a groth16 bn128 verification key"));
+    }
+
+    Ok(VerifyingKey {
+        alpha_g1: g1(&json["vk_alpha_1"])?,
+        beta_g2: g2(&json["vk_beta_2"])?,
+        gamma_g2: g2(&json["vk_gamma_2"])?,
+        delta_g2: g2(&json["vk_delta_2"])?,
+        gamma_abc_g1: json["IC"]
+            .as_array()
+            .ok_or(anyhow!("Missing IC"))?
+            .iter()
+            .map(g1)
+            .collect::<Result<_>>()?,
+    })
+}
+
+/// Returns the output of a blocking task, resuming its panic if it panicked
+#[cfg(feature = "async")]
+fn join<T>(result: Result<T, tokio::task::JoinError>) -> T {
+    result.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+}
+
+/// Returns the tree depths supported by the circuits, [`MIN_TREE_DEPTH`] to [`MAX_TREE_DEPTH`]
+pub fn supported_depths() -> impl Iterator<Item = u16> {
+    MIN_TREE_DEPTH..=MAX_TREE_DEPTH
+}
+
+/// Returns true if the circuits support a tree depth
+pub fn is_supported_depth(merkle_tree_depth: u16) -> bool {
+    (MIN_TREE_DEPTH..=MAX_TREE_DEPTH).contains(&merkle_tree_depth)
+}
+
+/// Returns the number of members a tree of a supported depth holds, `2^depth`
+///
+/// `None` for depths the circuits do not support.
+pub fn max_members(merkle_tree_depth: u16) -> Option<u64> {
+    is_supported_depth(merkle_tree_depth).then(|| 1 << merkle_tree_depth)
+}
+
+/// Returns the number of members the largest supported tree holds, at [`MAX_TREE_DEPTH`]
+pub fn max_members_overall() -> u64 {
+    1 << MAX_TREE_DEPTH
+}
+
+/// Turns the errors the deprecated proof generators used to panic on back into panics
+fn legacy_panics(result: Result<SemaphoreProof>) -> Result<SemaphoreProof> {
+    match result
+        .as_ref()
+        .map_err(|e| e.downcast_ref::<SemaphoreError>())
+    {
+        Err(Some(SemaphoreError::IdentityNotInGroup)) => panic!("The identity does not exist"),
+        Err(Some(SemaphoreError::InputSizeExceeded(_))) => {
+            panic!("BigUint too large: exceeds 32 bytes")
+        }
+        _ => result,
+    }
+}
+
+/// Checks that a tree depth is supported by the circuits
+fn check_tree_depth(merkle_tree_depth: u16) -> Result<(), SemaphoreError> {
+    if !is_supported_depth(merkle_tree_depth) {
+        return Err(SemaphoreError::TreeDepthOutOfRange(merkle_tree_depth));
+    }
+
+    Ok(())
+}
+
+/// Hashes a message or scope for the circuit, rejecting values Poseidon would reduce
+fn hashed(
+    value: &BigUint,
+    hash_mode: HashMode,
+    field: &'static str,
+) -> Result<BigUint, SemaphoreError> {
+    if hash_mode == HashMode::Poseidon && value.bits() > 256 {
+        return Err(SemaphoreError::ProofFieldOutOfRange(field));
+    }
+
+    Ok(hash_mode.hash(value))
+}
+
+/// Computes the nullifier of the circuit from the hashed scope: `Poseidon(scope, secret)`
+fn nullifier(identity: &Identity, hashed_scope: &BigUint) -> BigUint {
+    let scope = Fq::from(hashed_scope.clone());
+    let secret = Fq::from_le_bytes_mod_order(&identity.secret_scalar().into_bigint().to_bytes_le());
+    let nullifier = Poseidon::<Fq>::new_circom(2)
+        .unwrap()
+        .hash(&[scope, secret])
+        .unwrap();
+
+    nullifier.into_bigint().into()
+}
+
+/// Builds the circuit inputs, padding the Merkle proof siblings to the tree depth
+fn circuit_inputs(
+    identity: &Identity,
+    merkle_proof: &MerkleProof,
+    message: &BigUint,
+    scope: &BigUint,
+    merkle_tree_depth: u16,
+    hash_mode: HashMode,
+) -> Result<HashMap<String, Vec<String>>, SemaphoreError> {
+    // The padding below would drop the siblings above the depth, and prove a subtree root
+    let merkle_proof_length = merkle_proof.siblings.len();
+    if merkle_proof_length > merkle_tree_depth as usize {
+        // The smallest group with a proof of this length
+        let size = 1usize
+            .checked_shl(merkle_proof_length as u32 - 1)
+            .map_or(usize::MAX, |size| size + 1);
+        return Err(SemaphoreError::GroupTooLargeForDepth {
+            size,
+            depth: merkle_tree_depth,
+        });
+    }
+
+    // A single-member tree has no siblings and its root is the leaf
+    if merkle_proof.siblings.is_empty() && merkle_proof.root != merkle_proof.leaf {
+        return Err(SemaphoreError::InvalidMerkleProof);
+    }
+
+    // The circuit only hashes the first `merkleProofLength` siblings, the zero padding is
+    // ignored
+    if !Group::verify_proof(merkle_proof) {
+        return Err(SemaphoreError::InvalidMerkleProof);
+    }
+    let mut merkle_proof_siblings = MerkleProofBig::from(merkle_proof).siblings;
+    merkle_proof_siblings.resize(merkle_tree_depth as usize, BigUint::zero());
+
+    Ok(HashMap::from([
+        (
+            "secret".to_string(),
+            vec![identity.secret_scalar().to_string()],
+        ),
+        (
+            "merkleProofLength".to_string(),
+            vec![merkle_proof.siblings.len().to_string()],
+        ),
+        (
+            "merkleProofIndex".to_string(),
+            vec![merkle_proof.index.to_string()],
+        ),
+        (
+            "merkleProofSiblings".to_string(),
+            merkle_proof_siblings
+                .iter()
+                .map(|sibling| sibling.to_string())
+                .collect(),
+        ),
+        (
+            "scope".to_string(),
+            vec![hashed(scope, hash_mode, "scope")?.to_string()],
+        ),
+        (
+            "message".to_string(),
+            vec![hashed(message, hash_mode, "message")?.to_string()],
+        ),
+    ]))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        group::{Element, Group},
+        identity::Identity,
+        proof::SemaphoreProof,
+    };
+    use num_bigint::BigUint;
+    use std::str::FromStr;
+
+    const TREE_DEPTH: usize = 10;
+    const MESSAGE: &str = "Hello world";
+    const SCOPE: &str = "Scope";
+
+    const MEMBER1: Element = [1; 32];
+    const MEMBER2: Element = [2; 32];
+
+    /// Returns the message of a caught panic, formatted or not
+    fn panic_message(payload: &(dyn std::any::Any + Send)) -> Option<&str> {
+        payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied())
+    }
+
+    // The fixture key has every point at the generator, so that a proof is valid when
+    // `a = 3 + sum(public inputs)` with `A = a * G1`, `B = G2` and `C = G1`.
+    pub(crate) const FIXTURE_VKEY: &[u8] =
+        include_bytes!("../tests/fixtures/bundled-artifacts/0.0.0-test/semaphore-10.vkey.json");
+    pub(crate) const FIXTURE_DEPTH: u16 = 10;
+
+    /// Returns a verifier of the fixture key, loaded in memory so that nothing is downloaded
+    ///
+    /// The key is loaded for a circuit version that does not exist, not the one of the bundled
+    /// artifacts tests, which must load it themselves.
+    fn fixture_verifier() -> Proof {
+        fixture_verifier_with_mode(HashMode::Keccak)
+    }
+
+    fn fixture_verifier_with_mode(hash_mode: HashMode) -> Proof {
+        let version = CircuitVersion::pinned("0.0.0-fixture").unwrap();
+        let verifying_key = parse_verifying_key_json(FIXTURE_VKEY).unwrap();
+        VERIFYING_KEYS.write().unwrap().insert(
+            (version.clone(), FIXTURE_DEPTH),
+            Arc::new(prepare_verifying_key(&verifying_key)),
+        );
+
+        Proof::new(ProofConfig {
+            circuit_version: version,
+            hash_mode,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn fixture_proof() -> SemaphoreProof {
+        fixture_proof_with_mode(HashMode::Keccak)
+    }
+
+    fn fixture_proof_with_mode(hash_mode: HashMode) -> SemaphoreProof {
+        let proof = SemaphoreProof {
+            merkle_tree_depth: FIXTURE_DEPTH,
+            merkle_tree_root: BigUint::from(1u8),
+            message: BigUint::from(2u8),
+            nullifier: BigUint::from(3u8),
+            scope: BigUint::from(4u8),
+            points: Default::default(),
+        };
+        prove_fixture(proof, hash_mode)
+    }
+
+    /// Sets the points that make a proof valid for the fixture key
+    fn prove_fixture(mut proof: SemaphoreProof, hash_mode: HashMode) -> SemaphoreProof {
+        use ark_ec::{AffineRepr, CurveGroup};
+
+        let inputs = [
+            Fr::from(proof.merkle_tree_root.clone()),
+            Fr::from(proof.nullifier.clone()),
+            Fr::from(hash_mode.hash(&proof.message)),
+            Fr::from(hash_mode.hash(&proof.scope)),
+        ];
+        let a = (G1Affine::generator() * (Fr::from(3u8) + inputs.iter().sum::<Fr>())).into_affine();
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        let coordinate = |x: ark_bn254::Fq| BigUint::from(x.into_bigint());
+        proof.points = [
+            coordinate(a.x),
+            coordinate(a.y),
+            coordinate(g2.x.c1),
+            coordinate(g2.x.c0),
+            coordinate(g2.y.c1),
+            coordinate(g2.y.c0),
+            coordinate(g1.x),
+            coordinate(g1.y),
+        ];
+        proof
+    }
+
+    #[cfg(test)]
+    mod gen_proof {
+        use super::*;
+        use std::panic::{self, AssertUnwindSafe};
+
+        #[test]
+        fn test_proof() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let root = group.root().unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            assert_eq!(proof.merkle_tree_root, element_to_biguint(&root));
+            assert_eq!(proof.message, to_big_uint(&MESSAGE.to_string()));
+            assert_eq!(proof.scope, to_big_uint(&SCOPE.to_string()));
+        }
+
+        #[test]
+        fn test_proof_1_member() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = Group::new(&[to_element(*identity.commitment())]).unwrap();
+            let root = group.root().unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            assert_eq!(proof.merkle_tree_root, element_to_biguint(&root));
+        }
+
+        #[test]
+        fn test_proof_1_member_smallest_depths() {
+            for depth in [MIN_TREE_DEPTH, 2] {
+                let identity = Identity::new("secret".as_bytes());
+                let group = Group::new(&[to_element(*identity.commitment())]).unwrap();
+
+                let proof = Proof::default()
+                    .generate(
+                        identity,
+                        GroupOrMerkleProof::Group(group),
+                        MESSAGE,
+                        SCOPE,
+                        depth,
+                    )
+                    .unwrap();
+
+                assert!(Proof::default().verify(proof), "depth {depth}");
+            }
+        }
+
+        #[test]
+        fn test_proof_with_semaphore_proof() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let root = group.root().unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::MerkleProof(group.generate_proof(2).unwrap()),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            assert_eq!(proof.merkle_tree_root, element_to_biguint(&root));
+        }
+
+        #[test]
+        fn test_error_invalid_tree_depth() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let result = Proof::default().generate(
+                identity,
+                GroupOrMerkleProof::Group(group),
+                MESSAGE.to_string(),
+                SCOPE.to_string(),
+                33u16,
+            );
+
+            assert_eq!(
+                result.unwrap_err().downcast_ref::<SemaphoreError>(),
+                Some(&SemaphoreError::TreeDepthOutOfRange(33))
+            );
+        }
+
+        #[test]
+        fn test_build_inputs() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let group = GroupOrMerkleProof::Group(group);
+
+            let inputs =
+                Proof::build_inputs(&identity, &group, MESSAGE, SCOPE, TREE_DEPTH as u16).unwrap();
+
+            let mut keys: Vec<&str> = inputs.keys().map(String::as_str).collect();
+            keys.sort();
+            assert_eq!(
+                keys,
+                vec![
+                    "merkleProofIndex",
+                    "merkleProofLength",
+                    "merkleProofSiblings",
+                    "message",
+                    "scope",
+                    "secret",
+                ]
+            );
+            assert_eq!(inputs["merkleProofSiblings"].len(), TREE_DEPTH);
+            assert_eq!(inputs["merkleProofLength"], vec!["1"]);
+            assert_eq!(inputs["merkleProofIndex"], vec!["1"]);
+            assert_eq!(inputs["secret"], vec![identity.secret_scalar().to_string()]);
+
+            assert_eq!(
+                Proof::build_inputs(&identity, &group, MESSAGE, SCOPE, 33),
+                Err(SemaphoreError::TreeDepthOutOfRange(33))
+            );
+        }
+
+        #[test]
+        fn test_build_inputs_1_member() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = Group::new(&[to_element(*identity.commitment())]).unwrap();
+            let merkle_proof = group.generate_proof(0).unwrap();
+            assert!(merkle_proof.siblings.is_empty());
+
+            for depth in [MIN_TREE_DEPTH, 2] {
+                let inputs = Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::Group(group.clone()),
+                    MESSAGE,
+                    SCOPE,
+                    depth,
+                )
+                .unwrap();
+
+                assert_eq!(inputs["merkleProofLength"], vec!["0"]);
+                assert_eq!(inputs["merkleProofIndex"], vec!["0"]);
+                assert_eq!(inputs["merkleProofSiblings"], vec!["0"; depth as usize]);
+            }
+
+            // The root of a single-member proof must be its leaf
+            let invalid = MerkleProof {
+                root: MEMBER1,
+                ..merkle_proof
+            };
+            assert_eq!(
+                Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::MerkleProof(invalid),
+                    MESSAGE,
+                    SCOPE,
+                    MIN_TREE_DEPTH,
+                ),
+                Err(SemaphoreError::InvalidMerkleProof)
+            );
+        }
+
+        #[test]
+        fn test_timed_scope() {
+            let base: BigUint = Scope::from_str_hashed("poll") >> 64u32;
+            let scope = TimedScope::new(base.clone(), 20_000).unwrap();
+            let encoded = scope.to_big_uint();
+
+            assert_eq!(encoded, (base.clone() << 64) + 20_000u32);
+            assert_eq!(extract_epoch(&encoded), 20_000);
+            assert_eq!(TimedScope::from_scope(&encoded), Some(scope.clone()));
+            assert_eq!(Scope::from(scope), Scope::Numeric(encoded));
+
+            // Epochs change the scope, and so the nullifier
+            let next = TimedScope::new(base.clone(), 20_001).unwrap();
+            assert_ne!(
+                next.to_big_uint(),
+                TimedScope::new(base, 20_000).unwrap().to_big_uint()
+            );
+
+            let largest = TimedScope::new((BigUint::one() << 192) - 1u8, u64::MAX).unwrap();
+            assert_eq!(largest.to_big_uint(), (BigUint::one() << 256) - 1u8);
+            assert_eq!(extract_epoch(&largest.to_big_uint()), u64::MAX);
+            assert_eq!(extract_epoch(&BigUint::zero()), 0);
+            assert_eq!(TimedScope::from_scope(&(BigUint::one() << 256)), None);
+            assert_eq!(
+                TimedScope::new(BigUint::one() << 192, 0),
+                Err(SemaphoreError::TimedScopeBaseTooLarge)
+            );
+        }
+
+        #[test]
+        fn test_supported_depths() {
+            assert_eq!(
+                supported_depths().collect::<Vec<u16>>(),
+                (1..=32).collect::<Vec<u16>>()
+            );
+            assert!(supported_depths().all(is_supported_depth));
+            assert!(!is_supported_depth(0));
+            assert!(!is_supported_depth(33));
+        }
+
+        #[test]
+        fn test_max_members() {
+            assert_eq!(max_members(1), Some(2));
+            assert_eq!(max_members(10), Some(1024));
+            assert_eq!(max_members(32), Some(4_294_967_296));
+            assert_eq!(max_members(0), None);
+            assert_eq!(max_members(33), None);
+            assert_eq!(max_members_overall(), max_members(MAX_TREE_DEPTH).unwrap());
+        }
+
+        #[test]
+        fn test_build_inputs_siblings() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let inputs = Proof::build_inputs(
+                &identity,
+                &GroupOrMerkleProof::Group(group.clone()),
+                MESSAGE,
+                SCOPE,
+                TREE_DEPTH as u16,
+            )
+            .unwrap();
+
+            let mut siblings: Vec<String> = group
+                .generate_proof_biguint(2)
+                .unwrap()
+                .siblings
+                .iter()
+                .map(BigUint::to_string)
+                .collect();
+            siblings.resize(TREE_DEPTH, "0".to_string());
+            assert_eq!(inputs["merkleProofSiblings"], siblings);
+        }
+
+        #[test]
+        fn test_build_inputs_merkle_proof_deeper_than_depth() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = Group::new(&[
+                to_element(*identity.commitment()),
+                MEMBER1,
+                MEMBER2,
+                [3; 32],
+                [4; 32],
+            ])
+            .unwrap();
+            let merkle_proof = group.generate_proof(0).unwrap();
+            assert_eq!(merkle_proof.siblings.len(), 3);
+
+            assert_eq!(
+                Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::MerkleProof(merkle_proof.clone()),
+                    MESSAGE,
+                    SCOPE,
+                    2,
+                ),
+                Err(SemaphoreError::GroupTooLargeForDepth { size: 5, depth: 2 })
+            );
+            assert!(
+                Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::MerkleProof(merkle_proof),
+                    MESSAGE,
+                    SCOPE,
+                    3,
+                )
+                .is_ok()
+            );
+        }
+
+        #[test]
+        fn test_build_inputs_invalid_merkle_proof() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let mut merkle_proof = group.generate_proof(2).unwrap();
+            merkle_proof.siblings[0] = MEMBER1;
+
+            assert_eq!(
+                Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::MerkleProof(merkle_proof),
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                ),
+                Err(SemaphoreError::InvalidMerkleProof)
+            );
+        }
+
+        #[test]
+        fn test_build_inputs_id_not_in_group() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = GroupOrMerkleProof::Group(Group::new(&[MEMBER1, MEMBER2]).unwrap());
+
+            assert_eq!(
+                Proof::build_inputs(&identity, &group, MESSAGE, SCOPE, TREE_DEPTH as u16),
+                Err(SemaphoreError::IdentityNotInGroup)
+            );
+        }
+
+        #[test]
+        fn test_build_inputs_text_over_32bytes() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = GroupOrMerkleProof::Group(
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap(),
+            );
+            let long = "This message is over 32 bytes long!!";
+
+            assert_eq!(
+                Proof::build_inputs(&identity, &group, long, SCOPE, TREE_DEPTH as u16),
+                Err(SemaphoreError::InputSizeExceeded(long.len()))
+            );
+            assert_eq!(
+                Proof::build_inputs(&identity, &group, MESSAGE, long, TREE_DEPTH as u16),
+                Err(SemaphoreError::InputSizeExceeded(long.len()))
+            );
+        }
+
+        #[test]
+        fn test_compute_public_signals() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let group = GroupOrMerkleProof::Group(group);
+
+            let signals =
+                Proof::compute_public_signals(&identity, &group, MESSAGE, SCOPE, TREE_DEPTH as u16)
+                    .unwrap();
+            let proof = Proof::default()
+                .generate(identity, group, MESSAGE, SCOPE, TREE_DEPTH as u16)
+                .unwrap();
+
+            assert_eq!(
+                signals,
+                PublicSignals {
+                    merkle_tree_root: proof.merkle_tree_root,
+                    nullifier: proof.nullifier,
+                    message: proof.message,
+                    scope: proof.scope,
+                }
+            );
+        }
+
+        #[test]
+        fn test_compute_public_signals_errors() {
+            let identity = Identity::new("secret".as_bytes());
+            let non_member = GroupOrMerkleProof::Group(Group::new(&[MEMBER1, MEMBER2]).unwrap());
+            let group = GroupOrMerkleProof::Group(
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap(),
+            );
+            let long = "This message is over 32 bytes long!!";
+
+            assert_eq!(
+                Proof::compute_public_signals(
+                    &identity,
+                    &non_member,
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16
+                ),
+                Err(SemaphoreError::IdentityNotInGroup)
+            );
+            assert_eq!(
+                Proof::compute_public_signals(&identity, &group, long, SCOPE, TREE_DEPTH as u16),
+                Err(SemaphoreError::InputSizeExceeded(long.len()))
+            );
+            assert_eq!(
+                Proof::compute_public_signals(&identity, &group, MESSAGE, long, TREE_DEPTH as u16),
+                Err(SemaphoreError::InputSizeExceeded(long.len()))
+            );
+        }
+
+        #[test]
+        fn test_build_inputs_with_mode() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let group = GroupOrMerkleProof::Group(group);
+            let scope = Scope::from(SCOPE).to_big_uint();
+
+            let inputs = Proof::build_inputs_with_mode(
+                &identity,
+                &group,
+                MESSAGE,
+                SCOPE,
+                TREE_DEPTH as u16,
+                HashMode::Poseidon,
+            )
+            .unwrap();
+            assert_eq!(
+                inputs["scope"],
+                vec![HashMode::Poseidon.hash(&scope).to_string()]
+            );
+            assert_eq!(
+                Proof::build_inputs_with_mode(
+                    &identity,
+                    &group,
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                    HashMode::Keccak,
+                ),
+                Proof::build_inputs(&identity, &group, MESSAGE, SCOPE, TREE_DEPTH as u16)
+            );
+
+            let signals = |hash_mode| {
+                Proof::compute_public_signals_with_mode(
+                    &identity,
+                    &group,
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                    hash_mode,
+                )
+                .unwrap()
+            };
+            assert_ne!(
+                signals(HashMode::Poseidon).nullifier,
+                signals(HashMode::Keccak).nullifier
+            );
+
+            // Poseidon would reduce the values larger than 256 bits
+            let too_large: BigUint = BigUint::one() << 256u32;
+            assert_eq!(
+                Proof::build_inputs_with_mode(
+                    &identity,
+                    &group,
+                    too_large.clone(),
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                    HashMode::Poseidon,
+                ),
+                Err(SemaphoreError::ProofFieldOutOfRange("message"))
+            );
+            assert_eq!(
+                Proof::compute_public_signals_with_mode(
+                    &identity,
+                    &group,
+                    MESSAGE,
+                    too_large,
+                    TREE_DEPTH as u16,
+                    HashMode::Poseidon,
+                ),
+                Err(SemaphoreError::ProofFieldOutOfRange("scope"))
+            );
+        }
+
+        #[test]
+        fn test_generate_verify_with_hash_mode() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let prover = |hash_mode| {
+                Proof::new(ProofConfig {
+                    hash_mode,
+                    ..Default::default()
+                })
+            };
+
+            for (hash_mode, other) in [
+                (HashMode::Keccak, HashMode::Poseidon),
+                (HashMode::Poseidon, HashMode::Keccak),
+            ] {
+                let proof = prover(hash_mode)
+                    .generate(
+                        identity.clone(),
+                        GroupOrMerkleProof::Group(group.clone()),
+                        MESSAGE,
+                        SCOPE,
+                        TREE_DEPTH as u16,
+                    )
+                    .unwrap();
+
+                assert!(prover(hash_mode).verify(&proof));
+                assert!(!prover(other).verify(&proof));
+            }
+        }
+
+        #[test]
+        fn test_public_signals_semaphore_js_nullifier() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let signals = Proof::compute_public_signals(
+                &identity,
+                &GroupOrMerkleProof::Group(group),
+                MESSAGE,
+                SCOPE,
+                TREE_DEPTH as u16,
+            )
+            .unwrap();
+
+            // Nullifier of the proof generated from `Semaphore-js`
+            assert_eq!(
+                signals.nullifier.to_string(),
+                "17540473064543782218297133630279824063352907908315494138425986188962403570231"
+            );
+        }
+
+        #[test]
+        fn test_from_merkle_proof() {
+            let group = Group::new(&[MEMBER1, MEMBER2]).unwrap();
+            let merkle_proof = group.generate_proof(1).unwrap();
+
+            assert!(GroupOrMerkleProof::from_merkle_proof(merkle_proof.clone()).is_ok());
+
+            let mut tampered = merkle_proof;
+            tampered.siblings[0] = [3; 32];
+            assert!(matches!(
+                GroupOrMerkleProof::from_merkle_proof(tampered),
+                Err(SemaphoreError::InvalidMerkleProof)
+            ));
+        }
+
+        #[test]
+        fn test_group_too_large_for_depth() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let expected = || SemaphoreError::GroupTooLargeForDepth { size: 3, depth: 1 };
+
+            assert_eq!(
+                Proof::build_inputs(
+                    &identity,
+                    &GroupOrMerkleProof::Group(group.clone()),
+                    MESSAGE,
+                    SCOPE,
+                    1
+                ),
+                Err(expected())
+            );
+
+            let result = Proof::default().generate(
+                identity,
+                GroupOrMerkleProof::Group(group),
+                MESSAGE,
+                SCOPE,
+                1,
+            );
+            assert_eq!(
+                result.unwrap_err().downcast::<SemaphoreError>().unwrap(),
+                expected()
+            );
+        }
+
+        #[test]
+        fn test_proof_local_zkey() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let zkey_path = std::env::temp_dir().join("semaphore-missing.zkey");
+            let proof = Proof::new(ProofConfig {
+                zkey_path: Some(zkey_path.to_string_lossy().into_owned()),
+                ..Default::default()
+            });
+
+            let result = proof.generate(
+                identity,
+                GroupOrMerkleProof::Group(group),
+                MESSAGE,
+                SCOPE,
+                TREE_DEPTH as u16,
+            );
+
+            // The configured zkey is used instead of the downloaded artifact
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                format!("The zkey {} does not exist", zkey_path.display())
+            );
+        }
+
+        #[test]
+        fn test_error_id_not_in_group() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = Group::new(&[MEMBER1, MEMBER2]).unwrap();
+
+            let err = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                )
+                .unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<SemaphoreError>(),
+                Some(&SemaphoreError::IdentityNotInGroup)
+            );
+        }
+
+        #[test]
+        fn test_error_text_over_32bytes() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let long = "This message is over 32 bytes long!!";
+
+            for (message, scope) in [(long, SCOPE), (MESSAGE, long)] {
+                let err = Proof::default()
+                    .generate(
+                        identity.clone(),
+                        GroupOrMerkleProof::Group(group.clone()),
+                        message,
+                        scope,
+                        TREE_DEPTH as u16,
+                    )
+                    .unwrap_err();
+                assert_eq!(
+                    err.downcast_ref::<SemaphoreError>(),
+                    Some(&SemaphoreError::InputSizeExceeded(long.len()))
+                );
+            }
+        }
+
+        #[test]
+        #[allow(deprecated)]
+        fn test_panic_id_not_in_group() {
+            let identity = Identity::new("secret".as_bytes());
+            let group = Group::new(&[MEMBER1, MEMBER2]).unwrap();
+
+            let err = panic::catch_unwind(AssertUnwindSafe(|| {
+                Proof::generate_proof(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap()
+            }));
+
+            assert_eq!(
+                panic_message(&*err.unwrap_err()),
+                Some("The identity does not exist")
+            );
+        }
+
+        #[test]
+        #[allow(deprecated)]
+        fn test_panic_message_over_32bytes() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let err = panic::catch_unwind(AssertUnwindSafe(|| {
+                Proof::generate_proof(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    "This message is over 32 bytes long!!".to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap()
+            }));
+
+            assert_eq!(
+                panic_message(&*err.unwrap_err()),
+                Some("BigUint too large: exceeds 32 bytes")
+            );
+        }
+
+        #[test]
+        #[allow(deprecated)]
+        fn test_panic_scope_over_32bytes() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let err = panic::catch_unwind(AssertUnwindSafe(|| {
+                Proof::generate_proof(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    "This scope is over 32 bytes long!!".to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap()
+            }));
+
+            assert_eq!(
+                panic_message(&*err.unwrap_err()),
+                Some("BigUint too large: exceeds 32 bytes")
+            );
+        }
+    }
+
+    #[test]
+    fn test_proof_cache() {
+        let proof = |nullifier: u8| SemaphoreProof {
+            merkle_tree_depth: TREE_DEPTH as u16,
+            merkle_tree_root: BigUint::from(1u8),
+            message: Message::from(MESSAGE).to_big_uint(),
+            nullifier: BigUint::from(nullifier),
+            scope: Scope::from(SCOPE).to_big_uint(),
+            points: std::array::from_fn(BigUint::from),
+        };
+        let identity = Identity::new("secret".as_bytes());
+        let key_with = |message: u8, config: &ProofConfig| {
+            ProofCache::key(
+                &identity,
+                &MEMBER1,
+                &BigUint::from(message),
+                &BigUint::from(42u8),
+                TREE_DEPTH as u16,
+                config,
+            )
+        };
+        let key = |message: u8| key_with(message, &ProofConfig::default());
+        let (a, b, c) = (key(1), key(2), key(3));
+        for config in [
+            ProofConfig {
+                hash_mode: HashMode::Poseidon,
+                ..Default::default()
+            },
+            ProofConfig {
+                circuit_version: CircuitVersion::pinned("4.0.0").unwrap(),
+                ..Default::default()
+            },
+            ProofConfig {
+                zkey_path: Some("semaphore-10.zkey".to_string()),
+                ..Default::default()
+            },
+            ProofConfig {
+                zkey_path: Some(String::new()),
+                ..Default::default()
+            },
+        ] {
+            assert_ne!(a, key_with(1, &config));
+        }
+
+        let proofs = std::cell::Cell::new(0u8);
+        let prove = || {
+            proofs.set(proofs.get() + 1);
+            Ok(proof(proofs.get()))
+        };
+        let mut cache = ProofCache::new(2);
+
+        // The second identical request returns the first proof without proving
+        let first = cache.get_or_insert_with(a, prove).unwrap();
+        assert_eq!(cache.get_or_insert_with(a, prove).unwrap(), first);
+        assert_eq!(proofs.get(), 1);
+
+        // `a` is used more recently than `b`, so `b` is evicted for `c`
+        cache.get_or_insert_with(b, prove).unwrap();
+        cache.get_or_insert_with(a, prove).unwrap();
+        cache.get_or_insert_with(c, prove).unwrap();
+        assert_eq!(proofs.get(), 3);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get_or_insert_with(a, prove).unwrap(), first);
+        assert_eq!(proofs.get(), 3);
+        cache.get_or_insert_with(b, prove).unwrap();
+        assert_eq!(proofs.get(), 4);
+
+        // Errors are not cached
+        let mut cache = ProofCache::new(2);
+        assert!(
+            cache
+                .get_or_insert_with(a, || Err(anyhow!("failed")))
+                .is_err()
+        );
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.get_or_insert_with(a, prove).unwrap().nullifier,
+            BigUint::from(5u8)
+        );
+
+        // Nothing is cached without capacity
+        let mut cache = ProofCache::new(0);
+        cache.get_or_insert_with(a, prove).unwrap();
+        cache.get_or_insert_with(a, prove).unwrap();
+        assert_eq!(proofs.get(), 7);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_proof_cache_text_over_32bytes() {
+        let identity = Identity::new("secret".as_bytes());
+        let group = Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+        let long = "This message is over 32 bytes long!!";
+
+        let err = ProofCache::new(1)
+            .generate(
+                &Proof::default(),
+                identity,
+                GroupOrMerkleProof::Group(group),
+                long,
+                SCOPE,
+                TREE_DEPTH as u16,
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SemaphoreError>(),
+            Some(&SemaphoreError::InputSizeExceeded(long.len()))
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_generate_proof_on_pool() {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+
+        let proofs: Vec<SemaphoreProof> = std::thread::scope(|s| {
+            let handles: Vec<_> = ["secret1", "secret2", "secret3"]
+                .into_iter()
+                .map(|secret| {
+                    let pool = &pool;
+                    s.spawn(move || {
+                        let identity = Identity::new(secret.as_bytes());
+                        let group =
+                            Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())])
+                                .unwrap();
+
+                        Proof::default()
+                            .generate_on(
+                                pool,
+                                identity,
+                                GroupOrMerkleProof::Group(group),
+                                MESSAGE,
+                                SCOPE,
+                                TREE_DEPTH as u16,
+                            )
+                            .unwrap()
+                    })
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        assert_eq!(proofs.len(), 3);
+        for proof in proofs {
+            assert!(Proof::default().verify(proof));
+        }
+    }
+
+    #[test]
+    fn test_semaphore_proof_builder() {
+        let points: Vec<BigUint> = (1..=8u8).map(BigUint::from).collect();
+        let builder = SemaphoreProof::builder()
+            .merkle_tree_depth(TREE_DEPTH as u16)
+            .merkle_tree_root(BigUint::from(1u8))
+            .message(BigUint::from(2u8))
+            .nullifier(BigUint::from(3u8))
+            .scope(BigUint::from(4u8))
+            .points(points.clone());
+
+        let proof = builder.clone().build().unwrap();
+        assert_eq!(proof.merkle_tree_depth, TREE_DEPTH as u16);
+        assert_eq!(proof.nullifier, BigUint::from(3u8));
+        assert_eq!(proof.points.to_vec(), points);
+
+        // Missing fields
+        assert_eq!(
+            SemaphoreProof::builder().build(),
+            Err(SemaphoreError::MissingProofField("merkle_tree_depth"))
+        );
+        assert_eq!(
+            SemaphoreProof::builder()
+                .merkle_tree_depth(TREE_DEPTH as u16)
+                .points(points.clone())
+                .build(),
+            Err(SemaphoreError::MissingProofField("merkle_tree_root"))
+        );
+
+        // Out of range values
+        let scalar_modulus = BigUint::from(Fr::MODULUS);
+        let mut out_of_range = points.clone();
+        out_of_range[7] = BigUint::from(ark_bn254::Fq::MODULUS);
+        assert_eq!(
+            builder.clone().merkle_tree_depth(33).build(),
+            Err(SemaphoreError::TreeDepthOutOfRange(33))
+        );
+        assert_eq!(
+            builder.clone().nullifier(scalar_modulus.clone()).build(),
+            Err(SemaphoreError::ProofFieldOutOfRange("nullifier"))
+        );
+        assert_eq!(
+            builder.clone().points(out_of_range).build(),
+            Err(SemaphoreError::ProofFieldOutOfRange("points"))
+        );
+        assert_eq!(
+            builder.clone().points(&points[..7]).build(),
+            Err(SemaphoreError::InvalidPointsLength(7))
+        );
+        assert_eq!(
+            builder.clone().message(BigUint::one() << 256).build(),
+            Err(SemaphoreError::ProofFieldOutOfRange("message"))
+        );
+
+        // Message and scope are hashed, so they can exceed the field
+        assert!(builder.scope(scalar_modulus).build().is_ok());
+    }
+
+    #[test]
+    fn test_check_root_consistency() {
+        let group = Group::new(&[MEMBER1, MEMBER2]).unwrap();
+        let mut proof = SemaphoreProof {
+            merkle_tree_depth: TREE_DEPTH as u16,
+            merkle_tree_root: element_to_biguint(&group.root().unwrap()),
+            message: BigUint::from(1u8),
+            nullifier: BigUint::from(2u8),
+            scope: BigUint::from(3u8),
+            points: std::array::from_fn(|_| BigUint::from(0u8)),
+        };
+        assert_eq!(proof.check_root_consistency(&group), Ok(true));
+
+        let other = Group::new(&[MEMBER1]).unwrap();
+        assert_eq!(proof.check_root_consistency(&other), Ok(false));
+        assert_eq!(
+            proof.check_root_consistency(&Group::default()),
+            Err(SemaphoreError::EmptyGroup)
+        );
+
+        proof.merkle_tree_depth = 0;
+        assert_eq!(
+            proof.check_root_consistency(&group),
+            Err(SemaphoreError::TreeDepthOutOfRange(0))
+        );
+    }
+
+    #[test]
+    fn test_allocation_free_conversions() {
+        let modulus = BigUint::from(Fr::MODULUS);
+        for value in [
+            BigUint::zero(),
+            BigUint::from(42u8),
+            &modulus - 1u8,
+            modulus.clone(),
+            BigUint::one() << 255,
+            BigUint::one() << 300,
+        ] {
+            let bytes = value.to_bytes_le();
+            assert_eq!(field_element(&value), Fr::from_le_bytes_mod_order(&bytes));
+            assert_eq!(
+                hash_to_field(&value),
+                Fr::from_le_bytes_mod_order(&hash(value.clone()).to_bytes_le())
+            );
+        }
+    }
+
+    #[test]
+    fn test_groth16_proof_points() {
+        use ark_ec::AffineRepr;
+
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        let coordinate = |x: ark_bn254::Fq| BigUint::from(x.into_bigint());
+        let points = [
+            coordinate(g1.x),
+            coordinate(g1.y),
+            coordinate(g2.x.c1),
+            coordinate(g2.x.c0),
+            coordinate(g2.y.c1),
+            coordinate(g2.y.c0),
+            BigUint::zero(),
+            BigUint::zero(),
+        ];
+
+        let proof = groth16_proof(&points).unwrap();
+        assert_eq!((proof.a, proof.b), (g1, g2));
+        assert_eq!(proof.c, G1Affine::identity());
+
+        // Not on the curve
+        let mut invalid = points.clone();
+        invalid[1] += 1u8;
+        assert!(groth16_proof(&invalid).is_none());
+
+        // Non-canonical coordinate
+        let mut invalid = points.clone();
+        invalid[0] += BigUint::from(ark_bn254::Fq::MODULUS);
+        assert!(groth16_proof(&invalid).is_none());
+    }
+
+    #[cfg(test)]
+    mod verify_proof {
         use super::*;
         use std::panic::{self, AssertUnwindSafe};
 
         #[test]
-        fn test_proof() {
+        fn test_verify_proof() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            assert!(Proof::default().verify(proof))
+        }
+
+        #[test]
+        fn test_verify_proof_with_different_depth() {
+            for depth in supported_depths() {
+                let identity = Identity::new("secret".as_bytes());
+                let group =
+                    Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+                let fits = group.size() as u64 <= 1 << depth;
+
+                let result = Proof::default().generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    depth,
+                );
+
+                // The group does not fit in the smallest trees
+                if !fits {
+                    assert!(result.is_err());
+                    continue;
+                }
+
+                assert!(Proof::default().verify(result.unwrap()));
+            }
+        }
+
+        #[test]
+        fn test_panic_verify_invalid_tree_depth() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let mut proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+            proof.merkle_tree_depth = 40;
+
+            let err = panic::catch_unwind(AssertUnwindSafe(|| Proof::default().verify(proof)));
+            assert_eq!(
+                panic_message(&*err.unwrap_err()),
+                Some("The tree depth must be a number between 1 and 32")
+            );
+        }
+
+        #[test]
+        fn test_error_verify_invalid_proof() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::MerkleProof(group.generate_proof(0).unwrap()),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            assert!(!Proof::default().verify(proof))
+        }
+
+        #[test]
+        fn test_verify_and_extract() {
             let identity = Identity::new("secret".as_bytes());
             let group =
                 Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-            let root = group.root().unwrap();
 
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            let verifier = Proof::default();
+            assert_eq!(
+                verifier.verify_and_extract(&proof),
+                Ok(Some(proof.nullifier.clone()))
+            );
+
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
+            assert_eq!(verifier.verify_and_extract(&invalid), Ok(None));
+        }
+
+        #[test]
+        fn test_verify_and_extract_errors() {
+            let mut proof = SemaphoreProof {
+                merkle_tree_depth: 0,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: std::array::from_fn(|_| BigUint::from(0u8)),
+            };
+
+            assert_eq!(
+                Proof::default().verify_and_extract(&proof),
+                Err(SemaphoreError::TreeDepthOutOfRange(0))
+            );
+
+            proof.merkle_tree_depth = TREE_DEPTH as u16;
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some("missing.zkey".to_string()),
+                ..Default::default()
+            });
+            assert!(matches!(
+                verifier.verify_and_extract(&proof),
+                Err(SemaphoreError::VerifyingKeyUnavailable(_))
+            ));
+
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            assert_eq!(
+                verifier.verify_and_extract(&proof),
+                Ok(Some(proof.nullifier.clone()))
+            );
+            let modulus = BigUint::from(Fr::MODULUS);
+            let mut shifted_root = proof.clone();
+            shifted_root.merkle_tree_root += &modulus;
+            let mut shifted_nullifier = proof.clone();
+            shifted_nullifier.nullifier += &modulus;
+            for (shifted, field) in [
+                (shifted_root, "merkle_tree_root"),
+                (shifted_nullifier, "nullifier"),
+            ] {
+                // Every `Result`-returning verifier rejects the proof before verifying it
+                let error = || SemaphoreError::ProofFieldOutOfRange(field);
+                assert_eq!(verifier.verify_and_extract(&shifted), Err(error()));
+                assert_eq!(
+                    verifier.verify_proof_with_timeout(&shifted, Duration::from_secs(60)),
+                    Err(error())
+                );
+                assert_eq!(
+                    verifier.verify_proof_prehashed(
+                        &shifted,
+                        &hash(shifted.message.clone()),
+                        &hash(shifted.scope.clone())
+                    ),
+                    Err(error())
+                );
+                assert_eq!(verifier.verify_proof_debug(&shifted), Err(error()));
+                assert_eq!(
+                    Proof::verify_proof_with_vk_bytes(&shifted, FIXTURE_VKEY),
+                    Err(error())
+                );
+                assert_eq!(
+                    verifier
+                        .verify_stream(std::iter::once(shifted.clone()))
+                        .next()
+                        .unwrap()
+                        .1,
+                    Err(error())
+                );
+                assert_eq!(
+                    verifier.verify_distinct(std::slice::from_ref(&shifted), &shifted.scope),
+                    Err(error())
+                );
+            }
+        }
+
+        // This test case is to test a semaphore-js proof can be verified by semaphore-rs verifier.
+        #[test]
+        fn test_semaphore_js_proof() {
+            let points = [
+                // Proof generated from `Semaphore-js`
+                "2448901300518098096993075752654536134313649038239216706400667219963346227679",
+                "11383357624181217239434984412545229801919536849542936327488167664579097021171",
+                "4740704242184999702574958393302343834384154042177684026319208048433986938524",
+                "2103898499672759617084297744151588687300569178309824227315704845907524437637",
+                "18126651739688030584140960766793516019865850111238360168731489534891060767936",
+                "13293264290162772264887787723520088518667325866686508255341288441681546077334",
+                "13860303418198054644271827809984867757526756615344099647083475463061491185143",
+                "7750331146056656453454308267328134694500438800080743301030181391570997944788",
+            ]
+            .iter()
+            .map(|&p| BigUint::from_str(p).unwrap())
+            .collect::<Vec<BigUint>>()
+            .try_into()
+            .expect("Expected exactly 8 elements");
+
+            let proof = SemaphoreProof {
+                merkle_tree_depth: 10,
+                merkle_tree_root: BigUint::from_str(
+                    "4990292586352433503726012711155167179034286198473030768981544541070532815155",
+                )
+                .unwrap(),
+                nullifier: BigUint::from_str(
+                    "17540473064543782218297133630279824063352907908315494138425986188962403570231",
+                )
+                .unwrap(),
+                message: BigUint::from_str(
+                    "32745724963520510550185023804391900974863477733501474067656557556163468591104",
+                )
+                .unwrap(),
+                scope: BigUint::from_str(
+                    "37717653415819232215590989865455204849443869931268328771929128739472152723456",
+                )
+                .unwrap(),
+                points,
+            };
+
+            assert!(Proof::default().verify(proof));
+        }
+
+        #[test]
+        fn test_semaphore_js_encoding() {
+            // Text values are encoded as `bytes32` strings, like semaphore-js
+            assert_eq!(
+                Message::from(MESSAGE).to_big_uint(),
+                BigUint::from_str(
+                    "32745724963520510550185023804391900974863477733501474067656557556163468591104",
+                )
+                .unwrap()
+            );
+            assert_eq!(
+                Scope::from(SCOPE).to_big_uint(),
+                BigUint::from_str(
+                    "37717653415819232215590989865455204849443869931268328771929128739472152723456",
+                )
+                .unwrap()
+            );
+
+            // Numeric values are used as is
+            let numeric = BigUint::from(42u8);
+            assert_eq!(Message::from(numeric.clone()).to_big_uint(), numeric);
+            assert_eq!(Scope::from(numeric.clone()).to_big_uint(), numeric);
+        }
+
+        #[test]
+        fn test_try_to_big_uint() {
+            let exact = "a".repeat(32);
+            let long = "a".repeat(33);
+
+            assert_eq!(
+                Message::from(exact.as_str()).try_to_big_uint(),
+                Ok(Message::from(exact.as_str()).to_big_uint())
+            );
+            assert_eq!(
+                Scope::from(exact.as_str()).try_to_big_uint(),
+                Ok(Scope::from(exact.as_str()).to_big_uint())
+            );
+            assert_eq!(
+                Message::from(long.as_str()).try_to_big_uint(),
+                Err(SemaphoreError::InputSizeExceeded(33))
+            );
+            assert_eq!(
+                Scope::from(long).try_to_big_uint(),
+                Err(SemaphoreError::InputSizeExceeded(33))
+            );
+
+            // Numeric values of any size are used as is
+            let large = BigUint::one() << 300u32;
+            assert_eq!(Scope::from(large.clone()).try_to_big_uint(), Ok(large));
+        }
+
+        #[test]
+        fn test_from_str_hashed() {
+            let exact = "a".repeat(32);
+            let long = "https://example.com/polls/2024/which-feature-should-ship-next";
+
+            for text in [SCOPE, exact.as_str(), long] {
+                let hashed = Scope::from_str_hashed(text);
+                assert_eq!(hashed, Message::from_str_hashed(text));
+                assert_eq!(
+                    hashed.to_bytes_be(),
+                    ethers_core::utils::keccak256(text.as_bytes())[..31]
+                );
+                assert!(hashed < BigUint::from(1u8) << 248);
+            }
+
+            // Distinct from the `bytes32` encoding, which only fits 32 bytes
+            assert_ne!(
+                Scope::from_str_hashed(SCOPE),
+                Scope::from(SCOPE).to_big_uint()
+            );
+            assert_ne!(
+                Scope::from_str_hashed(&exact),
+                Scope::from(exact.as_str()).to_big_uint()
+            );
+            assert_ne!(
+                Scope::from_str_hashed(long),
+                Scope::from_str_hashed(&long[1..])
+            );
+        }
+
+        #[test]
+        fn test_from_challenge() {
+            let scope = Scope::from_challenge(b"verifier", b"nonce");
+            assert_eq!(scope, Scope::from_challenge(b"verifier", b"nonce"));
+            assert!(scope < BigUint::from(1u8) << 248);
+
+            // Distinct verifiers, nonces and splits of the same bytes do not collide
+            let challenges: [(&[u8], &[u8]); 6] = [
+                (b"verifier", b"nonce"),
+                (b"verifier", b"nonce2"),
+                (b"other", b"nonce"),
+                (b"ab", b"c"),
+                (b"a", b"bc"),
+                (b"", b"abc"),
+            ];
+            let scopes: HashSet<BigUint> = challenges
+                .iter()
+                .map(|(verifier_id, nonce)| Scope::from_challenge(verifier_id, nonce))
+                .collect();
+            assert_eq!(scopes.len(), challenges.len());
+            assert_ne!(
+                Scope::from_challenge(b"verifier", b"nonce"),
+                Scope::from_str_hashed("verifiernonce")
+            );
+        }
+
+        #[test]
+        fn test_solidity_calldata() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: 10,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: std::array::from_fn(|i| BigUint::from(5 + i)),
+            };
+
+            let calldata = proof.to_solidity_calldata().unwrap();
+            let words: Vec<BigUint> = calldata.chunks(32).map(BigUint::from_bytes_be).collect();
+            let expected: Vec<BigUint> = [10u8, 1, 3, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12]
+                .into_iter()
+                .map(BigUint::from)
+                .collect();
+            assert_eq!(calldata.len(), 13 * 32);
+            assert_eq!(words, expected);
+
+            let mut proof = proof;
+            proof.nullifier = BigUint::one() << 256;
+            assert_eq!(
+                proof.to_solidity_calldata(),
+                Err(SemaphoreError::InputSizeExceeded(33))
+            );
+        }
+
+        #[test]
+        fn test_fixed_bytes() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: 10,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::one() << 255,
+                points: std::array::from_fn(|i| BigUint::from(5 + i)),
+            };
+
+            let bytes = proof.to_fixed_bytes().unwrap();
+            assert_eq!(bytes.len(), 386);
+            assert_eq!(bytes[..2], [0, 10]);
+            assert_eq!(bytes[2 + 31], 1);
+            assert_eq!(bytes[2 + 32 + 31], 3);
+            assert_eq!(bytes[2 + 3 * 32], 0x80);
+            assert_eq!(bytes[385], 12);
+            assert_eq!(SemaphoreProof::from_fixed_bytes(&bytes), Ok(proof.clone()));
+
+            // Values larger than 32 bytes do not fit
+            let mut overflow = proof;
+            overflow.message = BigUint::one() << 256;
+            assert_eq!(
+                overflow.to_fixed_bytes(),
+                Err(SemaphoreError::InputSizeExceeded(33))
+            );
+
+            // Decoded values are validated
+            let mut invalid = bytes;
+            invalid[..2].copy_from_slice(&33u16.to_be_bytes());
+            assert_eq!(
+                SemaphoreProof::from_fixed_bytes(&invalid),
+                Err(SemaphoreError::TreeDepthOutOfRange(33))
+            );
+            let mut invalid = bytes;
+            invalid[2 + 32..2 + 64].fill(0xff);
+            assert_eq!(
+                SemaphoreProof::from_fixed_bytes(&invalid),
+                Err(SemaphoreError::ProofFieldOutOfRange("nullifier"))
+            );
+        }
+
+        #[test]
+        fn test_from_solidity_calldata() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: 10,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::one() << 255,
+                points: std::array::from_fn(|i| BigUint::from(5 + i)),
+            };
+
+            let words: Vec<U256> = proof
+                .to_solidity_calldata()
+                .unwrap()
+                .chunks(32)
+                .map(U256::from_big_endian)
+                .collect();
+            let decoded = SemaphoreProof::from_solidity_calldata(
+                words[5..].try_into().unwrap(),
+                words[1],
+                words[2],
+                words[3],
+                words[4],
+                words[0].as_u32() as u16,
             )
             .unwrap();
+            assert_eq!(decoded, proof);
 
-            assert_eq!(proof.merkle_tree_root, BigUint::from_bytes_le(&root));
-            assert_eq!(proof.message, to_big_uint(&MESSAGE.to_string()));
-            assert_eq!(proof.scope, to_big_uint(&SCOPE.to_string()));
+            assert_eq!(
+                SemaphoreProof::from_solidity_calldata(
+                    [U256::MAX; 8],
+                    words[1],
+                    words[2],
+                    words[3],
+                    words[4],
+                    10,
+                ),
+                Err(SemaphoreError::ProofFieldOutOfRange("points"))
+            );
         }
 
         #[test]
-        fn test_proof_1_member() {
+        fn test_verify_proof_with_vk_bytes() {
             let identity = Identity::new("secret".as_bytes());
-            let group = Group::new(&[to_element(*identity.commitment())]).unwrap();
-            let root = group.root().unwrap();
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE.to_string(),
+                    SCOPE.to_string(),
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+            let zkey =
+                download_zkey_version(TREE_DEPTH as u16, &CircuitVersion::default()).unwrap();
+            let vk = crate::utils::verifying_key_bytes(std::path::Path::new(&zkey)).unwrap();
 
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
+            assert_eq!(Proof::verify_proof_with_vk_bytes(&proof, &vk), Ok(true));
+        }
+
+        #[test]
+        fn test_verify_proof_with_invalid_vk_bytes() {
+            let mut proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: Default::default(),
+            };
+            assert!(matches!(
+                Proof::verify_proof_with_vk_bytes(&proof, b"not a key"),
+                Err(SemaphoreError::VerifyingKeyUnavailable(_))
+            ));
+            assert!(matches!(
+                Proof::verify_proof_with_vk_bytes(&proof, b"{}"),
+                Err(SemaphoreError::VerifyingKeyUnavailable(_))
+            ));
+
+            // A key for another number of public inputs parses but cannot verify
+            use ark_serialize::CanonicalSerialize;
+            let mut verifying_key = parse_verifying_key_json(FIXTURE_VKEY).unwrap();
+            verifying_key.gamma_abc_g1.pop();
+            let mut vk = Vec::new();
+            verifying_key.serialize_compressed(&mut vk).unwrap();
+            assert_eq!(
+                Proof::verify_proof_with_vk_bytes(&fixture_proof(), &vk),
+                Err(SemaphoreError::VerifyingKeyUnavailable(
+                    "Expected 5 IC points, got 4".to_string()
+                ))
+            );
+            assert!(!Proof::verify_with_key(
+                &prepare_verifying_key(&verifying_key),
+                &fixture_proof()
+            ));
+
+            proof.merkle_tree_depth = 0;
+            assert_eq!(
+                Proof::verify_proof_with_vk_bytes(&proof, b"not a key"),
+                Err(SemaphoreError::TreeDepthOutOfRange(0))
+            );
+        }
+
+        #[test]
+        fn test_pack_unpack_groth16_proof() {
+            let packed: PackedGroth16Proof = std::array::from_fn(|i| BigUint::from(i + 1));
+
+            let unpacked = Proof::unpack_groth16_proof_ref(&packed);
+            assert_eq!(unpacked.b.x, [BigUint::from(4u8), BigUint::from(3u8)]);
+            assert_eq!(unpacked.b.y, [BigUint::from(6u8), BigUint::from(5u8)]);
+            assert_eq!(unpacked, Proof::unpack_groth16_proof(packed.clone()));
+            assert_eq!(
+                Proof::pack_groth16_proof(Proof::unpack_groth16_proof(packed.clone())),
+                packed
+            );
+            assert_eq!(Proof::pack_groth16_proof(unpacked), packed);
+        }
+
+        #[test]
+        fn test_verify_proof_by_ref() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            let verifier = Proof::default();
+            assert!(verifier.verify(&proof));
+            assert!(verifier.verify(proof));
+        }
+
+        #[test]
+        fn test_verify_proof_bound() {
+            let identity = Identity::new("secret".as_bytes());
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let proof = Proof::default()
+                .generate(
+                    identity,
+                    GroupOrMerkleProof::Group(group),
+                    MESSAGE,
+                    SCOPE,
+                    TREE_DEPTH as u16,
+                )
+                .unwrap();
+
+            let message = Message::from(MESSAGE).to_big_uint();
+            let scope = Scope::from(SCOPE).to_big_uint();
+            assert!(
+                Proof::default()
+                    .verify_proof_bound(&proof, &message, &scope)
+                    .unwrap()
+            );
+        }
+
+        #[test]
+        fn test_verify_bound_mismatch() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(1u8),
+                message: Message::from(MESSAGE).to_big_uint(),
+                nullifier: BigUint::from(2u8),
+                scope: Scope::from(SCOPE).to_big_uint(),
+                points: std::array::from_fn(BigUint::from),
+            };
+            let other = BigUint::from(42u8);
+
+            // Rejected before loading any verifying key
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some("missing.zkey".to_string()),
+                ..Default::default()
+            });
+            assert!(
+                !verifier
+                    .verify_proof_bound(&proof, &other, &proof.scope)
+                    .unwrap()
+            );
+            assert!(
+                !verifier
+                    .verify_proof_bound(&proof, &proof.message, &other)
+                    .unwrap()
+            );
+            assert!(
+                verifier
+                    .verify_proof_bound(&proof, &proof.message, &proof.scope)
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn test_verify_challenge_mismatch() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(1u8),
+                message: Message::from(MESSAGE).to_big_uint(),
+                nullifier: BigUint::from(2u8),
+                scope: Scope::from_challenge(b"verifier", b"nonce"),
+                points: std::array::from_fn(BigUint::from),
+            };
+
+            // Rejected before loading any verifying key
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some("missing.zkey".to_string()),
+                ..Default::default()
+            });
+            assert!(
+                !verifier
+                    .verify_challenge(&proof, b"verifier", b"replayed")
+                    .unwrap()
+            );
+            assert!(
+                !verifier
+                    .verify_challenge(&proof, b"other", b"nonce")
+                    .unwrap()
+            );
+            assert!(
+                verifier
+                    .verify_challenge(&proof, b"verifier", b"nonce")
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn test_root_window() {
+            let mut window = RootWindow::new(2, 7);
+            for root in 1..=3u8 {
+                window.push_root(BigUint::from(root));
+            }
+            assert_eq!(
+                window.roots().cloned().collect::<Vec<BigUint>>(),
+                vec![BigUint::from(2u8), BigUint::from(3u8)]
+            );
+            assert!(!window.contains_root(&BigUint::from(1u8)));
+
+            let base = BigUint::from(42u8);
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(2u8),
+                message: Message::from(MESSAGE).to_big_uint(),
+                nullifier: BigUint::from(2u8),
+                scope: TimedScope::new(base.clone(), 7).unwrap().to_big_uint(),
+                points: std::array::from_fn(BigUint::from),
+            };
+            assert!(window.accepts(&proof));
+
+            // Rejected before loading any verifying key
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some("missing.zkey".to_string()),
+                ..Default::default()
+            });
+            let mut out_of_window = proof.clone();
+            out_of_window.merkle_tree_root = BigUint::from(1u8);
+            assert_eq!(
+                verifier.verify_proof_windowed(&out_of_window, &window),
+                Ok(false)
+            );
+            let mut wrong_epoch = proof.clone();
+            wrong_epoch.scope = TimedScope::new(base, 6).unwrap().to_big_uint();
+            assert_eq!(
+                verifier.verify_proof_windowed(&wrong_epoch, &window),
+                Ok(false)
+            );
+            window.set_epoch(6);
+            assert!(window.accepts(&wrong_epoch));
+            assert!(!window.accepts(&proof));
+
+            let empty = RootWindow::new(0, 7);
+            let mut empty_window = empty.clone();
+            empty_window.push_root(BigUint::from(2u8));
+            assert_eq!(empty_window, empty);
+        }
+
+        #[test]
+        fn test_verify_distinct() {
+            let identity1 = Identity::new("secret1".as_bytes());
+            let identity2 = Identity::new("secret2".as_bytes());
+            let group = Group::new(&[
+                to_element(*identity1.commitment()),
+                to_element(*identity2.commitment()),
+            ])
             .unwrap();
+            let scope = to_big_uint(&SCOPE.to_string());
+
+            let proofs: Vec<SemaphoreProof> = [identity1, identity2]
+                .into_iter()
+                .map(|identity| {
+                    Proof::default()
+                        .generate(
+                            identity,
+                            GroupOrMerkleProof::Group(group.clone()),
+                            MESSAGE,
+                            SCOPE,
+                            TREE_DEPTH as u16,
+                        )
+                        .unwrap()
+                })
+                .collect();
+
+            assert!(Proof::default().verify_distinct(&proofs, &scope).unwrap());
+        }
+
+        #[test]
+        fn test_verify_distinct_rejects_before_verifying() {
+            let scope = BigUint::from(42u8);
+            let proof = |nullifier: u8, scope: &BigUint| SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::one(),
+                message: BigUint::one(),
+                nullifier: BigUint::from(nullifier),
+                scope: scope.clone(),
+                points: std::array::from_fn(|_| BigUint::one()),
+            };
+            let verifier = Proof::default();
+
+            // Duplicate nullifier
+            let proofs = [proof(1, &scope), proof(2, &scope), proof(1, &scope)];
+            assert!(!verifier.verify_distinct(&proofs, &scope).unwrap());
+
+            // Wrong scope
+            let proofs = [proof(1, &scope), proof(2, &BigUint::from(43u8))];
+            assert!(!verifier.verify_distinct(&proofs, &scope).unwrap());
+
+            // Invalid tree depth
+            let mut invalid = proof(1, &scope);
+            invalid.merkle_tree_depth = 33;
+            assert_eq!(
+                verifier.verify_distinct(&[invalid], &scope),
+                Err(SemaphoreError::TreeDepthOutOfRange(33))
+            );
+        }
+
+        #[test]
+        fn test_verify_distinct_non_canonical_nullifier() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            let mut shifted = proof.clone();
+            shifted.nullifier += BigUint::from(Fr::MODULUS);
 
-            assert_eq!(proof.merkle_tree_root, BigUint::from_bytes_le(&root));
+            assert_eq!(
+                verifier.verify_distinct(&[proof.clone(), shifted], &proof.scope),
+                Err(SemaphoreError::ProofFieldOutOfRange("nullifier"))
+            );
         }
 
         #[test]
-        fn test_proof_with_semaphore_proof() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-            let root = group.root().unwrap();
-
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::MerkleProof(group.generate_proof(2).unwrap()),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
-            .unwrap();
+        fn test_local_zkey_verifying_key_cached() {
+            // Not a file: the key can only come from the cache
+            let zkey_path = "semaphore-cached-fixture.zkey";
+            let verifying_key = parse_verifying_key_json(FIXTURE_VKEY).unwrap();
+            ZKEY_VERIFYING_KEYS.write().unwrap().insert(
+                zkey_path.to_string(),
+                Arc::new(prepare_verifying_key(&verifying_key)),
+            );
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some(zkey_path.to_string()),
+                ..Default::default()
+            });
 
-            assert_eq!(proof.merkle_tree_root, BigUint::from_bytes_le(&root));
+            let proof = fixture_proof();
+            assert_eq!(
+                verifier.verify_and_extract(&proof),
+                Ok(Some(proof.nullifier))
+            );
+            assert_eq!(
+                verifier.verifying_key_fingerprint(FIXTURE_DEPTH),
+                Ok(fingerprint(&verifying_key))
+            );
         }
 
         #[test]
-        fn test_error_invalid_tree_depth() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+        fn test_worker_slots() {
+            static WORKERS: AtomicUsize = AtomicUsize::new(0);
 
-            let result = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                33u16,
-            );
+            let first = WorkerSlot::acquire(&WORKERS, 2).unwrap();
+            let second = WorkerSlot::acquire(&WORKERS, 2).unwrap();
+            assert!(WorkerSlot::acquire(&WORKERS, 2).is_none());
 
-            assert!(result.is_err());
-            if let Err(err) = result {
-                if let Some(msg) = err.downcast_ref::<String>() {
-                    assert_eq!(msg, "The tree depth must be a number between 1 and 32");
-                }
-            }
+            drop(first);
+            let third = WorkerSlot::acquire(&WORKERS, 2).unwrap();
+            assert!(WorkerSlot::acquire(&WORKERS, 2).is_none());
+
+            drop((second, third));
+            assert_eq!(WORKERS.load(Ordering::Acquire), 0);
         }
 
         #[test]
-        fn test_panic_id_not_in_group() {
+        fn test_verify_distinct_key_unavailable() {
+            let scope = BigUint::from(42u8);
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::one(),
+                message: BigUint::one(),
+                nullifier: BigUint::one(),
+                scope: scope.clone(),
+                points: std::array::from_fn(|_| BigUint::one()),
+            };
+            let verifier = Proof::new(ProofConfig {
+                zkey_path: Some("missing.zkey".to_string()),
+                ..Default::default()
+            });
+
+            assert!(matches!(
+                verifier.verify_distinct(&[proof], &scope),
+                Err(SemaphoreError::VerifyingKeyUnavailable(_))
+            ));
+        }
+
+        #[test]
+        fn test_verify_proof_numeric_scope() {
             let identity = Identity::new("secret".as_bytes());
-            let group = Group::new(&[MEMBER1, MEMBER2]).unwrap();
+            let group =
+                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+            let scope = BigUint::from(42u8);
 
-            let err = panic::catch_unwind(AssertUnwindSafe(|| {
-                Proof::generate_proof(
+            let proof = Proof::default()
+                .generate(
                     identity,
                     GroupOrMerkleProof::Group(group),
-                    MESSAGE.to_string(),
-                    SCOPE.to_string(),
+                    MESSAGE,
+                    scope.clone(),
                     TREE_DEPTH as u16,
                 )
-                .unwrap()
-            }));
+                .unwrap();
 
-            assert!(err.is_err());
-            if let Err(err) = err {
-                if let Some(msg) = err.downcast_ref::<String>() {
-                    assert_eq!(msg, "The identity does not exist");
-                }
-            }
+            assert_eq!(proof.scope, scope);
+            assert!(Proof::default().verify(proof));
         }
 
+        #[cfg(feature = "serde")]
         #[test]
-        fn test_panic_message_over_32bytes() {
+        fn test_proof_export_import() {
             let identity = Identity::new("secret".as_bytes());
             let group =
                 Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-
-            let err = panic::catch_unwind(AssertUnwindSafe(|| {
-                Proof::generate_proof(
+            let proof = Proof::default()
+                .generate(
                     identity,
                     GroupOrMerkleProof::Group(group),
-                    "This message is over 32 bytes long!!".to_string(),
+                    MESSAGE.to_string(),
                     SCOPE.to_string(),
                     TREE_DEPTH as u16,
                 )
-                .unwrap()
-            }));
-
-            assert!(err.is_err());
-            if let Err(err) = err {
-                if let Some(msg) = err.downcast_ref::<String>() {
-                    assert_eq!(msg, "BigUint too large: exceeds 32 bytes");
-                }
-            }
+                .unwrap();
+            let proof_json = proof.export().unwrap();
+            let proof_imported = SemaphoreProof::import(&proof_json).unwrap();
+            assert_eq!(proof, proof_imported);
+            let valid = Proof::default().verify(proof_imported);
+            assert!(valid);
         }
 
+        #[cfg(feature = "serde")]
         #[test]
-        fn test_panic_scope_over_32bytes() {
+        fn test_verify_json() {
             let identity = Identity::new("secret".as_bytes());
             let group =
                 Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-
-            let err = panic::catch_unwind(AssertUnwindSafe(|| {
-                Proof::generate_proof(
+            let proof = Proof::default()
+                .generate(
                     identity,
                     GroupOrMerkleProof::Group(group),
-                    MESSAGE.to_string(),
-                    "This scope is over 32 bytes long!!".to_string(),
+                    MESSAGE,
+                    SCOPE,
                     TREE_DEPTH as u16,
                 )
-                .unwrap()
-            }));
+                .unwrap();
 
-            assert!(err.is_err());
-            if let Err(err) = err {
-                if let Some(msg) = err.downcast_ref::<String>() {
-                    assert_eq!(msg, "BigUint too large: exceeds 32 bytes");
-                }
+            assert_eq!(
+                Proof::default().verify_json(&proof.export().unwrap()),
+                Ok(true)
+            );
+        }
+
+        #[cfg(feature = "serde")]
+        #[test]
+        fn test_verify_json_malformed() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: std::array::from_fn(BigUint::from),
+            };
+            let json: serde_json::Value = serde_json::from_str(&proof.export().unwrap()).unwrap();
+
+            let mut missing = json.clone();
+            missing.as_object_mut().unwrap().remove("nullifier");
+            let mut short = json.clone();
+            short["points"].as_array_mut().unwrap().pop();
+            let mut not_decimal = json.clone();
+            not_decimal["scope"] = "0x04".into();
+
+            for malformed in [
+                "not json".to_string(),
+                missing.to_string(),
+                short.to_string(),
+                not_decimal.to_string(),
+            ] {
+                assert!(matches!(
+                    Proof::default().verify_json(&malformed),
+                    Err(SemaphoreError::SerializationError(_))
+                ));
             }
         }
-    }
 
-    #[cfg(test)]
-    mod verify_proof {
-        use super::*;
-        use std::panic::{self, AssertUnwindSafe};
+        #[test]
+        fn test_verify_stream_is_lazy() {
+            let proof = SemaphoreProof {
+                merkle_tree_depth: 0,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: Default::default(),
+            };
+            let pulled = std::cell::Cell::new(0);
+            let proofs = std::iter::repeat_with(|| {
+                pulled.set(pulled.get() + 1);
+                proof.clone()
+            });
+
+            let verifier = Proof::default();
+            let mut results = verifier.verify_stream(proofs);
+            assert_eq!(pulled.get(), 0);
 
+            let (first, result) = results.next().unwrap();
+            assert_eq!(first, proof);
+            assert_eq!(result, Err(SemaphoreError::TreeDepthOutOfRange(0)));
+            assert_eq!(pulled.get(), 1);
+        }
+
+        #[cfg(feature = "metrics")]
         #[test]
-        fn test_verify_proof() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+        fn test_verify_with_key_metrics() {
+            use ark_ec::AffineRepr;
+            use ark_groth16::VerifyingKey;
+            use metrics_util::debugging::{DebugValue, DebuggingRecorder};
 
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
-            .unwrap();
+            let recorder = DebuggingRecorder::new();
+            let snapshotter = recorder.snapshotter();
+            let verifying_key = prepare_verifying_key(&VerifyingKey {
+                alpha_g1: G1Affine::generator(),
+                beta_g2: G2Affine::generator(),
+                gamma_g2: G2Affine::generator(),
+                delta_g2: G2Affine::generator(),
+                gamma_abc_g1: vec![G1Affine::generator(); 5],
+            });
+            let proof = SemaphoreProof {
+                merkle_tree_depth: TREE_DEPTH as u16,
+                merkle_tree_root: BigUint::from(1u8),
+                message: BigUint::from(2u8),
+                nullifier: BigUint::from(3u8),
+                scope: BigUint::from(4u8),
+                points: Default::default(),
+            };
+
+            metrics::with_local_recorder(&recorder, || {
+                assert!(!Proof::verify_with_key(&verifying_key, &proof));
+                assert!(!Proof::verify_with_key(&verifying_key, &proof));
+            });
 
-            assert!(Proof::verify_proof(proof))
+            let snapshot = snapshotter.snapshot().into_vec();
+            let (key, _, _, value) = snapshot.first().unwrap();
+            assert_eq!(key.key().name(), telemetry::VERIFICATIONS);
+            assert_eq!(*value, DebugValue::Counter(2));
         }
 
         #[test]
-        fn test_verify_proof_with_different_depth() {
-            for depth in MIN_TREE_DEPTH..=MAX_TREE_DEPTH {
-                let identity = Identity::new("secret".as_bytes());
-                let group =
-                    Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+        fn test_verify_with_fingerprint() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            let fingerprint = verifier.verifying_key_fingerprint(FIXTURE_DEPTH).unwrap();
 
-                let proof = Proof::generate_proof(
-                    identity,
-                    GroupOrMerkleProof::Group(group),
-                    MESSAGE.to_string(),
-                    SCOPE.to_string(),
-                    depth as u16,
-                )
+            assert_eq!(
+                verifier.verify_with_fingerprint(&proof, &fingerprint),
+                Ok(true)
+            );
+            assert_eq!(
+                verifier.verify_with_fingerprint(&proof, "0000000000000000"),
+                Err(SemaphoreError::VerifyingKeyMismatch {
+                    expected: "0000000000000000".to_string(),
+                    actual: fingerprint,
+                })
+            );
+        }
+
+        #[test]
+        fn test_verify_stream() {
+            let verifier = fixture_verifier();
+            let valid = fixture_proof();
+            let mut invalid = valid.clone();
+            invalid.nullifier += 1u8;
+            let mut unsupported = valid.clone();
+            unsupported.merkle_tree_depth = 0;
+            let mut shifted = valid.clone();
+            shifted.nullifier += BigUint::from(Fr::MODULUS);
+
+            // Built as the stream is consumed
+            let proofs = (0..6).map(|i| match i {
+                1 => invalid.clone(),
+                3 => unsupported.clone(),
+                4 => shifted.clone(),
+                _ => valid.clone(),
+            });
+            let results: Vec<(SemaphoreProof, Result<bool, SemaphoreError>)> =
+                verifier.verify_stream(proofs).collect();
+
+            assert_eq!(
+                results,
+                vec![
+                    (valid.clone(), Ok(true)),
+                    (invalid, Ok(false)),
+                    (valid.clone(), Ok(true)),
+                    (unsupported, Err(SemaphoreError::TreeDepthOutOfRange(0))),
+                    (
+                        shifted,
+                        Err(SemaphoreError::ProofFieldOutOfRange("nullifier"))
+                    ),
+                    (valid, Ok(true)),
+                ]
+            );
+        }
+
+        #[test]
+        fn test_verify_proof_with_vk_bytes_formats() {
+            use ark_serialize::CanonicalSerialize;
+
+            let mut compressed = Vec::new();
+            parse_verifying_key_json(FIXTURE_VKEY)
+                .unwrap()
+                .serialize_compressed(&mut compressed)
                 .unwrap();
+            let proof = fixture_proof();
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
 
-                assert!(Proof::verify_proof(proof));
+            for vk in [FIXTURE_VKEY, &compressed[..]] {
+                assert_eq!(Proof::verify_proof_with_vk_bytes(&proof, vk), Ok(true));
+                assert_eq!(Proof::verify_proof_with_vk_bytes(&invalid, vk), Ok(false));
             }
         }
 
         #[test]
-        fn test_panic_verify_invalid_tree_depth() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-
-            let mut proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
-            .unwrap();
-            proof.merkle_tree_depth = 40;
+        fn test_verify_proofs_summary() {
+            let verifier = fixture_verifier();
+            let valid = fixture_proof();
+            let mut invalid = valid.clone();
+            invalid.nullifier += 1u8;
+            let mut unsupported = valid.clone();
+            unsupported.merkle_tree_depth = 33;
+            // Valid once reduced, but not a canonical nullifier
+            let mut shifted = valid.clone();
+            shifted.nullifier += BigUint::from(Fr::MODULUS);
 
-            let err = panic::catch_unwind(AssertUnwindSafe(|| Proof::verify_proof(proof)));
-            assert!(err.is_err());
-            if let Err(err) = err {
-                if let Some(msg) = err.downcast_ref::<String>() {
-                    assert_eq!(msg, "The tree depth must be a number between 1 and 32");
+            assert_eq!(
+                verifier.verify_proofs_summary(&[
+                    valid.clone(),
+                    invalid,
+                    unsupported,
+                    shifted,
+                    valid
+                ]),
+                VerificationSummary {
+                    total: 5,
+                    passed: 2,
+                    failed: 1,
+                    errored: 2,
                 }
-            }
+            );
+            assert_eq!(
+                verifier.verify_proofs_summary(&[]),
+                VerificationSummary::default()
+            );
         }
 
         #[test]
-        fn test_error_verify_invalid_proof() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
+        fn test_verify_with_hash_mode() {
+            let proof = fixture_proof_with_mode(HashMode::Poseidon);
 
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::MerkleProof(group.generate_proof(0).unwrap()),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
-            .unwrap();
+            assert!(fixture_verifier_with_mode(HashMode::Poseidon).verify(&proof));
+            assert!(!fixture_verifier_with_mode(HashMode::Keccak).verify(&proof));
+            assert!(!fixture_verifier_with_mode(HashMode::Poseidon).verify(fixture_proof()));
 
-            assert_eq!(Proof::verify_proof(proof), false)
+            // A string scope is a uint256 above the field modulus
+            let mut proof = proof;
+            proof.scope = Scope::from("scope").to_big_uint();
+            let proof = prove_fixture(proof, HashMode::Poseidon);
+            assert!(fixture_verifier_with_mode(HashMode::Poseidon).verify(&proof));
+
+            // The modulus added to the high half is reduced by the hash
+            let mut too_large = proof.clone();
+            too_large.scope += BigUint::from(Fr::MODULUS) << 128;
+            assert_eq!(
+                HashMode::Poseidon.hash(&too_large.scope),
+                HashMode::Poseidon.hash(&proof.scope)
+            );
+            assert!(!fixture_verifier_with_mode(HashMode::Poseidon).verify(&too_large));
         }
 
-        // This test case is to test a semaphore-js proof can be verified by semaphore-rs verifier.
         #[test]
-        fn test_semaphore_js_proof() {
-            let points = [
-                // Proof generated from `Semaphore-js`
-                "2448901300518098096993075752654536134313649038239216706400667219963346227679",
-                "11383357624181217239434984412545229801919536849542936327488167664579097021171",
-                "4740704242184999702574958393302343834384154042177684026319208048433986938524",
-                "2103898499672759617084297744151588687300569178309824227315704845907524437637",
-                "18126651739688030584140960766793516019865850111238360168731489534891060767936",
-                "13293264290162772264887787723520088518667325866686508255341288441681546077334",
-                "13860303418198054644271827809984867757526756615344099647083475463061491185143",
-                "7750331146056656453454308267328134694500438800080743301030181391570997944788",
-            ]
-            .iter()
-            .map(|&p| BigUint::from_str(p).unwrap())
-            .collect::<Vec<BigUint>>()
-            .try_into()
-            .expect("Expected exactly 8 elements");
+        fn test_verify_proof_with_timeout() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
+            let timeout = Duration::from_secs(60);
 
-            let proof = SemaphoreProof {
-                merkle_tree_depth: 10,
-                merkle_tree_root: BigUint::from_str(
-                    "4990292586352433503726012711155167179034286198473030768981544541070532815155",
-                )
-                .unwrap(),
-                nullifier: BigUint::from_str(
-                    "17540473064543782218297133630279824063352907908315494138425986188962403570231",
-                )
-                .unwrap(),
-                message: BigUint::from_str(
-                    "32745724963520510550185023804391900974863477733501474067656557556163468591104",
-                )
-                .unwrap(),
-                scope: BigUint::from_str(
-                    "37717653415819232215590989865455204849443869931268328771929128739472152723456",
-                )
-                .unwrap(),
-                points,
+            assert_eq!(
+                verifier.verify_proof_with_timeout(&proof, timeout),
+                Ok(true)
+            );
+            assert_eq!(
+                verifier.verify_proof_with_timeout(&invalid, timeout),
+                Ok(false)
+            );
+            invalid.merkle_tree_depth = 0;
+            assert_eq!(
+                verifier.verify_proof_with_timeout(&invalid, timeout),
+                Err(SemaphoreError::TreeDepthOutOfRange(0))
+            );
+
+            // A pairing check takes milliseconds, longer than no time at all
+            assert_eq!(
+                verifier.verify_proof_with_timeout(&proof, Duration::ZERO),
+                Err(SemaphoreError::VerificationTimeout)
+            );
+        }
+
+        #[test]
+        fn test_verify_any() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
+            let calldata = |proof: &SemaphoreProof| {
+                let words: Vec<U256> = proof
+                    .to_solidity_calldata()
+                    .unwrap()
+                    .chunks(32)
+                    .map(U256::from_big_endian)
+                    .collect();
+                ProofInput::Calldata(Box::new(words.try_into().unwrap()))
+            };
+            let fixed_bytes = |proof: &SemaphoreProof| {
+                ProofInput::FixedBytes(Box::new(proof.to_fixed_bytes().unwrap()))
             };
 
-            assert!(Proof::verify_proof(proof));
+            let inputs = |proof: &SemaphoreProof| {
+                #[allow(unused_mut)]
+                let mut inputs = vec![fixed_bytes(proof), calldata(proof)];
+                #[cfg(feature = "serde")]
+                inputs.push(ProofInput::Json(proof.export().unwrap()));
+                inputs
+            };
+
+            for input in inputs(&proof) {
+                assert_eq!(input.parse(), Ok(proof.clone()));
+                assert_eq!(verifier.verify_any(&input), Ok(true));
+            }
+            for input in inputs(&invalid) {
+                assert_eq!(verifier.verify_any(&input), Ok(false));
+            }
+
+            #[cfg(feature = "serde")]
+            assert!(matches!(
+                verifier.verify_any(&ProofInput::Json("{}".to_string())),
+                Err(SemaphoreError::SerializationError(_))
+            ));
+            let ProofInput::Calldata(mut words) = calldata(&proof) else {
+                unreachable!()
+            };
+            words[0] = U256::from(u32::from(u16::MAX) + 1);
+            assert_eq!(
+                verifier.verify_any(&ProofInput::Calldata(words)),
+                Err(SemaphoreError::ProofFieldOutOfRange("merkle_tree_depth"))
+            );
         }
 
-        #[cfg(feature = "serde")]
         #[test]
-        fn test_proof_export_import() {
-            let identity = Identity::new("secret".as_bytes());
-            let group =
-                Group::new(&[MEMBER1, MEMBER2, to_element(*identity.commitment())]).unwrap();
-            let proof = Proof::generate_proof(
-                identity,
-                GroupOrMerkleProof::Group(group),
-                MESSAGE.to_string(),
-                SCOPE.to_string(),
-                TREE_DEPTH as u16,
-            )
-            .unwrap();
-            let proof_json = proof.export().unwrap();
-            let proof_imported = SemaphoreProof::import(&proof_json).unwrap();
-            assert_eq!(proof, proof_imported);
-            let valid = Proof::verify_proof(proof_imported);
-            assert!(valid);
+        fn test_verify_proof_windowed() {
+            let verifier = fixture_verifier();
+            let mut proof = fixture_proof();
+            proof.scope = TimedScope::new(BigUint::from(42u8), 7)
+                .unwrap()
+                .to_big_uint();
+            let proof = prove_fixture(proof, HashMode::Keccak);
+
+            let mut window = RootWindow::new(3, 7);
+            window.push_root(proof.merkle_tree_root.clone());
+            assert_eq!(verifier.verify_proof_windowed(&proof, &window), Ok(true));
+
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
+            assert_eq!(verifier.verify_proof_windowed(&invalid, &window), Ok(false));
+
+            // The root is evicted by the newer roots
+            for root in 2..=4u8 {
+                window.push_root(BigUint::from(root));
+            }
+            assert_eq!(verifier.verify_proof_windowed(&proof, &window), Ok(false));
+        }
+
+        #[test]
+        fn test_verify_proof_prehashed() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            let (message, scope) = (hash(proof.message.clone()), hash(proof.scope.clone()));
+
+            assert_eq!(
+                verifier.verify_proof_prehashed(&proof, &message, &scope),
+                Ok(true)
+            );
+            // Unhashed values are not the public inputs of the proof
+            assert_eq!(
+                verifier.verify_proof_prehashed(&proof, &proof.message, &proof.scope),
+                Ok(false)
+            );
+
+            // The message and scope of the proof are not used
+            let mut unhashed = proof.clone();
+            unhashed.message = message.clone();
+            unhashed.scope = scope.clone();
+            assert!(!verifier.verify(&unhashed));
+            assert_eq!(
+                verifier.verify_proof_prehashed(&unhashed, &message, &scope),
+                Ok(true)
+            );
+
+            assert_eq!(
+                verifier.verify_proof_prehashed(&proof, &BigUint::from(Fr::MODULUS), &scope),
+                Err(SemaphoreError::ProofFieldOutOfRange("message"))
+            );
+            let mut shifted = proof.clone();
+            shifted.nullifier += BigUint::from(Fr::MODULUS);
+            assert_eq!(
+                verifier.verify_proof_prehashed(&shifted, &message, &scope),
+                Err(SemaphoreError::ProofFieldOutOfRange("nullifier"))
+            );
+        }
+
+        #[test]
+        fn test_verify_proof_debug() {
+            let verifier = fixture_verifier();
+            let proof = fixture_proof();
+            assert_eq!(verifier.verify_proof_debug(&proof), Ok(None));
+
+            let mut invalid = proof.clone();
+            invalid.nullifier += 1u8;
+            assert_eq!(
+                verifier.verify_proof_debug(&invalid),
+                Ok(Some(VerificationDebug {
+                    public_inputs: vec![
+                        "1".to_string(),
+                        "4".to_string(),
+                        hash(BigUint::from(2u8)).to_string(),
+                        hash(BigUint::from(4u8)).to_string(),
+                    ],
+                    points: Proof::unpack_groth16_proof_ref(&proof.points),
+                    points_on_curve: true,
+                    verifying_key_fingerprint: verifier
+                        .verifying_key_fingerprint(FIXTURE_DEPTH)
+                        .unwrap(),
+                }))
+            );
+
+            let mut off_curve = proof;
+            off_curve.points[1] += 1u8;
+            let debug = verifier.verify_proof_debug(&off_curve).unwrap().unwrap();
+            assert!(!debug.points_on_curve);
+            assert_eq!(debug.points.a.y, off_curve.points[1]);
+
+            off_curve.merkle_tree_depth = 0;
+            assert_eq!(
+                verifier.verify_proof_debug(&off_curve),
+                Err(SemaphoreError::TreeDepthOutOfRange(0))
+            );
         }
     }
 }
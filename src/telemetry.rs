@@ -0,0 +1,94 @@
+//! Metrics emitted through the `metrics` crate facade with the `metrics` feature
+//!
+//! Without the feature, the functions are empty and compiled away.
+
+use std::time::Duration;
+
+/// Counter of generated proofs
+pub const PROOFS_GENERATED: &str = "semaphore_proofs_generated_total";
+/// Histogram of the proving latency, in seconds
+pub const PROOF_DURATION: &str = "semaphore_proof_duration_seconds";
+/// Counter of verifications, labeled with `result` `passed` or `failed`
+pub const VERIFICATIONS: &str = "semaphore_verifications_total";
+/// Counter of downloaded artifact bytes
+pub const DOWNLOADED_BYTES: &str = "semaphore_downloaded_bytes_total";
+
+/// Records a generated proof and how long it took
+#[inline]
+pub(crate) fn proof_generated(_duration: Duration) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(PROOFS_GENERATED).increment(1);
+        metrics::histogram!(PROOF_DURATION).record(_duration.as_secs_f64());
+    }
+}
+
+/// Records the result of a verification
+#[inline]
+pub(crate) fn verified(_valid: bool) {
+    #[cfg(feature = "metrics")]
+    {
+        let result = if _valid { "passed" } else { "failed" };
+        metrics::counter!(VERIFICATIONS, "result" => result).increment(1);
+    }
+}
+
+/// Records downloaded bytes
+#[cfg(feature = "network")]
+#[inline]
+pub(crate) fn downloaded(_bytes: u64) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(DOWNLOADED_BYTES).increment(_bytes);
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    #[test]
+    fn test_counters() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+
+        metrics::with_local_recorder(&recorder, || {
+            proof_generated(Duration::from_millis(1500));
+            proof_generated(Duration::from_millis(500));
+            verified(true);
+            verified(false);
+            verified(false);
+            #[cfg(feature = "network")]
+            downloaded(1024);
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let value = |name: &str, result: Option<&str>| {
+            snapshot
+                .iter()
+                .find(|(key, _, _, _)| {
+                    key.key().name() == name
+                        && key.key().labels().map(|label| label.value()).next() == result
+                })
+                .map(|(_, _, _, value)| value)
+        };
+
+        assert_eq!(value(PROOFS_GENERATED, None), Some(&DebugValue::Counter(2)));
+        assert!(matches!(
+            value(PROOF_DURATION, None),
+            Some(DebugValue::Histogram(durations)) if durations.len() == 2
+        ));
+        assert_eq!(
+            value(VERIFICATIONS, Some("passed")),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            value(VERIFICATIONS, Some("failed")),
+            Some(&DebugValue::Counter(2))
+        );
+        #[cfg(feature = "network")]
+        assert_eq!(
+            value(DOWNLOADED_BYTES, None),
+            Some(&DebugValue::Counter(1024))
+        );
+    }
+}
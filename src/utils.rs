@@ -1,13 +1,28 @@
-use ark_ed_on_bn254::Fq;
-use ark_ff::{BigInteger, PrimeField};
-use ethers_core::utils::keccak256;
+use ark_bn254::Bn254;
+use ark_ed_on_bn254::{Fq, Fr};
+use ark_ff::{BigInt, BigInteger, PrimeField};
+use ark_groth16::VerifyingKey;
+use ark_serialize::CanonicalSerialize;
+use circom_prover::prover::ark_circom::read_proving_key;
+use ethers_core::utils::{hex, keccak256};
+use light_poseidon::{Poseidon, PoseidonHasher};
 use num_bigint::BigUint;
-use reqwest::blocking::Client;
+#[cfg(feature = "network")]
+use reqwest::{StatusCode, blocking::Client, header::RANGE};
 use std::error::Error;
 use std::fs::File;
-use std::io::copy;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+#[cfg(feature = "network")]
+use std::{fs::OpenOptions, io::copy};
 
-use crate::group::{EMPTY_ELEMENT, Element};
+use crate::error::SemaphoreError;
+#[cfg(feature = "serde")]
+use crate::group::MerkleProof;
+use crate::group::{ELEMENT_SIZE, EMPTY_ELEMENT, Element};
+#[cfg(feature = "serde")]
+use std::str::FromStr;
 
 pub fn string_to_biguint(num_str: &str) -> BigUint {
     num_str
@@ -15,10 +30,60 @@ pub fn string_to_biguint(num_str: &str) -> BigUint {
         .expect("Failed to parse the string into BigUint")
 }
 
-pub fn hash(message: BigUint) -> String {
-    let mut h = BigUint::from_bytes_be(&keccak256(message.to_bytes_be()));
-    h >>= 8;
-    h.to_string()
+/// Hashes a message with Keccak-256 and shifts it right by 8 bits so it fits the field
+///
+/// The message is left-padded to 32 bytes, like `toBeHex(message, 32)` in semaphore-js.
+pub fn hash(message: BigUint) -> BigUint {
+    let bytes = message.to_bytes_be();
+    let mut padded = vec![0u8; 32usize.saturating_sub(bytes.len())];
+    padded.extend_from_slice(&bytes);
+    BigUint::from_bytes_be(&hash_bytes(&padded))
+}
+
+/// Hash applied to the message and scope before they enter the circuit
+///
+/// The Semaphore circuits expect [`hash`] (Keccak-256). Circuit variants hashing with
+/// Poseidon need [`HashMode::Poseidon`], for both generating and verifying proofs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HashMode {
+    /// Keccak-256 shifted right by 8 bits, see [`hash`]
+    #[default]
+    Keccak,
+    /// Poseidon of the two 128-bit halves of the `uint256` value: `Poseidon(high, low)`
+    Poseidon,
+}
+
+impl HashMode {
+    /// Hashes a message or scope
+    ///
+    /// With [`HashMode::Poseidon`], the high half of values larger than 256 bits is reduced.
+    /// Proof generation and verification reject them, as two values would share a hash.
+    pub fn hash(&self, value: &BigUint) -> BigUint {
+        match self {
+            HashMode::Keccak => hash(value.clone()),
+            HashMode::Poseidon => {
+                let low = value & ((BigUint::from(1u8) << 128) - 1u8);
+                poseidon(&[Fq::from(value >> 128), Fq::from(low)])
+                    .expect("Poseidon supports 2 inputs")
+                    .into_bigint()
+                    .into()
+            }
+        }
+    }
+}
+
+/// Hashes raw bytes with Keccak-256 and shifts it right by 8 bits (big-endian output)
+pub fn hash_bytes(message: &[u8]) -> [u8; 32] {
+    let digest = keccak256(message);
+    let mut output = [0u8; 32];
+    output[1..].copy_from_slice(&digest[..31]);
+    output
+}
+
+/// Hashes a message and returns the decimal string representation
+#[deprecated(note = "use `hash`, which returns a `BigUint`")]
+pub fn hash_to_string(message: BigUint) -> String {
+    hash(message).to_string()
 }
 
 /// Converts a decimal string to BigUint and zero-pads it to 32 bytes (big-endian).
@@ -30,6 +95,36 @@ pub fn to_big_uint(str: &String) -> BigUint {
     BigUint::from_bytes_be(&fixed_bytes)
 }
 
+/// Converts a BigUint to 32 bytes (big-endian), left-padded with zeros
+///
+/// This is the `uint256` encoding expected on-chain.
+pub fn biguint_to_be_32(value: &BigUint) -> Result<[u8; 32], SemaphoreError> {
+    let bytes = value.to_bytes_be();
+    if bytes.len() > 32 {
+        return Err(SemaphoreError::InputSizeExceeded(bytes.len()));
+    }
+    let mut output = [0u8; 32];
+    output[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(output)
+}
+
+/// Converts an element to a BigUint (little-endian)
+pub fn element_to_biguint(element: &Element) -> BigUint {
+    BigUint::from_bytes_le(element)
+}
+
+/// Converts a BigUint to an element (little-endian), erroring if it exceeds 32 bytes
+pub fn element_from_biguint(value: &BigUint) -> Result<Element, SemaphoreError> {
+    let bytes = value.to_bytes_le();
+    if bytes.len() > ELEMENT_SIZE {
+        return Err(SemaphoreError::InputSizeExceeded(bytes.len()));
+    }
+
+    let mut element = EMPTY_ELEMENT;
+    element[..bytes.len()].copy_from_slice(&bytes);
+    Ok(element)
+}
+
 /// Converts Fq to Element in little-endian order
 pub fn to_element(value: Fq) -> Element {
     let mut element = EMPTY_ELEMENT;
@@ -38,20 +133,635 @@ pub fn to_element(value: Fq) -> Element {
     element
 }
 
+/// Converts a secret scalar to 32 bytes (little-endian)
+///
+/// For keys kept outside the process, e.g. in an HSM: the public key is
+/// [`PublicKey::from_scalar`] of the imported scalar.
+///
+/// [`PublicKey::from_scalar`]: crate::identity::PublicKey::from_scalar
+pub fn scalar_to_bytes(scalar: &Fr) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&scalar.into_bigint().to_bytes_le());
+    bytes
+}
+
+/// Converts 32 bytes (little-endian) to a secret scalar, erroring if they are not smaller
+/// than the Baby Jubjub subgroup order
+pub fn scalar_from_bytes(bytes: &[u8; 32]) -> Result<Fr, SemaphoreError> {
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    Fr::from_bigint(BigInt(limbs)).ok_or(SemaphoreError::NonCanonicalScalar)
+}
+
+/// Hashes field elements with the circomlib Poseidon instance for their number
+///
+/// Matches `poseidonN` of circomlibjs and `Poseidon(N)` in circuits. The parameters of
+/// light-poseidon cover 1 to 12 inputs, other arities return an error.
+pub fn poseidon(inputs: &[Fq]) -> Result<Fq, SemaphoreError> {
+    Poseidon::<Fq>::new_circom(inputs.len())
+        .and_then(|mut poseidon| poseidon.hash(inputs))
+        .map_err(|_| SemaphoreError::UnsupportedPoseidonArity(inputs.len()))
+}
+
+/// Exports a Merkle proof to a JSON matching semaphore-js: `{ root, leaf, index, siblings }`
+///
+/// Elements are written as decimal strings.
+#[cfg(feature = "serde")]
+pub fn export_merkle_proof(proof: &MerkleProof) -> String {
+    serde_json::json!({
+        "root": element_to_biguint(&proof.root).to_string(),
+        "leaf": element_to_biguint(&proof.leaf).to_string(),
+        "index": proof.index,
+        "siblings": proof
+            .siblings
+            .iter()
+            .map(|s| element_to_biguint(s).to_string())
+            .collect::<Vec<String>>(),
+    })
+    .to_string()
+}
+
+/// Imports a Merkle proof from a JSON exported by semaphore-js or [`export_merkle_proof`]
+#[cfg(feature = "serde")]
+pub fn import_merkle_proof(json: &str) -> Result<MerkleProof, SemaphoreError> {
+    let json: serde_json::Value = serde_json::from_str(json)
+        .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+
+    let element = |value: &serde_json::Value| -> Result<Element, SemaphoreError> {
+        let decimal = value.as_str().ok_or_else(|| {
+            SemaphoreError::SerializationError("Expected a decimal string".to_string())
+        })?;
+        let value = BigUint::from_str(decimal)
+            .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+        element_from_biguint(&value)
+    };
+
+    let siblings = json["siblings"]
+        .as_array()
+        .ok_or_else(|| SemaphoreError::SerializationError("Missing siblings".to_string()))?
+        .iter()
+        .map(element)
+        .collect::<Result<Vec<Element>, SemaphoreError>>()?;
+
+    Ok(MerkleProof {
+        root: element(&json["root"])?,
+        leaf: element(&json["leaf"])?,
+        index: json["index"]
+            .as_u64()
+            .ok_or_else(|| SemaphoreError::SerializationError("Missing index".to_string()))?
+            as usize,
+        siblings,
+    })
+}
+
+/// Returns a short hash of the verifying key of a zkey
+///
+/// Proofs only verify against the key of the zkey they were generated with: comparing
+/// fingerprints tells a key mismatch, e.g. after an artifacts update, from an invalid proof.
+pub fn verifying_key_fingerprint(zkey_path: &Path) -> Result<String, SemaphoreError> {
+    Ok(fingerprint(&zkey_verifying_key(zkey_path)?))
+}
+
+/// Extracts the verifying key of a zkey, in the arkworks compressed serialization
+///
+/// A few hundred bytes to store and pass to [`Proof::verify_proof_with_vk_bytes`] instead of
+/// the zkey.
+///
+/// [`Proof::verify_proof_with_vk_bytes`]: crate::proof::Proof::verify_proof_with_vk_bytes
+pub fn verifying_key_bytes(zkey_path: &Path) -> Result<Vec<u8>, SemaphoreError> {
+    let mut bytes = Vec::new();
+    zkey_verifying_key(zkey_path)?
+        .serialize_compressed(&mut bytes)
+        .expect("Serializing to a vector cannot fail");
+    Ok(bytes)
+}
+
+/// Reads the verifying key of a zkey
+fn zkey_verifying_key(zkey_path: &Path) -> Result<VerifyingKey<Bn254>, SemaphoreError> {
+    let mut reader = BufReader::new(
+        File::open(zkey_path)
+            .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?,
+    );
+    let proving_key = read_proving_key::<_, Bn254>(&mut reader)
+        .map_err(|e| SemaphoreError::VerifyingKeyUnavailable(e.to_string()))?;
+    Ok(proving_key.vk)
+}
+
+/// Hashes a verifying key with Keccak-256, as 16 hex characters
+pub(crate) fn fingerprint(verifying_key: &VerifyingKey<Bn254>) -> String {
+    let mut bytes = Vec::new();
+    verifying_key
+        .serialize_compressed(&mut bytes)
+        .expect("Serializing to a vector cannot fail");
+    hex::encode(&keccak256(&bytes)[..8])
+}
+
+/// Default base URL of the Semaphore artifacts
+pub const DEFAULT_ARTIFACTS_BASE_URL: &str = "https://snark-artifacts.pse.dev/semaphore";
+
+static ARTIFACTS_BASE_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Overrides the base URL the artifacts are downloaded from, `None` restores the default
+pub fn set_artifacts_base_url(url: Option<&str>) {
+    *ARTIFACTS_BASE_URL.write().unwrap() = url.map(|u| u.trim_end_matches('/').to_string());
+}
+
+/// Returns the base URL the artifacts are downloaded from
+pub fn artifacts_base_url() -> String {
+    ARTIFACTS_BASE_URL
+        .read()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| DEFAULT_ARTIFACTS_BASE_URL.to_string())
+}
+
+/// Version of the Semaphore circuits the artifacts are downloaded for
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum CircuitVersion {
+    /// Latest version supported by this crate, [`LATEST_CIRCUIT_VERSION`]
+    #[default]
+    Latest,
+    /// Pinned artifacts version, built with [`CircuitVersion::pinned`]
+    Pinned(PinnedVersion),
+}
+
+/// Artifacts version of [`CircuitVersion::Pinned`], e.g. `4.0.0` for `semaphore/4.0.0/`
+///
+/// Only built by [`CircuitVersion::pinned`], so it is always a single path component.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PinnedVersion(String);
+
+impl PinnedVersion {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Artifacts version used for [`CircuitVersion::Latest`]
+pub const LATEST_CIRCUIT_VERSION: &str = "4.13.0";
+
+impl CircuitVersion {
+    /// Pins an artifacts version, e.g. `"4.0.0"` for `semaphore/4.0.0/` (a leading `v` is ignored)
+    ///
+    /// The version names the artifacts directory, in the URL, the cached files and
+    /// `SEMAPHORE_ARTIFACTS_DIR`. Versions that are not made of ASCII letters, digits, `.`, `+`
+    /// and `-`, or that contain `..`, are a [`SemaphoreError::InvalidCircuitVersion`] error.
+    pub fn pinned(version: &str) -> Result<Self, SemaphoreError> {
+        let trimmed = version.trim_start_matches('v');
+        if !is_version_component(trimmed) {
+            return Err(SemaphoreError::InvalidCircuitVersion(version.to_string()));
+        }
+
+        Ok(CircuitVersion::Pinned(PinnedVersion(trimmed.to_string())))
+    }
+
+    /// Returns the artifacts directory name
+    pub fn as_str(&self) -> &str {
+        match self {
+            CircuitVersion::Latest => LATEST_CIRCUIT_VERSION,
+            CircuitVersion::Pinned(version) => version.as_str(),
+        }
+    }
+}
+
+/// Environment variable pointing at a directory of pre-downloaded artifacts
+pub const ARTIFACTS_DIR_ENV: &str = "SEMAPHORE_ARTIFACTS_DIR";
+
+/// Looks for a zkey in the `SEMAPHORE_ARTIFACTS_DIR` directory
+///
+/// The zkey is searched in `{dir}/{version}/semaphore-{depth}.zkey`, like the artifacts URL,
+/// then in `{dir}/semaphore-{depth}.zkey`.
+fn local_zkey(version: &str, depth: u16) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let Some(dir) = std::env::var_os(ARTIFACTS_DIR_ENV).filter(|dir| !dir.is_empty()) else {
+        return Ok(None);
+    };
+    let dir = PathBuf::from(dir);
+    if !dir.is_dir() {
+        return Err(format!("{ARTIFACTS_DIR_ENV} is not a directory: {}", dir.display()).into());
+    }
+
+    zkey_in_dir(&dir, version, depth)
+}
+
+/// Finds `{dir}/{version}/semaphore-{depth}.zkey`, then `{dir}/semaphore-{depth}.zkey`
+///
+/// A file that resolves outside `dir`, e.g. through a symlink, is an error.
+fn zkey_in_dir(dir: &Path, version: &str, depth: u16) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    if !is_version_component(version) {
+        return Err(SemaphoreError::InvalidCircuitVersion(version.to_string()).into());
+    }
+
+    let root = dir.canonicalize()?;
+    let filename = format!("semaphore-{depth}.zkey");
+    for path in [dir.join(version).join(&filename), dir.join(&filename)] {
+        if !path.is_file() {
+            continue;
+        }
+        if !path.canonicalize()?.starts_with(&root) {
+            return Err(format!("{} is outside {}", path.display(), root.display()).into());
+        }
+        return Ok(Some(path));
+    }
+
+    Ok(None)
+}
+
+/// Whether a version is a single path component of ASCII letters, digits, `.`, `+` and `-`
+fn is_version_component(version: &str) -> bool {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-');
+    !version.is_empty() && version != "." && !version.contains("..") && version.chars().all(allowed)
+}
+
 /// Download zkey from artifacts: https://snark-artifacts.pse.dev/
+///
+/// A zkey found in the `SEMAPHORE_ARTIFACTS_DIR` directory is used without downloading.
 pub fn download_zkey(depth: u16) -> Result<String, Box<dyn Error>> {
-    let version = "4.13.0";
-    let base_url = format!("https://snark-artifacts.pse.dev/semaphore/{version}/");
-    let filename = format!("semaphore-{depth}.zkey");
-    let dest_filename = format!("semaphore-{version}-{depth}.zkey");
+    download_zkey_version(depth, &CircuitVersion::Latest)
+}
+
+/// Download the zkey of a circuit version from artifacts
+///
+/// A zkey found in the `SEMAPHORE_ARTIFACTS_DIR` directory, or embedded with the
+/// `bundled-artifacts` feature, is used without downloading. Without the `network` feature,
+/// other zkeys are a [`SemaphoreError::NetworkingDisabled`] error.
+///
+/// The zkey is cached in the temp dir under a name namespaced by the crate version, the
+/// artifacts version and the URL, so processes using different artifacts never share a file.
+/// A lock file next to it makes concurrent processes wait for a single download, and is
+/// removed once the zkey is in place.
+pub fn download_zkey_version(
+    depth: u16,
+    version: &CircuitVersion,
+) -> Result<String, Box<dyn Error>> {
+    let version = version.as_str();
+    if let Some(zkey_path) = local_zkey(version, depth)? {
+        return Ok(zkey_path.to_string_lossy().into_owned());
+    }
+
+    #[cfg(feature = "bundled-artifacts")]
+    if let Some(zkey) = crate::bundled::zkey(version, depth) {
+        return write_bundled_zkey(version, depth, &zkey);
+    }
+
+    cached_download(version, depth)
+}
+
+/// Downloads a zkey into the temp dir, unless it is already there
+#[cfg(feature = "network")]
+fn cached_download(version: &str, depth: u16) -> Result<String, Box<dyn Error>> {
+    let url = format!("{}/{version}/semaphore-{depth}.zkey", artifacts_base_url());
+    let url_hash = hex::encode(&keccak256(url.as_bytes())[..8]);
+    let dest_filename = format!(
+        "semaphore-rs-{}-{version}-{depth}-{url_hash}.zkey",
+        env!("CARGO_PKG_VERSION")
+    );
+    let out_dir = std::env::temp_dir();
+    let dest_path = out_dir.join(&dest_filename);
+    if !dest_path.exists() {
+        // Only one thread or process downloads at a time, the others find the file once it is complete
+        let lock_path = out_dir.join(format!("{dest_filename}.lock"));
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)?;
+        lock.lock()?;
+        if !dest_path.exists() {
+            let part_path = out_dir.join(format!("{dest_filename}.part"));
+            download(&url, &part_path)?;
+            std::fs::rename(&part_path, &dest_path)?;
+            // Still locked: waiters and later callers find the zkey, so none needs this lock
+            // file again. A failed download keeps it, for the next caller to retry alone.
+            let _ = std::fs::remove_file(&lock_path);
+        }
+    }
+    Ok(dest_path.to_string_lossy().into_owned())
+}
+
+/// Networking is compiled out
+#[cfg(not(feature = "network"))]
+fn cached_download(_version: &str, _depth: u16) -> Result<String, Box<dyn Error>> {
+    Err(SemaphoreError::NetworkingDisabled.into())
+}
+
+/// Writes an embedded zkey to the temp dir, as the prover reads zkeys from files
+#[cfg(feature = "bundled-artifacts")]
+fn write_bundled_zkey(version: &str, depth: u16, zkey: &[u8]) -> Result<String, Box<dyn Error>> {
+    let dest_filename = format!(
+        "semaphore-rs-{}-{version}-{depth}-bundled.zkey",
+        env!("CARGO_PKG_VERSION")
+    );
     let out_dir = std::env::temp_dir();
-    let dest_path = out_dir.join(dest_filename.clone());
+    let dest_path = out_dir.join(&dest_filename);
     if !dest_path.exists() {
-        let url = format!("{base_url}{filename}");
-        let client = Client::new();
-        let mut resp = client.get(&url).send()?.error_for_status()?;
-        let mut out = File::create(&dest_path)?;
-        copy(&mut resp, &mut out)?;
+        let part_path = out_dir.join(format!("{dest_filename}.{}.part", std::process::id()));
+        std::fs::write(&part_path, zkey)?;
+        std::fs::rename(&part_path, &dest_path)?;
     }
     Ok(dest_path.to_string_lossy().into_owned())
 }
+
+/// Number of attempts to download an artifact before giving up
+#[cfg(feature = "network")]
+const DOWNLOAD_ATTEMPTS: usize = 3;
+
+/// Downloads a file into `part_path`, retrying and resuming interrupted downloads
+///
+/// A part file left by a failed attempt, or a previous process, is resumed with an HTTP range
+/// request when the server supports it, and downloaded again from the start otherwise.
+#[cfg(feature = "network")]
+fn download(url: &str, part_path: &Path) -> Result<(), Box<dyn Error>> {
+    let client = Client::new();
+    let mut result = Ok(());
+    for _ in 0..DOWNLOAD_ATTEMPTS {
+        result = download_part(&client, url, part_path);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
+/// Downloads the rest of a file, from the current length of `part_path`
+#[cfg(feature = "network")]
+fn download_part(client: &Client, url: &str, part_path: &Path) -> Result<(), Box<dyn Error>> {
+    let offset = std::fs::metadata(part_path).map_or(0, |m| m.len());
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={offset}-"));
+    }
+
+    let resp = request.send()?;
+    if resp.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // The part file does not match the remote file, start over
+        std::fs::remove_file(part_path)?;
+        return Err(format!("Cannot resume the download of {url}").into());
+    }
+    let mut resp = resp.error_for_status()?;
+
+    let mut out = if resp.status() == StatusCode::PARTIAL_CONTENT {
+        OpenOptions::new().append(true).open(part_path)?
+    } else {
+        File::create(part_path)?
+    };
+    let bytes = copy(&mut resp, &mut out)?;
+    crate::telemetry::downloaded(bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::{Identity, PublicKey};
+    use num_traits::{One, Zero};
+
+    #[test]
+    fn test_hash() {
+        let message = to_big_uint(&"Hello world".to_string());
+        let bytes = hash_bytes(&message.to_bytes_be());
+
+        assert_eq!(bytes[0], 0);
+        assert_eq!(hash(message.clone()), BigUint::from_bytes_be(&bytes));
+        assert_eq!(
+            hash(message.clone()),
+            BigUint::from_bytes_be(&keccak256(message.to_bytes_be())) >> 8
+        );
+    }
+
+    #[test]
+    fn test_hash_numeric() {
+        // keccak256(toBeHex(1, 32)) >> 8
+        let expected = BigUint::parse_bytes(
+            b"b10e2d527612073b26eecdfd717e6a320cf44b4afac2b0732d9fcbe2b7fa0c",
+            16,
+        )
+        .unwrap();
+
+        assert_eq!(hash(BigUint::from(1u8)), expected);
+    }
+
+    #[test]
+    fn test_scalar_bytes() {
+        let identity = Identity::new(b"secret");
+        let bytes = scalar_to_bytes(identity.secret_scalar());
+        let scalar = scalar_from_bytes(&bytes).unwrap();
+
+        assert_eq!(&scalar, identity.secret_scalar());
+        assert_eq!(
+            &PublicKey::from_scalar(&scalar).commitment(),
+            identity.commitment()
+        );
+
+        let mut modulus = [0u8; 32];
+        modulus.copy_from_slice(&Fr::MODULUS.to_bytes_le());
+        assert_eq!(
+            scalar_from_bytes(&modulus),
+            Err(SemaphoreError::NonCanonicalScalar)
+        );
+        assert_eq!(
+            scalar_from_bytes(&[0xff; 32]),
+            Err(SemaphoreError::NonCanonicalScalar)
+        );
+        modulus[0] -= 1;
+        assert_eq!(scalar_from_bytes(&modulus), Ok(-Fr::from(1u8)));
+    }
+
+    #[test]
+    fn test_hash_mode() {
+        let value = BigUint::from(1u8);
+        assert_eq!(HashMode::default(), HashMode::Keccak);
+        assert_eq!(HashMode::Keccak.hash(&value), hash(value.clone()));
+        assert_eq!(
+            HashMode::Poseidon.hash(&value),
+            BigUint::from(
+                poseidon(&[Fq::from(0u8), Fq::from(1u8)])
+                    .unwrap()
+                    .into_bigint()
+            )
+        );
+        assert_eq!(
+            HashMode::Poseidon.hash(&(BigUint::from(2u8) << 128 | BigUint::from(3u8))),
+            BigUint::from(
+                poseidon(&[Fq::from(2u8), Fq::from(3u8)])
+                    .unwrap()
+                    .into_bigint()
+            )
+        );
+        assert_ne!(
+            HashMode::Poseidon.hash(&value),
+            HashMode::Keccak.hash(&value)
+        );
+    }
+
+    #[test]
+    fn test_poseidon() {
+        let inputs: Vec<Fq> = (1..=5u8).map(Fq::from).collect();
+
+        // circomlibjs poseidon2, poseidon3 and poseidon5 of [1, 2, ...]
+        for (arity, expected) in [
+            (
+                2,
+                "7853200120776062878684798364095072458815029376092732009249414926327459813530",
+            ),
+            (
+                3,
+                "6542985608222806190361240322586112750744169038454362455181422643027100751666",
+            ),
+            (
+                5,
+                "6183221330272524995739186171720101788151706631170188140075976616310159254464",
+            ),
+        ] {
+            let output = poseidon(&inputs[..arity]).unwrap();
+            assert_eq!(BigUint::from(output.into_bigint()).to_string(), expected);
+        }
+
+        assert!(poseidon(&[Fq::from(1u8); 12]).is_ok());
+        assert_eq!(
+            poseidon(&[]),
+            Err(SemaphoreError::UnsupportedPoseidonArity(0))
+        );
+        assert_eq!(
+            poseidon(&[Fq::from(1u8); 13]),
+            Err(SemaphoreError::UnsupportedPoseidonArity(13))
+        );
+    }
+
+    #[test]
+    fn test_biguint_to_be_32() {
+        let mut expected = [0u8; 32];
+        expected[31] = 1;
+        assert_eq!(biguint_to_be_32(&BigUint::from(1u8)), Ok(expected));
+        assert_eq!(biguint_to_be_32(&BigUint::zero()), Ok([0u8; 32]));
+
+        let max = (BigUint::one() << 256) - 1u8;
+        assert_eq!(biguint_to_be_32(&max), Ok([0xff; 32]));
+        assert_eq!(
+            biguint_to_be_32(&(max + 1u8)),
+            Err(SemaphoreError::InputSizeExceeded(33))
+        );
+    }
+
+    #[test]
+    fn test_element_biguint_round_trip() {
+        let modulus = BigUint::from(Fq::MODULUS);
+        let values = [
+            BigUint::zero(),
+            BigUint::one(),
+            BigUint::from(0x0102u16),
+            &modulus - 1u8,
+            modulus.clone(),
+            (BigUint::one() << 256) - 1u8,
+        ];
+
+        for value in values {
+            let element = element_from_biguint(&value).unwrap();
+            assert_eq!(element_to_biguint(&element), value);
+        }
+
+        let element = element_from_biguint(&BigUint::from(0x0102u16)).unwrap();
+        assert_eq!(element[..3], [0x02, 0x01, 0x00]);
+        assert_eq!(
+            element_from_biguint(&(&modulus - 1u8)).unwrap(),
+            to_element(-Fq::from(1u8))
+        );
+        assert_eq!(
+            element_from_biguint(&(BigUint::one() << 256)),
+            Err(SemaphoreError::InputSizeExceeded(33))
+        );
+    }
+
+    #[test]
+    fn test_circuit_version_pinned() {
+        for (version, name) in [
+            ("4.0.0", "4.0.0"),
+            ("v4.0.0", "4.0.0"),
+            ("4.1.0-rc.1+b2", "4.1.0-rc.1+b2"),
+        ] {
+            assert_eq!(CircuitVersion::pinned(version).unwrap().as_str(), name);
+        }
+        for version in [
+            "", "v", ".", "..", "../..", "4.0.0/..", "/etc", "4.0.0\\x", "4..0", "4.0 0",
+        ] {
+            assert_eq!(
+                CircuitVersion::pinned(version),
+                Err(SemaphoreError::InvalidCircuitVersion(version.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_zkey_in_dir_traversal() {
+        let base =
+            std::env::temp_dir().join(format!("semaphore-rs-traversal-{}", std::process::id()));
+        let dir = base.join("artifacts");
+        std::fs::create_dir_all(dir.join("4.0.0")).unwrap();
+        std::fs::write(base.join("semaphore-10.zkey"), b"outside").unwrap();
+        std::fs::write(dir.join("4.0.0/semaphore-10.zkey"), b"inside").unwrap();
+
+        let inside = zkey_in_dir(&dir, "4.0.0", 10).unwrap();
+        let missing = zkey_in_dir(&dir, "4.0.0", 11).unwrap();
+        let traversal = ["..", "4.0.0/../..", "/tmp"].map(|version| zkey_in_dir(&dir, version, 10));
+        #[cfg(unix)]
+        let symlink = {
+            std::os::unix::fs::symlink(
+                base.join("semaphore-10.zkey"),
+                dir.join("semaphore-12.zkey"),
+            )
+            .unwrap();
+            zkey_in_dir(&dir, "4.0.0", 12)
+        };
+        std::fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(inside, Some(dir.join("4.0.0/semaphore-10.zkey")));
+        assert_eq!(missing, None);
+        for result in traversal {
+            assert!(result.is_err());
+        }
+        #[cfg(unix)]
+        assert!(symlink.unwrap_err().to_string().contains("is outside"));
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_hash_to_string() {
+        let message = to_big_uint(&"Hello world".to_string());
+
+        assert_eq!(hash_to_string(message.clone()), hash(message).to_string());
+    }
+
+    #[test]
+    fn test_verifying_key_fingerprint() {
+        let depth_10 = download_zkey(10).unwrap();
+        let depth_11 = download_zkey(11).unwrap();
+
+        let fingerprint_10 = verifying_key_fingerprint(Path::new(&depth_10)).unwrap();
+        let fingerprint_11 = verifying_key_fingerprint(Path::new(&depth_11)).unwrap();
+
+        assert_eq!(fingerprint_10.len(), 16);
+        assert_ne!(fingerprint_10, fingerprint_11);
+        assert_eq!(
+            verifying_key_fingerprint(Path::new(&depth_10)).unwrap(),
+            fingerprint_10
+        );
+    }
+
+    #[test]
+    fn test_verifying_key_fingerprint_invalid_zkey() {
+        let not_a_zkey = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/artifacts/semaphore-12.zkey"
+        );
+
+        assert!(matches!(
+            verifying_key_fingerprint(Path::new(not_a_zkey)),
+            Err(SemaphoreError::VerifyingKeyUnavailable(_))
+        ));
+        assert!(matches!(
+            verifying_key_fingerprint(Path::new("missing.zkey")),
+            Err(SemaphoreError::VerifyingKeyUnavailable(_))
+        ));
+    }
+}
@@ -1,135 +1,323 @@
-use circom_prover::graph;
+//! Witness calculation
+//!
+//! The witness graphs are embedded zstd-compressed (about 4.3 MB instead of 10.1 MB for
+//! the 32 depths) and decompressed on first use of each depth.
+
+use crate::{error::SemaphoreError, proof::is_supported_depth};
+use anyhow::anyhow;
+use ruzstd::decoding::StreamingDecoder;
+use std::{collections::HashMap, io::Read, sync::OnceLock};
+
+/// Embeds a compressed witness graph and defines its witness function
+macro_rules! graph {
+    ($name:ident, $path:expr) => {
+        fn $name(json_input: &str) -> anyhow::Result<Vec<u8>> {
+            static COMPRESSED: &[u8] = include_bytes!($path);
+            static GRAPH: OnceLock<Vec<u8>> = OnceLock::new();
+
+            let graph = GRAPH.get_or_init(|| decompress(COMPRESSED));
+            circom_witnesscalc::calc_witness(json_input, graph).map_err(|e| anyhow!("{}", e))
+        }
+    };
+}
+
+/// Decompresses an embedded witness graph
+fn decompress(compressed: &[u8]) -> Vec<u8> {
+    let mut decoder =
+        StreamingDecoder::new(compressed).expect("Failed to decompress the witness graph");
+    let mut graph = Vec::new();
+    decoder
+        .read_to_end(&mut graph)
+        .expect("Failed to decompress the witness graph");
+    graph
+}
+
+/// Calculates the witness of circuit inputs, e.g. from [`crate::proof::Proof::build_inputs`]
+///
+/// Returns the witness in the snarkjs `.wtns` format, for debugging or another prover.
+pub fn calculate_witness(
+    inputs: &HashMap<String, Vec<String>>,
+    depth: u16,
+) -> Result<Vec<u8>, SemaphoreError> {
+    if !is_supported_depth(depth) {
+        return Err(SemaphoreError::TreeDepthOutOfRange(depth));
+    }
+
+    let json = serde_json::to_string(inputs)
+        .map_err(|e| SemaphoreError::SerializationError(e.to_string()))?;
+    dispatch_witness(depth)(&json)
+        .map_err(|e| SemaphoreError::WitnessCalculationFailed(e.to_string()))
+}
 
 pub fn dispatch_witness(depth: u16) -> fn(&str) -> anyhow::Result<Vec<u8>> {
     match depth {
         1_u16 => {
-            graph!(semaphore1, "../witness_graph/semaphore-1.bin");
+            graph!(semaphore1_witness, "../witness_graph/semaphore-1.bin.zst");
             semaphore1_witness
         }
         2_u16 => {
-            graph!(semaphore2, "../witness_graph/semaphore-2.bin");
+            graph!(semaphore2_witness, "../witness_graph/semaphore-2.bin.zst");
             semaphore2_witness
         }
         3_u16 => {
-            graph!(semaphore3, "../witness_graph/semaphore-3.bin");
+            graph!(semaphore3_witness, "../witness_graph/semaphore-3.bin.zst");
             semaphore3_witness
         }
         4_u16 => {
-            graph!(semaphore4, "../witness_graph/semaphore-4.bin");
+            graph!(semaphore4_witness, "../witness_graph/semaphore-4.bin.zst");
             semaphore4_witness
         }
         5_u16 => {
-            graph!(semaphore5, "../witness_graph/semaphore-5.bin");
+            graph!(semaphore5_witness, "../witness_graph/semaphore-5.bin.zst");
             semaphore5_witness
         }
         6_u16 => {
-            graph!(semaphore6, "../witness_graph/semaphore-6.bin");
+            graph!(semaphore6_witness, "../witness_graph/semaphore-6.bin.zst");
             semaphore6_witness
         }
         7_u16 => {
-            graph!(semaphore7, "../witness_graph/semaphore-7.bin");
+            graph!(semaphore7_witness, "../witness_graph/semaphore-7.bin.zst");
             semaphore7_witness
         }
         8_u16 => {
-            graph!(semaphore8, "../witness_graph/semaphore-8.bin");
+            graph!(semaphore8_witness, "../witness_graph/semaphore-8.bin.zst");
             semaphore8_witness
         }
         9_u16 => {
-            graph!(semaphore9, "../witness_graph/semaphore-9.bin");
+            graph!(semaphore9_witness, "../witness_graph/semaphore-9.bin.zst");
             semaphore9_witness
         }
         10_u16 => {
-            graph!(semaphore10, "../witness_graph/semaphore-10.bin");
+            graph!(semaphore10_witness, "../witness_graph/semaphore-10.bin.zst");
             semaphore10_witness
         }
         11_u16 => {
-            graph!(semaphore11, "../witness_graph/semaphore-11.bin");
+            graph!(semaphore11_witness, "../witness_graph/semaphore-11.bin.zst");
             semaphore11_witness
         }
         12_u16 => {
-            graph!(semaphore12, "../witness_graph/semaphore-12.bin");
+            graph!(semaphore12_witness, "../witness_graph/semaphore-12.bin.zst");
             semaphore12_witness
         }
         13_u16 => {
-            graph!(semaphore13, "../witness_graph/semaphore-13.bin");
+            graph!(semaphore13_witness, "../witness_graph/semaphore-13.bin.zst");
             semaphore13_witness
         }
         14_u16 => {
-            graph!(semaphore14, "../witness_graph/semaphore-14.bin");
+            graph!(semaphore14_witness, "../witness_graph/semaphore-14.bin.zst");
             semaphore14_witness
         }
         15_u16 => {
-            graph!(semaphore15, "../witness_graph/semaphore-15.bin");
+            graph!(semaphore15_witness, "../witness_graph/semaphore-15.bin.zst");
             semaphore15_witness
         }
         16_u16 => {
-            graph!(semaphore16, "../witness_graph/semaphore-16.bin");
+            graph!(semaphore16_witness, "../witness_graph/semaphore-16.bin.zst");
             semaphore16_witness
         }
         17_u16 => {
-            graph!(semaphore17, "../witness_graph/semaphore-17.bin");
+            graph!(semaphore17_witness, "../witness_graph/semaphore-17.bin.zst");
             semaphore17_witness
         }
         18_u16 => {
-            graph!(semaphore18, "../witness_graph/semaphore-18.bin");
+            graph!(semaphore18_witness, "../witness_graph/semaphore-18.bin.zst");
             semaphore18_witness
         }
         19_u16 => {
-            graph!(semaphore19, "../witness_graph/semaphore-19.bin");
+            graph!(semaphore19_witness, "../witness_graph/semaphore-19.bin.zst");
             semaphore19_witness
         }
         20_u16 => {
-            graph!(semaphore20, "../witness_graph/semaphore-20.bin");
+            graph!(semaphore20_witness, "../witness_graph/semaphore-20.bin.zst");
             semaphore20_witness
         }
         21_u16 => {
-            graph!(semaphore21, "../witness_graph/semaphore-21.bin");
+            graph!(semaphore21_witness, "../witness_graph/semaphore-21.bin.zst");
             semaphore21_witness
         }
         22_u16 => {
-            graph!(semaphore22, "../witness_graph/semaphore-22.bin");
+            graph!(semaphore22_witness, "../witness_graph/semaphore-22.bin.zst");
             semaphore22_witness
         }
         23_u16 => {
-            graph!(semaphore23, "../witness_graph/semaphore-23.bin");
+            graph!(semaphore23_witness, "../witness_graph/semaphore-23.bin.zst");
             semaphore23_witness
         }
         24_u16 => {
-            graph!(semaphore24, "../witness_graph/semaphore-24.bin");
+            graph!(semaphore24_witness, "../witness_graph/semaphore-24.bin.zst");
             semaphore24_witness
         }
         25_u16 => {
-            graph!(semaphore25, "../witness_graph/semaphore-25.bin");
+            graph!(semaphore25_witness, "../witness_graph/semaphore-25.bin.zst");
             semaphore25_witness
         }
         26_u16 => {
-            graph!(semaphore26, "../witness_graph/semaphore-26.bin");
+            graph!(semaphore26_witness, "../witness_graph/semaphore-26.bin.zst");
             semaphore26_witness
         }
         27_u16 => {
-            graph!(semaphore27, "../witness_graph/semaphore-27.bin");
+            graph!(semaphore27_witness, "../witness_graph/semaphore-27.bin.zst");
             semaphore27_witness
         }
         28_u16 => {
-            graph!(semaphore28, "../witness_graph/semaphore-28.bin");
+            graph!(semaphore28_witness, "../witness_graph/semaphore-28.bin.zst");
             semaphore28_witness
         }
         29_u16 => {
-            graph!(semaphore29, "../witness_graph/semaphore-29.bin");
+            graph!(semaphore29_witness, "../witness_graph/semaphore-29.bin.zst");
             semaphore29_witness
         }
         30_u16 => {
-            graph!(semaphore30, "../witness_graph/semaphore-30.bin");
+            graph!(semaphore30_witness, "../witness_graph/semaphore-30.bin.zst");
             semaphore30_witness
         }
         31_u16 => {
-            graph!(semaphore31, "../witness_graph/semaphore-31.bin");
+            graph!(semaphore31_witness, "../witness_graph/semaphore-31.bin.zst");
             semaphore31_witness
         }
         32_u16 => {
-            graph!(semaphore32, "../witness_graph/semaphore-32.bin");
+            graph!(semaphore32_witness, "../witness_graph/semaphore-32.bin.zst");
             semaphore32_witness
         }
         _ => panic!("Unsupported depth"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        group::Group,
+        identity::Identity,
+        proof::{GroupOrMerkleProof, Proof},
+        utils::{HashMode, to_element},
+    };
+    use num_bigint::BigUint;
+
+    /// Computes a witness and returns its signals
+    fn witness_signals(
+        identity: &Identity,
+        group: &GroupOrMerkleProof,
+        depth: u16,
+    ) -> impl Fn(usize) -> BigUint + use<> {
+        let inputs = Proof::build_inputs(identity, group, "message", "scope", depth).unwrap();
+        inputs_signals(&inputs, depth)
+    }
+
+    /// Computes the witness of circuit inputs and returns its signals
+    fn inputs_signals(
+        inputs: &HashMap<String, Vec<String>>,
+        depth: u16,
+    ) -> impl Fn(usize) -> BigUint + use<> {
+        let witness = calculate_witness(inputs, depth).unwrap();
+
+        // wtns: file header, header section (n8, prime, witness count), witness section
+        let n8 = u32::from_le_bytes(witness[24..28].try_into().unwrap()) as usize;
+        let values = witness[28 + n8 + 4 + 12..].to_vec();
+        move |i: usize| BigUint::from_bytes_le(&values[i * n8..(i + 1) * n8])
+    }
+
+    #[test]
+    fn test_compressed_graph_witness() {
+        let identity = Identity::new("secret".as_bytes());
+        let group = Group::new(&[[1; 32], [2; 32], to_element(*identity.commitment())]).unwrap();
+        let group = GroupOrMerkleProof::Group(group);
+        let signals =
+            Proof::compute_public_signals(&identity, &group, "message", "scope", 10).unwrap();
+
+        let signal = witness_signals(&identity, &group, 10);
+
+        // The outputs follow the constant 1: merkle root and nullifier
+        assert_eq!(signal(0), BigUint::from(1u8));
+        assert_eq!(signal(1), signals.merkle_tree_root);
+        assert_eq!(signal(2), signals.nullifier);
+    }
+
+    #[test]
+    fn test_poseidon_hash_mode_witness() {
+        let identity = Identity::new("secret".as_bytes());
+        let group = Group::new(&[[1; 32], [2; 32], to_element(*identity.commitment())]).unwrap();
+        let group = GroupOrMerkleProof::Group(group);
+        let signals = Proof::compute_public_signals_with_mode(
+            &identity,
+            &group,
+            "message",
+            "scope",
+            10,
+            HashMode::Poseidon,
+        )
+        .unwrap();
+
+        let inputs = Proof::build_inputs_with_mode(
+            &identity,
+            &group,
+            "message",
+            "scope",
+            10,
+            HashMode::Poseidon,
+        )
+        .unwrap();
+        let signal = inputs_signals(&inputs, 10);
+
+        // The circuit nullifier is computed from the Poseidon scope hash
+        assert_eq!(signal(2), signals.nullifier);
+    }
+
+    #[test]
+    fn test_root_at_depth_matches_circuit() {
+        let identity = Identity::new("secret".as_bytes());
+        let group = Group::new(&[[1; 32], [2; 32], to_element(*identity.commitment())]).unwrap();
+
+        for depth in [2, 5, 10] {
+            let root = group.root_at_depth(depth).unwrap();
+            let signal =
+                witness_signals(&identity, &GroupOrMerkleProof::Group(group.clone()), depth);
+
+            assert_eq!(signal(1), BigUint::from_bytes_le(&root));
+        }
+    }
+
+    #[test]
+    fn test_1_member_smallest_depths() {
+        let identity = Identity::new("secret".as_bytes());
+        let commitment = to_element(*identity.commitment());
+        let group = GroupOrMerkleProof::Group(Group::new(&[commitment]).unwrap());
+
+        for depth in [1, 2] {
+            let signal = witness_signals(&identity, &group, depth);
+
+            // The root of a single-member tree is the member
+            assert_eq!(signal(1), BigUint::from_bytes_le(&commitment));
+        }
+    }
+
+    #[test]
+    fn test_calculate_witness() {
+        let identity = Identity::new("secret".as_bytes());
+        let group = Group::new(&[[1; 32], [2; 32], to_element(*identity.commitment())]).unwrap();
+        let group = GroupOrMerkleProof::Group(group);
+
+        for depth in [2, 10, 32] {
+            let inputs = Proof::build_inputs(&identity, &group, "message", "scope", depth).unwrap();
+            let witness = calculate_witness(&inputs, depth).unwrap();
+
+            // Each level of the Merkle proof adds 247 signals to the circuit
+            let signals = 1514 + 247 * depth as usize;
+
+            // Header (12 bytes), header section (12 + 4 + 32 + 4) and witness section (12 + 32 per signal)
+            assert_eq!(&witness[..4], b"wtns");
+            assert_eq!(witness.len(), 76 + 32 * signals, "depth {depth}");
+        }
+
+        let inputs = Proof::build_inputs(&identity, &group, "message", "scope", 10).unwrap();
+        assert_eq!(
+            calculate_witness(&inputs, 33),
+            Err(SemaphoreError::TreeDepthOutOfRange(33))
+        );
+        let malformed = HashMap::from([("secret".to_string(), vec!["secret".to_string()])]);
+        assert!(matches!(
+            calculate_witness(&malformed, 10),
+            Err(SemaphoreError::WitnessCalculationFailed(_))
+        ));
+    }
+}
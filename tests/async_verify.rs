@@ -0,0 +1,101 @@
+//! Async batch verification integration tests, with the `async` feature
+
+#![cfg(feature = "async")]
+
+// Proof generated from `Semaphore-js`
+const TREE_DEPTH: u16 = 10;
+const MERKLE_TREE_ROOT: &str =
+    "4990292586352433503726012711155167179034286198473030768981544541070532815155";
+const NULLIFIER: &str =
+    "17540473064543782218297133630279824063352907908315494138425986188962403570231";
+const MESSAGE: &str =
+    "32745724963520510550185023804391900974863477733501474067656557556163468591104";
+const SCOPE: &str = "37717653415819232215590989865455204849443869931268328771929128739472152723456";
+const POINTS: [&str; 8] = [
+    "2448901300518098096993075752654536134313649038239216706400667219963346227679",
+    "11383357624181217239434984412545229801919536849542936327488167664579097021171",
+    "4740704242184999702574958393302343834384154042177684026319208048433986938524",
+    "2103898499672759617084297744151588687300569178309824227315704845907524437637",
+    "18126651739688030584140960766793516019865850111238360168731489534891060767936",
+    "13293264290162772264887787723520088518667325866686508255341288441681546077334",
+    "13860303418198054644271827809984867757526756615344099647083475463061491185143",
+    "7750331146056656453454308267328134694500438800080743301030181391570997944788",
+];
+
+#[cfg(test)]
+mod async_verify {
+    use super::*;
+    use ark_bn254::Fr;
+    use ark_ff::PrimeField;
+    use num_bigint::BigUint;
+    use semaphore::{
+        error::SemaphoreError,
+        proof::{Proof, SemaphoreProof},
+    };
+    use std::str::FromStr;
+
+    fn semaphore_js_proof() -> SemaphoreProof {
+        SemaphoreProof {
+            merkle_tree_depth: TREE_DEPTH,
+            merkle_tree_root: BigUint::from_str(MERKLE_TREE_ROOT).unwrap(),
+            nullifier: BigUint::from_str(NULLIFIER).unwrap(),
+            message: BigUint::from_str(MESSAGE).unwrap(),
+            scope: BigUint::from_str(SCOPE).unwrap(),
+            points: POINTS.map(|p| BigUint::from_str(p).unwrap()),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn verify_proofs_async() {
+        let valid = semaphore_js_proof();
+        let mut invalid = valid.clone();
+        invalid.nullifier += 1u8;
+
+        let results = Proof::default()
+            .verify_proofs_async(vec![valid.clone(), invalid, valid])
+            .await;
+
+        assert_eq!(results, vec![Ok(true), Ok(false), Ok(true)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn verify_proofs_async_non_canonical() {
+        let valid = semaphore_js_proof();
+        let modulus = BigUint::from(Fr::MODULUS);
+        let mut shifted_root = valid.clone();
+        shifted_root.merkle_tree_root += &modulus;
+        let mut shifted_nullifier = valid.clone();
+        shifted_nullifier.nullifier += &modulus;
+
+        let results = Proof::default()
+            .verify_proofs_async(vec![shifted_root, shifted_nullifier, valid])
+            .await;
+
+        assert_eq!(
+            results,
+            vec![
+                Err(SemaphoreError::ProofFieldOutOfRange("merkle_tree_root")),
+                Err(SemaphoreError::ProofFieldOutOfRange("nullifier")),
+                Ok(true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_proofs_async_unsupported_depth() {
+        let mut unsupported = semaphore_js_proof();
+        unsupported.merkle_tree_depth = 33;
+
+        let results = Proof::default()
+            .verify_proofs_async(vec![unsupported])
+            .await;
+
+        assert_eq!(results, vec![Err(SemaphoreError::TreeDepthOutOfRange(33))]);
+        assert!(
+            Proof::default()
+                .verify_proofs_async(vec![])
+                .await
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! CLI integration tests
+//!
+//! Runs the `semaphore` binary end to end through its subcommands.
+
+#![cfg(feature = "cli")]
+
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+const PRIVATE_KEY: &str = "secret";
+const MEMBER1: &str = "100000000000000000000000000000";
+const MEMBER2: &str = "200000000000000000000000000000";
+const MESSAGE: &str = "Hello world";
+const SCOPE: &str = "Scope";
+const TREE_DEPTH: &str = "10";
+
+fn semaphore(args: &[&str]) -> (bool, String) {
+    semaphore_with_stdin(args, "")
+}
+
+/// Runs the binary with `stdin` as its standard input, and without `SEMAPHORE_PRIVATE_KEY`
+fn semaphore_with_stdin(args: &[&str], stdin: &str) -> (bool, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_semaphore"))
+        .args(args)
+        .env_remove("SEMAPHORE_PRIVATE_KEY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run the semaphore binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap().trim().to_string(),
+    )
+}
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("semaphore-cli-{}-{name}", std::process::id()));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn identity_new() {
+    let expected = semaphore::identity::Identity::new(PRIVATE_KEY.as_bytes())
+        .commitment()
+        .to_string();
+
+    // From the standard input, with the line ending of a shell
+    let (success, commitment) =
+        semaphore_with_stdin(&["identity", "new"], &format!("{PRIVATE_KEY}\n"));
+    assert!(success);
+    assert_eq!(commitment, expected);
+
+    // From a file
+    let key_path = temp_file("identity-key.txt", PRIVATE_KEY);
+    let (success, commitment) = semaphore(&[
+        "identity",
+        "new",
+        "--private-key-file",
+        key_path.to_str().unwrap(),
+    ]);
+    assert!(success);
+    assert_eq!(commitment, expected);
+
+    // From the environment
+    let output = Command::new(env!("CARGO_BIN_EXE_semaphore"))
+        .args(["identity", "new"])
+        .env("SEMAPHORE_PRIVATE_KEY", PRIVATE_KEY)
+        .output()
+        .expect("Failed to run the semaphore binary");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap().trim(), expected);
+
+    // Not from the arguments
+    let (success, _) = semaphore(&["identity", "new", PRIVATE_KEY]);
+    assert!(!success);
+    let (success, _) = semaphore(&["identity", "new"]);
+    assert!(!success);
+
+    fs::remove_file(key_path).unwrap();
+}
+
+#[test]
+fn group_add() {
+    let (success, group_json) = semaphore(&["group", "add", MEMBER1]);
+    assert!(success);
+
+    let group_path = temp_file("group-add.json", &group_json);
+    let (success, group_json) = semaphore(&[
+        "group",
+        "add",
+        "--group",
+        group_path.to_str().unwrap(),
+        MEMBER2,
+    ]);
+    assert!(success);
+
+    let group = semaphore::group::Group::import(&group_json).unwrap();
+    assert_eq!(group.size(), 2);
+
+    let (success, _) = semaphore(&["group", "add", "not-a-number"]);
+    assert!(!success);
+
+    fs::remove_file(group_path).unwrap();
+}
+
+#[test]
+fn proof_generate_and_verify() {
+    let (_, commitment) = semaphore_with_stdin(&["identity", "new"], PRIVATE_KEY);
+    let (_, group_json) = semaphore(&["group", "add", MEMBER1, MEMBER2, &commitment]);
+    let group_path = temp_file("proof-group.json", &group_json);
+    let key_path = temp_file("proof-key.txt", PRIVATE_KEY);
+
+    let (success, proof_json) = semaphore(&[
+        "proof",
+        "generate",
+        "--private-key-file",
+        key_path.to_str().unwrap(),
+        "--group",
+        group_path.to_str().unwrap(),
+        "--message",
+        MESSAGE,
+        "--scope",
+        SCOPE,
+        "--depth",
+        TREE_DEPTH,
+    ]);
+    assert!(success);
+
+    let proof_path = temp_file("proof.json", &proof_json);
+    let (success, _) = semaphore(&["proof", "verify", proof_path.to_str().unwrap()]);
+    assert!(success);
+
+    fs::remove_file(group_path).unwrap();
+    fs::remove_file(key_path).unwrap();
+    fs::remove_file(proof_path).unwrap();
+}
+
+#[test]
+fn proof_verify_unsupported_depth() {
+    let proof = semaphore::proof::SemaphoreProof {
+        merkle_tree_depth: 33,
+        merkle_tree_root: 1u8.into(),
+        message: 2u8.into(),
+        nullifier: 3u8.into(),
+        scope: 4u8.into(),
+        points: std::array::from_fn(|_| 0u8.into()),
+    };
+    let proof_path = temp_file("proof-depth-33.json", &proof.export().unwrap());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_semaphore"))
+        .args(["proof", "verify", proof_path.to_str().unwrap()])
+        .output()
+        .expect("Failed to run the semaphore binary");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    // An error exits with 1, a panic with 101
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!stderr.contains("panicked"), "{stderr}");
+
+    fs::remove_file(proof_path).unwrap();
+}
@@ -0,0 +1,262 @@
+//! Artifacts download integration tests
+//!
+//! These tests change the process-wide artifacts base URL and environment, so they live in their
+//! own test binary and take `BASE_URL_LOCK` while they are overridden.
+
+use std::sync::Mutex;
+
+#[cfg(feature = "network")]
+const ZKEY: &[u8] = b"semaphore zkey";
+
+static BASE_URL_LOCK: Mutex<()> = Mutex::new(());
+
+#[cfg(all(test, feature = "network"))]
+mod download {
+    use super::*;
+    use semaphore::utils::{
+        ARTIFACTS_DIR_ENV, CircuitVersion, download_zkey, download_zkey_version,
+        set_artifacts_base_url,
+    };
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::Arc,
+        thread,
+        time::Duration,
+    };
+
+    /// Serves `ZKEY` to any request, slowly, and records the requested paths
+    fn serve_zkey() -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let paths = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line.split_whitespace().nth(1).unwrap_or_default();
+                paths.lock().unwrap().push(path.to_string());
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+
+                thread::sleep(Duration::from_millis(200));
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    ZKEY.len()
+                )
+                .unwrap();
+                stream.write_all(ZKEY).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    /// Serves `ZKEY`, interrupting the first response halfway, and records the range headers
+    ///
+    /// Range requests get the rest of `ZKEY` if `range_support` is set, the whole file otherwise.
+    fn serve_interrupted(range_support: bool) -> (String, Arc<Mutex<Vec<Option<String>>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let ranges = requests.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut range = None;
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    if let Some(value) = line.to_lowercase().strip_prefix("range: bytes=") {
+                        range = Some(value.trim().to_string());
+                    }
+                    line.clear();
+                }
+                let first = ranges.lock().unwrap().is_empty();
+                ranges.lock().unwrap().push(range.clone());
+
+                let header = "Connection: close\r\n";
+                match range.filter(|_| range_support) {
+                    Some(range) => {
+                        let start: usize = range.trim_end_matches('-').parse().unwrap();
+                        write!(
+                            stream,
+                            "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {start}-{}/{}\r\nContent-Length: {}\r\n{header}\r\n",
+                            ZKEY.len() - 1,
+                            ZKEY.len(),
+                            ZKEY.len() - start,
+                        )
+                        .unwrap();
+                        stream.write_all(&ZKEY[start..]).unwrap();
+                    }
+                    None => {
+                        write!(
+                            stream,
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n{header}\r\n",
+                            ZKEY.len()
+                        )
+                        .unwrap();
+                        let len = if first { ZKEY.len() / 2 } else { ZKEY.len() };
+                        stream.write_all(&ZKEY[..len]).unwrap();
+                    }
+                }
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn remove_zkey(path: &str) {
+        // The lock file is removed with the download
+        assert!(!std::path::Path::new(&format!("{path}.lock")).exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn concurrent_downloads() {
+        let _guard = BASE_URL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let (url, requests) = serve_zkey();
+        set_artifacts_base_url(Some(&url));
+
+        let paths: Vec<String> = (0..2)
+            .map(|_| thread::spawn(|| download_zkey(10).unwrap()))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert_eq!(paths[0], paths[1]);
+        assert!(paths[0].contains(env!("CARGO_PKG_VERSION")));
+        assert_eq!(std::fs::read(&paths[0]).unwrap(), ZKEY);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        // Other artifacts are cached in another file
+        let (other_url, _) = serve_zkey();
+        set_artifacts_base_url(Some(&other_url));
+        let other_path = download_zkey(10).unwrap();
+        set_artifacts_base_url(None);
+
+        assert_ne!(other_path, paths[0]);
+
+        remove_zkey(&paths[0]);
+        remove_zkey(&other_path);
+    }
+
+    #[test]
+    fn pinned_circuit_version() {
+        let _guard = BASE_URL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let (url, requests) = serve_zkey();
+        set_artifacts_base_url(Some(&url));
+
+        let latest = download_zkey_version(10, &CircuitVersion::Latest).unwrap();
+        let pinned = download_zkey_version(10, &CircuitVersion::pinned("v4.0.0").unwrap()).unwrap();
+        set_artifacts_base_url(None);
+
+        assert_ne!(latest, pinned);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            ["/4.13.0/semaphore-10.zkey", "/4.0.0/semaphore-10.zkey"]
+        );
+
+        remove_zkey(&latest);
+        remove_zkey(&pinned);
+    }
+
+    #[test]
+    fn local_artifacts_dir() {
+        let _guard = BASE_URL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let (url, requests) = serve_zkey();
+        set_artifacts_base_url(Some(&url));
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/artifacts");
+        unsafe { std::env::set_var(ARTIFACTS_DIR_ENV, fixtures) };
+
+        let versioned = download_zkey(10).unwrap();
+        let flat = download_zkey(12).unwrap();
+        let downloaded = download_zkey(11).unwrap();
+
+        unsafe { std::env::set_var(ARTIFACTS_DIR_ENV, format!("{fixtures}/semaphore-12.zkey")) };
+        let not_a_dir = download_zkey(10);
+
+        unsafe { std::env::remove_var(ARTIFACTS_DIR_ENV) };
+        set_artifacts_base_url(None);
+
+        assert_eq!(versioned, format!("{fixtures}/4.13.0/semaphore-10.zkey"));
+        assert_eq!(flat, format!("{fixtures}/semaphore-12.zkey"));
+        assert_eq!(std::fs::read(&downloaded).unwrap(), ZKEY);
+        assert_eq!(*requests.lock().unwrap(), ["/4.13.0/semaphore-11.zkey"]);
+        assert!(not_a_dir.is_err());
+
+        remove_zkey(&downloaded);
+    }
+
+    #[test]
+    fn resume_interrupted_download() {
+        let _guard = BASE_URL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let half = format!("{}-", ZKEY.len() / 2);
+
+        for range_support in [true, false] {
+            let (url, requests) = serve_interrupted(range_support);
+            set_artifacts_base_url(Some(&url));
+            let path = download_zkey(10).unwrap();
+            set_artifacts_base_url(None);
+
+            assert_eq!(std::fs::read(&path).unwrap(), ZKEY);
+            assert_eq!(*requests.lock().unwrap(), [None, Some(half.clone())]);
+
+            remove_zkey(&path);
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "network")))]
+mod no_network {
+    use super::*;
+    use semaphore::{
+        error::SemaphoreError,
+        utils::{ARTIFACTS_DIR_ENV, download_zkey},
+    };
+
+    #[test]
+    fn networking_disabled() {
+        let _guard = BASE_URL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/artifacts");
+        unsafe { std::env::set_var(ARTIFACTS_DIR_ENV, fixtures) };
+
+        let local = download_zkey(10);
+        let missing = download_zkey(11);
+
+        unsafe { std::env::remove_var(ARTIFACTS_DIR_ENV) };
+
+        assert_eq!(
+            local.unwrap(),
+            format!("{fixtures}/4.13.0/semaphore-10.zkey")
+        );
+        assert_eq!(
+            missing.unwrap_err().downcast_ref::<SemaphoreError>(),
+            Some(&SemaphoreError::NetworkingDisabled)
+        );
+    }
+
+    /// The HTTP client is not linked at all, not only unused
+    #[test]
+    fn no_http_client_linked() {
+        let binary = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        // Reversed, so that the crate names are not in this binary as string literals
+        for reversed in ["tsewqer", "repyh", "slt_evitan", "lssnepo"] {
+            let name: String = reversed.chars().rev().collect();
+            assert!(
+                !binary
+                    .windows(name.len())
+                    .any(|window| window == name.as_bytes()),
+                "{name} is linked"
+            );
+        }
+    }
+}
@@ -175,11 +175,11 @@ mod group {
                 .collect::<Vec<_>>()
         );
 
-        assert_eq!(Group::verify_proof(&proof), true);
+        assert!(Group::verify_proof(&proof));
 
         let mut invalid_proof = proof.clone();
         invalid_proof.leaf = str_to_element(INDEX_LOOKUP_NON_EXISTING_MEMBER);
-        assert_eq!(Group::verify_proof(&invalid_proof), false);
+        assert!(!Group::verify_proof(&invalid_proof));
 
         assert!(group.generate_proof(MERKLE_PROOF_INVALID_INDEX).is_err());
     }
@@ -238,4 +238,36 @@ mod group {
                 .collect::<Vec<_>>()
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn merkle_proof_semaphore_js_json() {
+        use semaphore::utils::{export_merkle_proof, import_merkle_proof};
+
+        let json = format!(
+            r#"{{"root":"{}","leaf":"{}","index":{},"siblings":["{}","{}"]}}"#,
+            MERKLE_PROOF_ROOT_STR,
+            MERKLE_PROOF_LEAF,
+            MERKLE_PROOF_INDEX,
+            MERKLE_PROOF_SIBLINGS[0],
+            MERKLE_PROOF_SIBLINGS[1]
+        );
+
+        let elements: Vec<Element> = INITIAL_MEMBERS.iter().map(|s| str_to_element(s)).collect();
+        let group = Group::new(&elements).unwrap();
+        let proof = group.generate_proof(MERKLE_PROOF_INDEX).unwrap();
+
+        let imported = import_merkle_proof(&json).unwrap();
+        assert_eq!(imported, proof);
+        assert!(Group::verify_proof(&imported));
+
+        let exported: serde_json::Value =
+            serde_json::from_str(&export_merkle_proof(&proof)).unwrap();
+        assert_eq!(
+            exported,
+            serde_json::from_str::<serde_json::Value>(&json).unwrap()
+        );
+
+        assert!(import_merkle_proof(r#"{"root":"1","leaf":"2","index":0}"#).is_err());
+    }
 }
@@ -198,4 +198,33 @@ mod identity {
             SemaphoreError::SignaturePointNotOnCurve
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn signature_serde() {
+        let identity = Identity::new(&PRIVATE_KEY_BYTES);
+        let signature = identity.sign_message(&MESSAGE_BYTES).unwrap();
+
+        let json = serde_json::to_string(&signature).unwrap();
+        let deserialized: Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(signature, deserialized);
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["s"], SIGNATURE_S_STR);
+
+        let invalid_r = format!(r#"{{"r":"{}","s":"{}"}}"#, "ff".repeat(32), SIGNATURE_S_STR);
+        assert!(serde_json::from_str::<Signature>(&invalid_r).is_err());
+
+        // `s + l` satisfies the verification equation too, it is rejected rather than reduced
+        let s = num_bigint::BigUint::from(signature.s.into_bigint())
+            + num_bigint::BigUint::from(Fr::MODULUS);
+        let malleable = json.replace(SIGNATURE_S_STR, &s.to_string());
+        assert_ne!(malleable, json);
+        let error = serde_json::from_str::<Signature>(&malleable).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains(&SemaphoreError::MalleableSignature.to_string())
+        );
+    }
 }
@@ -0,0 +1,72 @@
+//! Identity derivation compatibility with semaphore-js
+//!
+//! The vectors of `fixtures/identity_vectors.json` are generated with the semaphore-js
+//! `Identity` by `script/identity_vectors.sh`. Private keys are hex encoded bytes.
+
+use ethers_core::utils::hex;
+use serde_json::Value;
+
+const VECTORS: &str = include_str!("fixtures/identity_vectors.json");
+
+/// Identity derived by semaphore-js
+struct Vector {
+    private_key: Vec<u8>,
+    secret_scalar: String,
+    public_key: (String, String),
+    commitment: String,
+}
+
+fn vectors() -> Vec<Vector> {
+    let json: Value = serde_json::from_str(VECTORS).unwrap();
+    let decimal = |value: &Value| value.as_str().unwrap().to_string();
+
+    json.as_array()
+        .unwrap()
+        .iter()
+        .map(|vector| Vector {
+            private_key: hex::decode(vector["privateKey"].as_str().unwrap()).unwrap(),
+            secret_scalar: decimal(&vector["secretScalar"]),
+            public_key: (
+                decimal(&vector["publicKey"][0]),
+                decimal(&vector["publicKey"][1]),
+            ),
+            commitment: decimal(&vector["commitment"]),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod identity_compat {
+    use super::*;
+    use semaphore::identity::Identity;
+
+    #[test]
+    fn semaphore_js_identities() {
+        let vectors = vectors();
+        assert!(vectors.len() >= 20);
+
+        for vector in vectors {
+            let identity = Identity::new(&vector.private_key);
+            let key = hex::encode(&vector.private_key);
+
+            assert_eq!(
+                identity.secret_scalar().to_string(),
+                vector.secret_scalar,
+                "secret scalar of {key}"
+            );
+            assert_eq!(
+                (
+                    identity.public_key().x().to_string(),
+                    identity.public_key().y().to_string()
+                ),
+                vector.public_key,
+                "public key of {key}"
+            );
+            assert_eq!(
+                identity.commitment().to_string(),
+                vector.commitment,
+                "commitment of {key}"
+            );
+        }
+    }
+}
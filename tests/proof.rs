@@ -0,0 +1,65 @@
+//! Proof module integration tests
+//!
+//! These tests change the process-wide artifacts base URL, so they live in their own test binary.
+
+// Proof generated from `Semaphore-js`
+const TREE_DEPTH: u16 = 10;
+const MERKLE_TREE_ROOT: &str =
+    "4990292586352433503726012711155167179034286198473030768981544541070532815155";
+const NULLIFIER: &str =
+    "17540473064543782218297133630279824063352907908315494138425986188962403570231";
+const MESSAGE: &str =
+    "32745724963520510550185023804391900974863477733501474067656557556163468591104";
+const SCOPE: &str = "37717653415819232215590989865455204849443869931268328771929128739472152723456";
+const POINTS: [&str; 8] = [
+    "2448901300518098096993075752654536134313649038239216706400667219963346227679",
+    "11383357624181217239434984412545229801919536849542936327488167664579097021171",
+    "4740704242184999702574958393302343834384154042177684026319208048433986938524",
+    "2103898499672759617084297744151588687300569178309824227315704845907524437637",
+    "18126651739688030584140960766793516019865850111238360168731489534891060767936",
+    "13293264290162772264887787723520088518667325866686508255341288441681546077334",
+    "13860303418198054644271827809984867757526756615344099647083475463061491185143",
+    "7750331146056656453454308267328134694500438800080743301030181391570997944788",
+];
+
+const UNREACHABLE_BASE_URL: &str = "http://unreachable.invalid";
+
+#[cfg(test)]
+mod proof {
+    use super::*;
+    use num_bigint::BigUint;
+    use semaphore::{
+        proof::{Proof, SemaphoreProof},
+        utils::set_artifacts_base_url,
+    };
+    use std::str::FromStr;
+
+    fn semaphore_js_proof() -> SemaphoreProof {
+        SemaphoreProof {
+            merkle_tree_depth: TREE_DEPTH,
+            merkle_tree_root: BigUint::from_str(MERKLE_TREE_ROOT).unwrap(),
+            nullifier: BigUint::from_str(NULLIFIER).unwrap(),
+            message: BigUint::from_str(MESSAGE).unwrap(),
+            scope: BigUint::from_str(SCOPE).unwrap(),
+            points: POINTS.map(|p| BigUint::from_str(p).unwrap()),
+        }
+    }
+
+    #[test]
+    fn verify_after_preload() {
+        Proof::preload(TREE_DEPTH).unwrap();
+
+        // Any download attempt would now fail
+        set_artifacts_base_url(Some(UNREACHABLE_BASE_URL));
+        let valid = Proof::default().verify(semaphore_js_proof());
+        set_artifacts_base_url(None);
+
+        assert!(valid);
+    }
+
+    #[test]
+    fn preload_invalid_tree_depth() {
+        assert!(Proof::preload(0).is_err());
+        assert!(Proof::preload(33).is_err());
+    }
+}